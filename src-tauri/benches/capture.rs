@@ -0,0 +1,110 @@
+// 截图延迟的基准测试：对比每次重新连接、重新分配缓冲的冷截图和预热截图
+//
+// 运行：cargo bench --bench capture
+// 假后端始终运行；设置了 DISPLAY 时还会测试 X11 后端，可以在 Xvfb 中运行：
+//   xvfb-run -s "-screen 0 3840x2160x24" cargo bench --bench capture
+
+use screenshot_lib::warm::{self, FakeGrabber, Grabber, WarmCapture};
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+const ITERATIONS: usize = 50;
+
+/// 假后端的屏幕尺寸
+const FAKE_SCREEN: (u32, u32) = (3840, 2160);
+
+struct Measurement {
+    median: Duration,
+    p95: Duration,
+}
+
+fn measure(mut f: impl FnMut()) -> Measurement {
+    let mut times: Vec<Duration> = (0..ITERATIONS)
+        .map(|_| {
+            let start = Instant::now();
+            f();
+            start.elapsed()
+        })
+        .collect();
+    times.sort();
+    Measurement {
+        median: times[ITERATIONS / 2],
+        p95: times[ITERATIONS * 95 / 100],
+    }
+}
+
+fn report(name: &str, cold: Measurement, warm: Measurement) {
+    println!(
+        "  {:<16} 冷截图 p50 {:>9.3?} p95 {:>9.3?} | 预热 p50 {:>9.3?} p95 {:>9.3?} | 加速 {:.1}x",
+        name,
+        cold.median,
+        cold.p95,
+        warm.median,
+        warm.p95,
+        cold.median.as_secs_f64() / warm.median.as_secs_f64(),
+    );
+}
+
+/// 测试的区域：(名称, x, y, width, height)
+fn regions(width: u32, height: u32) -> [(String, u32, u32, u32, u32); 2] {
+    [
+        (format!("整屏 {}x{}", width, height), 0, 0, width, height),
+        (
+            format!("区域 {}x{}", width / 4, height / 4),
+            width / 3,
+            height / 3,
+            width / 4,
+            height / 4,
+        ),
+    ]
+}
+
+/// 冷截图每次都创建后端和帧缓冲；预热截图复用同一个后端，并像应用中一样归还缓冲
+fn compare<G: Grabber>(width: u32, height: u32, mut connect: impl FnMut() -> G) {
+    let mut warm_capture = WarmCapture::new(connect());
+    for (name, x, y, w, h) in regions(width, height) {
+        let (x, y) = (x as i32, y as i32);
+        let cold = measure(|| {
+            let mut capture = WarmCapture::new(connect());
+            black_box(capture.capture(x, y, w, h).unwrap());
+        });
+        let warmed = measure(|| {
+            let image = warm_capture.capture(x, y, w, h).unwrap();
+            warm_capture.recycle(black_box(image));
+        });
+        report(&name, cold, warmed);
+    }
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+fn native_screen() -> Option<(u32, u32)> {
+    use x11rb::connection::Connection;
+
+    let (conn, screen_num) = x11rb::connect(None).ok()?;
+    let screen = &conn.setup().roots[screen_num];
+    Some((
+        screen.width_in_pixels.into(),
+        screen.height_in_pixels.into(),
+    ))
+}
+
+#[cfg(not(all(unix, not(target_os = "macos"))))]
+fn native_screen() -> Option<(u32, u32)> {
+    None
+}
+
+fn main() {
+    let (width, height) = FAKE_SCREEN;
+    let fake = FakeGrabber::new(width, height);
+    println!("假后端 {}x{}", width, height);
+    compare(width, height, || fake.clone());
+
+    match (native_screen(), warm::connect_native()) {
+        (Some((width, height)), Ok(_)) => {
+            println!("X11 后端 {}x{}", width, height);
+            compare(width, height, || warm::connect_native().unwrap());
+        }
+        (_, Err(e)) => println!("跳过原生后端: {}", e),
+        (None, Ok(_)) => println!("跳过原生后端: 无法获取屏幕尺寸"),
+    }
+}
@@ -0,0 +1,152 @@
+// 区域截图裁剪路径的基准测试：对比旧实现（复制整帧后再裁剪）和直接从帧中裁剪
+//
+// 运行：cargo bench --bench crop
+// 每种实现输出单次耗时的中位数，以及单次执行中的内存分配峰值和分配总量。
+
+use image::{imageops, RgbaImage};
+use rayon::prelude::*;
+use screenshot_lib::crop;
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::hint::black_box;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+/// 统计当前占用、峰值和累计分配字节数的分配器
+struct CountingAlloc;
+
+static CURRENT: AtomicUsize = AtomicUsize::new(0);
+static PEAK: AtomicUsize = AtomicUsize::new(0);
+static TOTAL: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc(layout);
+        if !ptr.is_null() {
+            let current = CURRENT.fetch_add(layout.size(), Ordering::Relaxed) + layout.size();
+            PEAK.fetch_max(current, Ordering::Relaxed);
+            TOTAL.fetch_add(layout.size(), Ordering::Relaxed);
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout);
+        CURRENT.fetch_sub(layout.size(), Ordering::Relaxed);
+    }
+}
+
+#[global_allocator]
+static ALLOC: CountingAlloc = CountingAlloc;
+
+const ITERATIONS: usize = 30;
+
+/// 5K 显示器的整屏帧
+const FRAME: (u32, u32) = (5120, 2880);
+
+/// 测试的裁剪区域：(名称, x, y, width, height)
+const REGIONS: [(&str, u32, u32, u32, u32); 3] = [
+    ("小区域 320x200", 2400, 1300, 320, 200),
+    ("半屏 2560x1440", 1280, 720, 2560, 1440),
+    ("整屏 5120x2880", 0, 0, 5120, 2880),
+];
+
+/// 旧的 capture_region：clone 整帧构造 RgbaImage，crop_imm 后 to_image，再转换为 RGB
+fn legacy_rgb(frame: &RgbaImage, x: u32, y: u32, width: u32, height: u32) -> Vec<u8> {
+    let full = RgbaImage::from_raw(frame.width(), frame.height(), frame.as_raw().clone()).unwrap();
+    let cropped = imageops::crop_imm(&full, x, y, width, height).to_image();
+    let mut rgb = vec![0u8; width as usize * height as usize * 3];
+    cropped
+        .as_raw()
+        .par_chunks_exact(4)
+        .zip(rgb.par_chunks_exact_mut(3))
+        .for_each(|(rgba, rgb)| rgb.copy_from_slice(&rgba[..3]));
+    rgb
+}
+
+/// 旧的 capture_and_copy_region：to_vec 整帧，crop_imm 后 to_image，复制到剪贴板前再 to_vec
+fn legacy_rgba(frame: &RgbaImage, x: u32, y: u32, width: u32, height: u32) -> Vec<u8> {
+    let full = RgbaImage::from_raw(frame.width(), frame.height(), frame.as_raw().to_vec()).unwrap();
+    let cropped = imageops::crop_imm(&full, x, y, width, height).to_image();
+    cropped.as_raw().to_vec()
+}
+
+struct Measurement {
+    median: Duration,
+    peak: usize,
+    total: usize,
+}
+
+fn measure<T>(mut f: impl FnMut() -> T) -> Measurement {
+    // 预热，避免线程池初始化计入结果
+    black_box(f());
+
+    let base = CURRENT.load(Ordering::Relaxed);
+    PEAK.store(base, Ordering::Relaxed);
+    TOTAL.store(0, Ordering::Relaxed);
+    black_box(f());
+    let peak = PEAK.load(Ordering::Relaxed) - base;
+    let total = TOTAL.load(Ordering::Relaxed);
+
+    let mut times: Vec<Duration> = (0..ITERATIONS)
+        .map(|_| {
+            let start = Instant::now();
+            black_box(f());
+            start.elapsed()
+        })
+        .collect();
+    times.sort();
+    Measurement {
+        median: times[ITERATIONS / 2],
+        peak,
+        total,
+    }
+}
+
+fn mib(bytes: usize) -> f64 {
+    bytes as f64 / 1024.0 / 1024.0
+}
+
+fn report(name: &str, legacy: Measurement, direct: Measurement) {
+    println!(
+        "  {:<10} 旧实现 {:>9.3?} 峰值 {:>7.2} MiB 累计 {:>7.2} MiB | 直接裁剪 {:>9.3?} 峰值 {:>7.2} MiB 累计 {:>7.2} MiB | 加速 {:.1}x",
+        name,
+        legacy.median,
+        mib(legacy.peak),
+        mib(legacy.total),
+        direct.median,
+        mib(direct.peak),
+        mib(direct.total),
+        legacy.median.as_secs_f64() / direct.median.as_secs_f64(),
+    );
+}
+
+fn main() {
+    let (width, height) = FRAME;
+    let frame = RgbaImage::from_fn(width, height, |x, y| {
+        image::Rgba([x as u8, y as u8, (x ^ y) as u8, 255])
+    });
+    println!(
+        "整屏帧 {}x{} ({:.2} MiB)",
+        width,
+        height,
+        mib(frame.as_raw().len())
+    );
+
+    for (name, x, y, w, h) in REGIONS {
+        assert_eq!(
+            legacy_rgb(&frame, x, y, w, h),
+            crop::crop_rgb(&frame, x, y, w, h).unwrap()
+        );
+        println!("{}", name);
+        report(
+            "RGB",
+            measure(|| legacy_rgb(&frame, x, y, w, h)),
+            measure(|| crop::crop_rgb(&frame, x, y, w, h).unwrap()),
+        );
+        report(
+            "RGBA",
+            measure(|| legacy_rgba(&frame, x, y, w, h)),
+            measure(|| crop::crop_rgba(&frame, x, y, w, h).unwrap()),
+        );
+    }
+}
@@ -0,0 +1,347 @@
+use crate::hooks;
+use crate::notifications;
+use crate::screenshot::{self, CapturedImage, SavedScreenshot};
+use crate::settings;
+use crate::timings::CaptureTimings;
+use crate::upload;
+use log::{error, info, warn};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::time::Duration;
+use tauri::{command, AppHandle, Emitter};
+use tauri_plugin_notification::NotificationExt;
+use tauri_plugin_opener::OpenerExt;
+
+/// 动作链执行完毕后发送的事件，负载为 `ChainReport`
+pub const CAPTURE_ACTIONS_FINISHED_EVENT: &str = "capture-actions-finished";
+
+/// 截图方式，每种方式有各自的动作链
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CaptureMode {
+    FullScreen,
+    Region,
+    ActiveWindow,
+}
+
+/// 截图后执行的单个动作
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Action {
+    /// 按设置中的目录和文件名模板保存
+    Save,
+    /// 复制图像到剪贴板
+    CopyImage,
+    /// 复制保存的文件路径到剪贴板
+    CopyPath,
+    /// 上传到指定目标，`sink` 为空时使用默认目标
+    Upload {
+        #[serde(default)]
+        sink: Option<String>,
+    },
+    /// 用指定应用打开，`app` 为空时使用系统默认应用
+    OpenWith {
+        #[serde(default)]
+        app: Option<String>,
+    },
+    /// 通过系统 shell 运行命令，文件路径作为第一个参数（`$1`）和环境变量 `SCREENSHOT_PATH` 传入
+    RunCommand { command: String },
+    /// 显示系统通知，`body` 为空时显示文件路径
+    Notify {
+        #[serde(default)]
+        title: Option<String>,
+        #[serde(default)]
+        body: Option<String>,
+    },
+}
+
+impl Action {
+    /// 用于日志和错误提示的中文名称
+    pub fn label(&self) -> &'static str {
+        match self {
+            Action::Save => "保存",
+            Action::CopyImage => "复制图像",
+            Action::CopyPath => "复制路径",
+            Action::Upload { .. } => "上传",
+            Action::OpenWith { .. } => "打开",
+            Action::RunCommand { .. } => "运行命令",
+            Action::Notify { .. } => "通知",
+        }
+    }
+
+    /// 是否需要先保存文件
+    fn needs_file(&self) -> bool {
+        matches!(
+            self,
+            Action::CopyPath
+                | Action::Upload { .. }
+                | Action::OpenWith { .. }
+                | Action::RunCommand { .. }
+        )
+    }
+}
+
+/// 各截图方式的动作链，默认全屏和窗口截图保存文件，区域截图复制到剪贴板
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ActionChains {
+    pub full_screen: Vec<Action>,
+    pub region: Vec<Action>,
+    pub active_window: Vec<Action>,
+}
+
+impl Default for ActionChains {
+    fn default() -> Self {
+        Self {
+            full_screen: vec![Action::Save],
+            region: vec![Action::CopyImage],
+            active_window: vec![Action::Save],
+        }
+    }
+}
+
+impl ActionChains {
+    pub fn get(&self, mode: CaptureMode) -> &[Action] {
+        match mode {
+            CaptureMode::FullScreen => &self.full_screen,
+            CaptureMode::Region => &self.region,
+            CaptureMode::ActiveWindow => &self.active_window,
+        }
+    }
+
+    /// 截图方式的动作链中是否有上传动作
+    pub fn uploads(&self, mode: CaptureMode) -> bool {
+        self.get(mode)
+            .iter()
+            .any(|action| matches!(action, Action::Upload { .. }))
+    }
+
+    /// 校验动作链，`sinks` 为已配置的上传目标名称
+    pub fn validate(&self, sinks: &[&str]) -> Result<(), String> {
+        for action in [&self.full_screen, &self.region, &self.active_window]
+            .into_iter()
+            .flatten()
+        {
+            match action {
+                Action::RunCommand { command } if command.trim().is_empty() => {
+                    return Err("运行命令的动作必须指定命令".to_string());
+                }
+                Action::Upload { sink: Some(sink) } if !sinks.contains(&sink.as_str()) => {
+                    return Err(format!("上传目标不存在: {}", sink));
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+/// 单个动作的执行结果
+#[derive(Debug, Clone, Serialize)]
+pub struct StepReport {
+    pub action: Action,
+    /// 动作产生的结果，如上传链接、命令输出
+    pub output: Option<String>,
+    pub error: Option<String>,
+}
+
+/// 动作链的执行结果，某一步失败不会中断后续不依赖它的动作
+#[derive(Debug, Clone, Serialize)]
+pub struct ChainReport {
+    pub mode: CaptureMode,
+    /// 保存的文件路径，动作链中没有保存或保存失败时为 `None`
+    pub path: Option<String>,
+    pub steps: Vec<StepReport>,
+    /// 截图各阶段的耗时
+    pub timings: CaptureTimings,
+}
+
+impl ChainReport {
+    /// 第一个失败动作的错误信息
+    pub fn first_error(&self) -> Option<String> {
+        self.steps.iter().find_map(|step| {
+            step.error
+                .as_ref()
+                .map(|error| format!("{}失败: {}", step.action.label(), error))
+        })
+    }
+}
+
+/// 运行动作链中的命令，超时时间使用钩子的默认值
+fn run_shell(command: &str, path: &Path) -> Result<String, String> {
+    hooks::run_command(
+        command,
+        path,
+        None,
+        &[],
+        Duration::from_secs(hooks::DEFAULT_TIMEOUT_SECS),
+    )
+}
+
+fn run_step(
+    app: &AppHandle,
+    action: &Action,
+    captured: &CapturedImage,
+    saved: &mut Option<SavedScreenshot>,
+) -> Result<Option<String>, String> {
+    if action.needs_file() && saved.is_none() {
+        return Err("没有已保存的截图文件，请在此动作之前添加保存动作".to_string());
+    }
+    let file = saved
+        .as_ref()
+        .map(|saved| saved.path.clone())
+        .unwrap_or_default();
+
+    match action {
+        Action::Save => {
+            let file = screenshot::save_captured(app, captured, Some(captured.timings.mode))?;
+            let path = file.path.clone();
+            *saved = Some(file);
+            Ok(Some(path))
+        }
+        Action::CopyImage => screenshot::copy_image_to_clipboard(&captured.image).map(|_| None),
+        Action::CopyPath => screenshot::copy_text_to_clipboard(&file).map(|_| None),
+        Action::Upload { sink } => {
+            upload::upload_and_share(app, Path::new(&file), sink.as_deref()).map(Some)
+        }
+        Action::OpenWith { app: with } => app
+            .opener()
+            .open_path(&file, with.as_deref())
+            .map(|_| None)
+            .map_err(|e| format!("打开截图失败: {}", e)),
+        Action::RunCommand { command } => {
+            let output = run_shell(command, Path::new(&file))?;
+            Ok(Some(output).filter(|output| !output.is_empty()))
+        }
+        Action::Notify { title, body } => {
+            let body = body
+                .clone()
+                .or_else(|| saved.as_ref().map(|saved| saved.path.clone()))
+                .unwrap_or_default();
+            app.notification()
+                .builder()
+                .title(title.as_deref().unwrap_or("截图完成"))
+                .body(body)
+                .show()
+                .map(|_| None)
+                .map_err(|e| format!("显示通知失败: {}", e))
+        }
+    }
+}
+
+/// 按设置执行截图方式对应的动作链，并发送执行结果事件
+///
+/// 动作链保存的截图在所有动作完成后才运行钩子，钩子不会和动作链中的上传等动作同时处理同一个文件。
+pub fn run(app: &AppHandle, mode: CaptureMode, captured: CapturedImage) -> ChainReport {
+    let chain = settings::current(app).actions.get(mode).to_vec();
+    let mut saved = None;
+    let mut steps = Vec::with_capacity(chain.len());
+
+    for action in chain {
+        let result = run_step(app, &action, &captured, &mut saved);
+        match &result {
+            Ok(_) => info!("动作「{}」完成", action.label()),
+            Err(e) => error!("动作「{}」失败: {}", action.label(), e),
+        }
+        steps.push(StepReport {
+            action,
+            output: result.as_ref().ok().cloned().flatten(),
+            error: result.err(),
+        });
+    }
+
+    let report = ChainReport {
+        mode,
+        path: saved.as_ref().map(|saved| saved.path.clone()),
+        steps,
+        timings: captured.timings.clone(),
+    };
+    captured.recycle();
+    if let Err(e) = app.emit(CAPTURE_ACTIONS_FINISHED_EVENT, &report) {
+        warn!("发送动作链结果事件失败: {}", e);
+    }
+    if let Some(saved) = saved {
+        hooks::spawn(app, saved);
+    }
+    report
+}
+
+/// 截取指定区域并执行区域截图的动作链，供前端选区完成后调用
+#[command]
+pub async fn capture_region_with_actions(
+    app: AppHandle,
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
+) -> Result<ChainReport, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        // 选区界面仍在显示，从打开选区界面时冻结的画面中裁剪
+        let captured = match screenshot::grab_region(&app, x, y, width, height, true) {
+            Ok(captured) => captured,
+            Err(e) => {
+                notifications::notify_capture(&app, &Err(e.clone()));
+                return Err(e);
+            }
+        };
+        let report = run(&app, CaptureMode::Region, captured);
+        let result = match report.first_error() {
+            Some(e) => Err(e),
+            None => Ok(report.path.clone()),
+        };
+        notifications::notify_capture(&app, &result);
+        Ok(report)
+    })
+    .await
+    .map_err(|e| format!("截图任务失败: {}", e))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_action_chains_serde_and_validate() {
+        let chains: ActionChains = serde_json::from_value(serde_json::json!({
+            "region": [
+                { "type": "save" },
+                { "type": "upload", "sink": "minio" },
+                { "type": "run_command", "command": "optipng \"$1\"" },
+                { "type": "notify" }
+            ]
+        }))
+        .unwrap();
+        assert_eq!(chains.full_screen, vec![Action::Save]);
+        assert_eq!(chains.get(CaptureMode::Region).len(), 4);
+        assert_eq!(
+            chains.region[1],
+            Action::Upload {
+                sink: Some("minio".to_string())
+            }
+        );
+        assert!(chains.uploads(CaptureMode::Region));
+        assert!(!chains.uploads(CaptureMode::FullScreen));
+        assert_eq!(chains.validate(&["minio"]), Ok(()));
+        assert!(chains.validate(&[]).is_err());
+
+        let empty_command = ActionChains {
+            active_window: vec![Action::RunCommand {
+                command: " ".to_string(),
+            }],
+            ..Default::default()
+        };
+        assert!(empty_command.validate(&[]).is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_run_shell_passes_path() {
+        let path = Path::new("/tmp/shot 1.png");
+        assert_eq!(
+            run_shell("printf '%s|%s' \"$1\" \"$SCREENSHOT_PATH\"", path).unwrap(),
+            "/tmp/shot 1.png|/tmp/shot 1.png"
+        );
+        let error = run_shell("echo failed >&2; exit 3", path).unwrap_err();
+        assert!(error.contains("3") && error.contains("failed"));
+    }
+}
@@ -0,0 +1,883 @@
+use crate::cli::{self, CaptureOptions, CaptureTarget, CliError, CommandEnv};
+use crate::history::{self, HistoryQuery, HistoryState};
+use crate::monitors;
+use crate::screenshot;
+use crate::settings::{self, Settings};
+use crate::storage;
+use base64::{engine::general_purpose, Engine as _};
+use interprocess::local_socket::{prelude::*, ListenerOptions, Name, Stream};
+use log::{error, info, warn};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::fmt;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{Ipv4Addr, TcpListener, TcpStream};
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Manager};
+
+/// HTTP 请求体的大小上限
+const MAX_BODY_SIZE: usize = 1024 * 1024;
+
+/// HTTP 请求头的大小上限
+const MAX_HEADER_SIZE: usize = 16 * 1024;
+
+/// 读取请求的超时时间，防止空闲连接一直占用线程
+const READ_TIMEOUT: Duration = Duration::from_secs(30);
+
+// JSON-RPC 2.0 错误码
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+/// 截图、历史记录等操作本身失败
+const OPERATION_FAILED: i64 = -32000;
+/// 找不到指定的显示器、窗口或历史记录
+const NOT_FOUND: i64 = -32001;
+
+/// 自动化接口的传输方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AutomationTransport {
+    /// Unix 域套接字（Windows 上为命名管道），每行一个 JSON-RPC 请求，依靠文件权限限制访问
+    #[default]
+    Socket,
+    /// 监听 127.0.0.1 的 HTTP 接口，`POST /` 提交 JSON-RPC 请求，需携带 Bearer 令牌
+    Http,
+}
+
+/// 本地自动化接口设置，默认关闭，重启后生效
+///
+/// 启动后连接方式写入配置目录下的 automation.json（仅当前用户可读）。
+#[derive(Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AutomationSettings {
+    pub enabled: bool,
+    pub transport: AutomationTransport,
+    /// HTTP 监听端口，0 表示由系统分配
+    pub port: u16,
+    /// HTTP 访问令牌，`None` 时每次启动随机生成
+    pub token: Option<String>,
+}
+
+// 设置会被写入日志，令牌不能出现在其中
+impl fmt::Debug for AutomationSettings {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AutomationSettings")
+            .field("enabled", &self.enabled)
+            .field("transport", &self.transport)
+            .field("port", &self.port)
+            .field("token", &self.token.as_ref().map(|_| "<redacted>"))
+            .finish()
+    }
+}
+
+impl AutomationSettings {
+    /// 校验令牌，返回错误信息
+    pub fn validate_token(&self) -> Result<(), String> {
+        match &self.token {
+            Some(token) if token.len() < 16 => Err("访问令牌至少需要 16 个字符".to_string()),
+            Some(token) if !token.bytes().all(|b| b.is_ascii_graphic()) => {
+                Err("访问令牌只能包含可见的 ASCII 字符".to_string())
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// 写入 automation.json 的连接信息，供自动化脚本读取
+#[derive(Debug, Serialize)]
+struct Endpoint {
+    transport: AutomationTransport,
+    /// 套接字路径或管道名称
+    #[serde(skip_serializing_if = "Option::is_none")]
+    socket: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    token: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RpcRequest {
+    jsonrpc: String,
+    /// 没有 id 的请求为通知，执行后不返回响应
+    id: Option<Value>,
+    method: String,
+    #[serde(default)]
+    params: Value,
+}
+
+#[derive(Debug, Serialize)]
+struct RpcResponse {
+    jsonrpc: &'static str,
+    id: Value,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    result: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<RpcError>,
+}
+
+#[derive(Debug, Serialize)]
+struct RpcError {
+    code: i64,
+    message: String,
+}
+
+impl RpcError {
+    fn new(code: i64, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+        }
+    }
+}
+
+impl From<CliError> for RpcError {
+    fn from(e: CliError) -> Self {
+        let code = match e.code {
+            cli::EXIT_USAGE => INVALID_PARAMS,
+            cli::EXIT_NOT_FOUND => NOT_FOUND,
+            _ => OPERATION_FAILED,
+        };
+        RpcError::new(code, e.message)
+    }
+}
+
+/// 截图方法返回图像的方式
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum ReturnMode {
+    /// 保存文件并返回保存信息（与截图保存事件的内容相同）
+    #[default]
+    Path,
+    /// 不保存文件，返回 Base64 编码的图像数据
+    Bytes,
+}
+
+/// 截图方法的公共参数，`output` 按前端保存截图的规则限制在截图目录和允许的目录之内，
+/// 默认不覆盖已存在的文件，`overwrite` 为 true 时才覆盖
+#[derive(Debug, Default, Deserialize)]
+struct CaptureParams {
+    #[serde(flatten)]
+    options: CaptureOptions,
+    #[serde(default, rename = "return")]
+    mode: ReturnMode,
+}
+
+#[derive(Debug, Deserialize)]
+struct RegionParams {
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
+    #[serde(flatten)]
+    capture: CaptureParams,
+}
+
+#[derive(Debug, Deserialize)]
+struct WindowParams {
+    title: Option<String>,
+    #[serde(flatten)]
+    capture: CaptureParams,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct PageParams {
+    limit: Option<u32>,
+    offset: Option<u32>,
+}
+
+#[derive(Debug, Deserialize)]
+struct IdParams {
+    id: i64,
+}
+
+#[derive(Debug, Deserialize)]
+struct TagParams {
+    id: i64,
+    tags: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct StarParams {
+    id: i64,
+    starred: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct DeleteParams {
+    id: i64,
+    #[serde(default)]
+    delete_file: bool,
+}
+
+/// 解析方法参数，省略 `params` 时按空对象处理
+fn parse_params<T: serde::de::DeserializeOwned>(params: Value) -> Result<T, RpcError> {
+    let params = if params.is_null() {
+        Value::Object(Default::default())
+    } else {
+        params
+    };
+    serde_json::from_value(params).map_err(|e| RpcError::new(INVALID_PARAMS, e.to_string()))
+}
+
+fn to_value<T: Serialize>(value: T) -> Result<Value, RpcError> {
+    serde_json::to_value(value).map_err(|e| RpcError::new(OPERATION_FAILED, e.to_string()))
+}
+
+fn history_state(app: &AppHandle) -> Result<tauri::State<'_, HistoryState>, RpcError> {
+    app.try_state::<HistoryState>()
+        .ok_or_else(|| RpcError::new(OPERATION_FAILED, "截图历史不可用"))
+}
+
+/// 执行 JSON-RPC 方法
+///
+/// - `capture.full` / `capture.region` / `capture.window`：截图，参数同命令行的截图选项，
+///   另有 `return`（`path` 或 `bytes`）
+/// - `monitors.list`：列出显示器
+/// - `history.list` / `history.search` / `history.get` / `history.tag` / `history.star` /
+///   `history.delete`：与前端的历史记录命令相同
+fn dispatch(app: &AppHandle, method: &str, params: Value) -> Result<Value, RpcError> {
+    match method {
+        "capture.full" => capture(app, CaptureTarget::Full, parse_params(params)?),
+        "capture.region" => {
+            let params: RegionParams = parse_params(params)?;
+            let target = CaptureTarget::Region {
+                x: params.x,
+                y: params.y,
+                width: params.width,
+                height: params.height,
+            };
+            capture(app, target, params.capture)
+        }
+        "capture.window" => {
+            let params: WindowParams = parse_params(params)?;
+            let target = CaptureTarget::Window {
+                title: params.title,
+            };
+            capture(app, target, params.capture)
+        }
+        "monitors.list" => to_value(
+            monitors::registry()
+                .monitors()
+                .map_err(|e| RpcError::new(OPERATION_FAILED, e))?,
+        ),
+        "history.list" => {
+            let params: PageParams = parse_params(params)?;
+            let entries = history::list_history(history_state(app)?, params.limit, params.offset)
+                .map_err(|e| RpcError::new(OPERATION_FAILED, e))?;
+            to_value(entries)
+        }
+        "history.search" => {
+            let query: HistoryQuery = parse_params(params)?;
+            let entries = history::search_history(history_state(app)?, query)
+                .map_err(|e| RpcError::new(OPERATION_FAILED, e))?;
+            to_value(entries)
+        }
+        "history.get" => {
+            let params: IdParams = parse_params(params)?;
+            let entry = history_state(app)?
+                .get(params.id)
+                .map_err(|e| RpcError::new(OPERATION_FAILED, e))?
+                .ok_or_else(|| {
+                    RpcError::new(NOT_FOUND, format!("历史记录不存在: {}", params.id))
+                })?;
+            to_value(entry)
+        }
+        "history.tag" => {
+            let params: TagParams = parse_params(params)?;
+            let entry = history::tag_history_entry(history_state(app)?, params.id, params.tags)
+                .map_err(|e| RpcError::new(OPERATION_FAILED, e))?;
+            to_value(entry)
+        }
+        "history.star" => {
+            let params: StarParams = parse_params(params)?;
+            let entry = history::star_history_entry(history_state(app)?, params.id, params.starred)
+                .map_err(|e| RpcError::new(OPERATION_FAILED, e))?;
+            to_value(entry)
+        }
+        "history.delete" => {
+            let params: DeleteParams = parse_params(params)?;
+            history::delete_history_entry(history_state(app)?, params.id, params.delete_file)
+                .map_err(|e| RpcError::new(OPERATION_FAILED, e))?;
+            Ok(Value::Null)
+        }
+        _ => Err(RpcError::new(
+            METHOD_NOT_FOUND,
+            format!("未知的方法: {}", method),
+        )),
+    }
+}
+
+fn capture(
+    app: &AppHandle,
+    target: CaptureTarget,
+    params: CaptureParams,
+) -> Result<Value, RpcError> {
+    let settings = settings::current(app);
+    let mut options = params.options;
+
+    if options
+        .quality
+        .is_some_and(|quality| !(1..=100).contains(&quality))
+    {
+        return Err(RpcError::new(
+            INVALID_PARAMS,
+            "quality 必须在 1 到 100 之间",
+        ));
+    }
+    options.output = match (params.mode, options.output.take()) {
+        (ReturnMode::Bytes, None) => Some("-".to_string()),
+        (ReturnMode::Bytes, Some(_)) => {
+            return Err(RpcError::new(
+                INVALID_PARAMS,
+                "return 为 bytes 时不能指定 output",
+            ))
+        }
+        (ReturnMode::Path, Some(output)) => {
+            let screenshots_dir = screenshot::get_screenshots_dir(&settings)
+                .map_err(|e| RpcError::new(OPERATION_FAILED, e))?;
+            let (dir, name) = storage::resolve_target(
+                &output,
+                &screenshots_dir,
+                &settings.allowed_save_dirs,
+                options.format.image_format(),
+            )
+            .map_err(|e| RpcError::new(INVALID_PARAMS, e))?;
+            Some(dir.join(name).to_string_lossy().to_string())
+        }
+        (ReturnMode::Path, None) => None,
+    };
+
+    let format = options.format;
+    let next_counter = |settings: &Settings| screenshot::next_filename_counter(app, settings);
+    let env = CommandEnv {
+        headless: false,
+        settings,
+        cwd: PathBuf::new(),
+        next_counter: &next_counter,
+    };
+    let mut out = Vec::new();
+    let saved = cli::capture(target, options, &env, &mut out)?;
+
+    if params.mode == ReturnMode::Bytes {
+        let extension = storage::extension_for(format.image_format());
+        return Ok(json!({
+            "format": extension,
+            "mime": format.image_format().to_mime_type(),
+            "size": out.len(),
+            "data": general_purpose::STANDARD.encode(&out),
+        }));
+    }
+
+    // 只复制到剪贴板时没有保存文件
+    let Some(saved) = saved else {
+        return Ok(Value::Null);
+    };
+    screenshot::announce_saved(app, &saved);
+    to_value(saved)
+}
+
+/// 处理一条 JSON-RPC 消息并记录审计日志，通知类请求返回 `None`
+fn handle_message<F>(client: &str, message: &[u8], dispatch: F) -> Option<String>
+where
+    F: FnOnce(&str, Value) -> Result<Value, RpcError>,
+{
+    let start = Instant::now();
+    let (id, outcome) = match serde_json::from_slice::<Value>(message) {
+        Err(e) => {
+            warn!("[自动化审计] client={}, 无法解析的请求: {}", client, e);
+            (
+                Some(Value::Null),
+                Err(RpcError::new(PARSE_ERROR, format!("无法解析请求: {}", e))),
+            )
+        }
+        Ok(value) if value.is_array() => {
+            warn!("[自动化审计] client={}, 拒绝批量请求", client);
+            (
+                Some(Value::Null),
+                Err(RpcError::new(INVALID_REQUEST, "不支持批量请求")),
+            )
+        }
+        Ok(value) => match serde_json::from_value::<RpcRequest>(value) {
+            Ok(request) if request.jsonrpc == "2.0" => {
+                info!(
+                    "[自动化审计] client={}, id={}, method={}, params={}",
+                    client,
+                    request.id.as_ref().unwrap_or(&Value::Null),
+                    request.method,
+                    request.params
+                );
+                let result = dispatch(&request.method, request.params);
+                match &result {
+                    Ok(_) => info!(
+                        "[自动化审计] client={}, method={}, 成功, 耗时: {:?}",
+                        client,
+                        request.method,
+                        start.elapsed()
+                    ),
+                    Err(e) => warn!(
+                        "[自动化审计] client={}, method={}, 失败 ({}): {}, 耗时: {:?}",
+                        client,
+                        request.method,
+                        e.code,
+                        e.message,
+                        start.elapsed()
+                    ),
+                }
+                (request.id, result)
+            }
+            Ok(_) => (
+                Some(Value::Null),
+                Err(RpcError::new(INVALID_REQUEST, "jsonrpc 必须为 \"2.0\"")),
+            ),
+            Err(e) => {
+                warn!("[自动化审计] client={}, 无效的请求: {}", client, e);
+                (
+                    Some(Value::Null),
+                    Err(RpcError::new(INVALID_REQUEST, format!("无效的请求: {}", e))),
+                )
+            }
+        },
+    };
+
+    let id = id?;
+    let (result, error) = match outcome {
+        Ok(result) => (Some(result), None),
+        Err(error) => (None, Some(error)),
+    };
+    let response = RpcResponse {
+        jsonrpc: "2.0",
+        id,
+        result,
+        error,
+    };
+    Some(serde_json::to_string(&response).unwrap_or_default())
+}
+
+/// 自动化套接字名称
+fn socket_name() -> io::Result<(Name<'static>, String)> {
+    #[cfg(unix)]
+    {
+        use interprocess::local_socket::GenericFilePath;
+        let dir = crate::get_app_config_dir()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "无法获取配置目录"))?;
+        let path = dir.join("automation.sock");
+        let display = path.to_string_lossy().to_string();
+        Ok((path.to_fs_name::<GenericFilePath>()?, display))
+    }
+
+    #[cfg(not(unix))]
+    {
+        use interprocess::local_socket::GenericNamespaced;
+        let user = std::env::var("USERNAME").unwrap_or_default();
+        let name = format!("screenshot-app-automation-{}", user);
+        Ok((
+            name.clone().to_ns_name::<GenericNamespaced>()?,
+            format!(r"\\.\pipe\{}", name),
+        ))
+    }
+}
+
+fn get_endpoint_path() -> Option<PathBuf> {
+    crate::get_app_config_dir().map(|dir| dir.join("automation.json"))
+}
+
+/// 限制文件只有当前用户可以访问
+fn restrict_permissions(path: &std::path::Path) -> io::Result<()> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600))
+    }
+
+    #[cfg(not(unix))]
+    {
+        let _ = path;
+        Ok(())
+    }
+}
+
+fn write_endpoint(endpoint: &Endpoint) -> Result<(), String> {
+    let path = get_endpoint_path().ok_or("无法获取配置目录".to_string())?;
+    let content = serde_json::to_string_pretty(endpoint).map_err(|e| e.to_string())?;
+
+    // 先创建空文件并收紧权限，再写入令牌
+    std::fs::write(&path, "")
+        .and_then(|_| restrict_permissions(&path))
+        .and_then(|_| std::fs::write(&path, content))
+        .map_err(|e| {
+            error!("写入自动化接口连接信息失败: {}", e);
+            format!("写入自动化接口连接信息失败: {}", e)
+        })
+}
+
+/// 生成随机访问令牌：操作系统随机数生成器产生的 32 字节，以十六进制表示
+fn generate_token() -> Result<String, String> {
+    let mut bytes = [0u8; 32];
+    getrandom::fill(&mut bytes).map_err(|e| format!("生成访问令牌失败: {}", e))?;
+    Ok(bytes.iter().map(|byte| format!("{:02x}", byte)).collect())
+}
+
+/// 逐字节比较，耗时与内容无关，避免通过响应时间猜测令牌
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+/// 按设置启动自动化接口，未启用时删除残留的连接信息文件
+pub fn init(app: &AppHandle) {
+    let config = settings::current(app).automation;
+    if !config.enabled {
+        if let Some(path) = get_endpoint_path().filter(|path| path.exists()) {
+            let _ = std::fs::remove_file(path);
+        }
+        return;
+    }
+
+    let result = match config.transport {
+        AutomationTransport::Socket => listen_socket(app),
+        AutomationTransport::Http => {
+            let token = match config.token.clone() {
+                Some(token) => Ok(token),
+                None => generate_token(),
+            };
+            token.and_then(|token| listen_http(app, config.port, token))
+        }
+    };
+    if let Err(e) = result {
+        error!("启动自动化接口失败: {}", e);
+    }
+}
+
+fn listen_socket(app: &AppHandle) -> Result<(), String> {
+    let (name, display) = socket_name().map_err(|e| format!("无法确定套接字名称: {}", e))?;
+    // 单实例保证只有本进程使用该套接字，残留文件来自异常退出的进程
+    let listener = ListenerOptions::new()
+        .name(name)
+        .try_overwrite(true)
+        .create_sync()
+        .map_err(|e| format!("创建自动化套接字失败: {}", e))?;
+    #[cfg(unix)]
+    restrict_permissions(std::path::Path::new(&display))
+        .map_err(|e| format!("设置自动化套接字权限失败: {}", e))?;
+
+    write_endpoint(&Endpoint {
+        transport: AutomationTransport::Socket,
+        socket: Some(display.clone()),
+        url: None,
+        token: None,
+    })?;
+    info!("自动化接口已启动: {}", display);
+
+    let app = app.clone();
+    std::thread::spawn(move || {
+        for (connection, stream) in listener.incoming().enumerate() {
+            match stream {
+                Ok(stream) => {
+                    let app = app.clone();
+                    let client = format!("socket#{}", connection);
+                    std::thread::spawn(move || {
+                        if let Err(e) = serve_socket(&app, &client, stream) {
+                            warn!("自动化连接 {} 异常断开: {}", client, e);
+                        }
+                    });
+                }
+                Err(e) => warn!("接受自动化连接失败: {}", e),
+            }
+        }
+    });
+    Ok(())
+}
+
+/// 套接字上每行一个请求，连接可以复用
+fn serve_socket(app: &AppHandle, client: &str, stream: Stream) -> io::Result<()> {
+    let mut reader = BufReader::new(stream);
+    let mut line = Vec::new();
+    loop {
+        line.clear();
+        if reader.read_until(b'\n', &mut line)? == 0 {
+            return Ok(());
+        }
+        if line.iter().all(u8::is_ascii_whitespace) {
+            continue;
+        }
+        if let Some(mut response) = handle_message(client, &line, |method, params| {
+            dispatch(app, method, params)
+        }) {
+            response.push('\n');
+            reader.get_mut().write_all(response.as_bytes())?;
+        }
+    }
+}
+
+fn listen_http(app: &AppHandle, port: u16, token: String) -> Result<(), String> {
+    let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, port))
+        .map_err(|e| format!("监听 127.0.0.1:{} 失败: {}", port, e))?;
+    let port = listener
+        .local_addr()
+        .map_err(|e| format!("获取监听地址失败: {}", e))?
+        .port();
+
+    write_endpoint(&Endpoint {
+        transport: AutomationTransport::Http,
+        socket: None,
+        url: Some(format!("http://127.0.0.1:{}/", port)),
+        token: Some(token.clone()),
+    })?;
+    info!("自动化接口已启动: http://127.0.0.1:{}/", port);
+
+    let app = app.clone();
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            match stream {
+                Ok(stream) => {
+                    let app = app.clone();
+                    let token = token.clone();
+                    std::thread::spawn(move || {
+                        if let Err(e) = serve_http(&app, stream, port, &token) {
+                            warn!("处理自动化 HTTP 请求失败: {}", e);
+                        }
+                    });
+                }
+                Err(e) => warn!("接受自动化连接失败: {}", e),
+            }
+        }
+    });
+    Ok(())
+}
+
+/// 解析后的 HTTP 请求
+#[derive(Debug, Default)]
+struct HttpRequest {
+    method: String,
+    path: String,
+    host: Option<String>,
+    origin: Option<String>,
+    authorization: Option<String>,
+    content_length: Option<usize>,
+}
+
+/// 读取请求行和请求头，请求体留在 `reader` 中
+fn read_http_head(reader: &mut impl BufRead) -> Result<HttpRequest, u16> {
+    let mut request = HttpRequest::default();
+    let mut read = 0;
+    let mut line = String::new();
+    let mut first = true;
+    loop {
+        line.clear();
+        let n = reader.read_line(&mut line).map_err(|_| 400u16)?;
+        read += n;
+        if n == 0 || read > MAX_HEADER_SIZE {
+            return Err(400);
+        }
+        let line = line.trim_end_matches(['\r', '\n']);
+        if first {
+            let mut parts = line.split(' ');
+            request.method = parts.next().unwrap_or_default().to_string();
+            request.path = parts.next().unwrap_or_default().to_string();
+            first = false;
+            continue;
+        }
+        if line.is_empty() {
+            return Ok(request);
+        }
+        let Some((name, value)) = line.split_once(':') else {
+            return Err(400);
+        };
+        let value = value.trim().to_string();
+        match name.trim().to_ascii_lowercase().as_str() {
+            "host" => request.host = Some(value),
+            "origin" => request.origin = Some(value),
+            "authorization" => request.authorization = Some(value),
+            "content-length" => request.content_length = Some(value.parse().map_err(|_| 400u16)?),
+            _ => {}
+        }
+    }
+}
+
+/// 检查请求能否交给 JSON-RPC 处理，拒绝时返回 HTTP 状态码
+fn check_http_request(request: &HttpRequest, port: u16, token: &str) -> Result<usize, u16> {
+    if request.method != "POST" {
+        return Err(405);
+    }
+    if request.path != "/" {
+        return Err(404);
+    }
+    // 浏览器中的网页也能访问 127.0.0.1：带 Origin 的请求一律拒绝，并校验 Host 防止 DNS 重绑定
+    if request.origin.is_some() {
+        return Err(403);
+    }
+    let host_allowed = request.host.as_deref().is_some_and(|host| {
+        host == format!("127.0.0.1:{}", port) || host == format!("localhost:{}", port)
+    });
+    if !host_allowed {
+        return Err(403);
+    }
+    let authorized = request
+        .authorization
+        .as_deref()
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .is_some_and(|value| constant_time_eq(value.trim().as_bytes(), token.as_bytes()));
+    if !authorized {
+        return Err(401);
+    }
+    match request.content_length {
+        None => Err(411),
+        Some(length) if length > MAX_BODY_SIZE => Err(413),
+        Some(length) => Ok(length),
+    }
+}
+
+fn reason_phrase(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        204 => "No Content",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        403 => "Forbidden",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        411 => "Length Required",
+        413 => "Payload Too Large",
+        _ => "Error",
+    }
+}
+
+fn write_http_response(stream: &mut TcpStream, status: u16, body: &str) -> io::Result<()> {
+    let mut response = format!(
+        "HTTP/1.1 {} {}\r\nContent-Length: {}\r\nConnection: close\r\n",
+        status,
+        reason_phrase(status),
+        body.len()
+    );
+    if status == 401 {
+        response.push_str("WWW-Authenticate: Bearer\r\n");
+    }
+    if !body.is_empty() {
+        response.push_str("Content-Type: application/json\r\n");
+    }
+    response.push_str("\r\n");
+    response.push_str(body);
+    stream.write_all(response.as_bytes())?;
+    stream.flush()
+}
+
+/// 每个连接处理一个请求
+fn serve_http(app: &AppHandle, stream: TcpStream, port: u16, token: &str) -> io::Result<()> {
+    let client = stream
+        .peer_addr()
+        .map(|addr| format!("http://{}", addr))
+        .unwrap_or_else(|_| "http".to_string());
+    stream.set_read_timeout(Some(READ_TIMEOUT))?;
+    let mut writer = stream.try_clone()?;
+    let mut reader = BufReader::new(stream);
+
+    let checked = read_http_head(&mut reader).and_then(|request| {
+        check_http_request(&request, port, token).map_err(|status| {
+            warn!(
+                "[自动化审计] client={}, 拒绝 HTTP 请求 ({}): {} {}",
+                client, status, request.method, request.path
+            );
+            status
+        })
+    });
+    let length = match checked {
+        Ok(length) => length,
+        Err(status) => return write_http_response(&mut writer, status, ""),
+    };
+
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body)?;
+    match handle_message(&client, &body, |method, params| {
+        dispatch(app, method, params)
+    }) {
+        Some(response) => write_http_response(&mut writer, 200, &response),
+        None => write_http_response(&mut writer, 204, ""),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn echo(method: &str, params: Value) -> Result<Value, RpcError> {
+        match method {
+            "echo" => Ok(params),
+            _ => Err(RpcError::new(METHOD_NOT_FOUND, method)),
+        }
+    }
+
+    fn call(message: &str) -> Option<Value> {
+        handle_message("test", message.as_bytes(), echo)
+            .map(|response| serde_json::from_str(&response).unwrap())
+    }
+
+    #[test]
+    fn test_handle_message() {
+        let response =
+            call(r#"{"jsonrpc":"2.0","id":1,"method":"echo","params":{"a":1}}"#).unwrap();
+        assert_eq!(response["id"], 1);
+        assert_eq!(response["result"]["a"], 1);
+        assert!(response.get("error").is_none());
+
+        let response = call(r#"{"jsonrpc":"2.0","id":"x","method":"nope"}"#).unwrap();
+        assert_eq!(response["id"], "x");
+        assert_eq!(response["error"]["code"], METHOD_NOT_FOUND);
+
+        assert_eq!(call("{not json").unwrap()["error"]["code"], PARSE_ERROR);
+        assert_eq!(call("[]").unwrap()["error"]["code"], INVALID_REQUEST);
+        assert_eq!(
+            call(r#"{"jsonrpc":"1.0","id":1,"method":"echo"}"#).unwrap()["error"]["code"],
+            INVALID_REQUEST
+        );
+        // 通知不返回响应
+        assert!(call(r#"{"jsonrpc":"2.0","method":"echo"}"#).is_none());
+    }
+
+    #[test]
+    fn test_capture_params() {
+        let params: RegionParams = parse_params(json!({
+            "x": 10, "y": 20, "width": 300, "height": 200,
+            "format": "jpeg", "quality": 80, "return": "bytes"
+        }))
+        .unwrap();
+        assert_eq!((params.x, params.width), (10, 300));
+        assert_eq!(params.capture.options.format, cli::OutputFormat::Jpeg);
+        assert_eq!(params.capture.options.quality, Some(80));
+        assert_eq!(params.capture.mode, ReturnMode::Bytes);
+
+        let params: CaptureParams = parse_params(Value::Null).unwrap();
+        assert_eq!(params.mode, ReturnMode::Path);
+        assert!(parse_params::<RegionParams>(json!({ "x": 1 })).is_err());
+    }
+
+    #[test]
+    fn test_http_request_checks() {
+        let head = "POST / HTTP/1.1\r\nHost: 127.0.0.1:4000\r\nAuthorization: Bearer secret-token\r\nContent-Length: 12\r\n\r\n";
+        let request = read_http_head(&mut head.as_bytes()).unwrap();
+        assert_eq!(check_http_request(&request, 4000, "secret-token"), Ok(12));
+        assert_eq!(check_http_request(&request, 4000, "other-token!"), Err(401));
+        assert_eq!(check_http_request(&request, 5000, "secret-token"), Err(403));
+
+        let with_origin = HttpRequest {
+            origin: Some("http://example.com".to_string()),
+            ..request
+        };
+        assert_eq!(
+            check_http_request(&with_origin, 4000, "secret-token"),
+            Err(403)
+        );
+
+        assert_eq!(
+            read_http_head(&mut "GET / HTTP/1.1\r\n".as_bytes()).unwrap_err(),
+            400
+        );
+        let token = generate_token().unwrap();
+        assert_eq!(token.len(), 64);
+        assert_ne!(token, generate_token().unwrap());
+        assert!(constant_time_eq(b"abc", b"abc"));
+        assert!(!constant_time_eq(b"abc", b"abd"));
+    }
+}
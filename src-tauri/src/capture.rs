@@ -0,0 +1,528 @@
+use crate::crop;
+#[cfg(all(unix, not(target_os = "macos")))]
+use crate::portal;
+use image::RgbaImage;
+use log::info;
+use serde::Serialize;
+use std::fmt;
+use std::sync::OnceLock;
+use xcap::Monitor;
+
+/// 区域截图的实现方式
+///
+/// 支持只截取子区域的后端优先使用，否则截取整个显示器后再裁剪。
+/// xcap 更新到提供 `Monitor::capture_region` 的版本后，可以在这里增加对应的后端。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RegionBackend {
+    /// X11 `GetImage` 只读取请求的矩形
+    #[cfg_attr(not(all(unix, not(target_os = "macos"))), allow(dead_code))]
+    X11,
+    /// Wayland 下通过桌面门户截图：整屏使用 Screenshot，区域使用 ScreenCast + PipeWire
+    #[cfg_attr(not(all(unix, not(target_os = "macos"))), allow(dead_code))]
+    Portal,
+    /// 截取整个显示器后裁剪
+    FullFrame,
+}
+
+impl RegionBackend {
+    pub fn label(self) -> &'static str {
+        match self {
+            RegionBackend::X11 => "X11 区域读取",
+            RegionBackend::Portal => "桌面门户",
+            RegionBackend::FullFrame => "整屏截取后裁剪",
+        }
+    }
+}
+
+/// 检测当前环境支持的区域截图方式，结果在进程内缓存
+pub fn region_backend() -> RegionBackend {
+    static BACKEND: OnceLock<RegionBackend> = OnceLock::new();
+    *BACKEND.get_or_init(|| {
+        let backend = detect();
+        info!("区域截图后端: {}", backend.label());
+        backend
+    })
+}
+
+fn detect() -> RegionBackend {
+    #[cfg(all(unix, not(target_os = "macos")))]
+    {
+        // Wayland 会话中直接读取屏幕会失败或得到黑屏
+        if x11::is_wayland_session() {
+            return RegionBackend::Portal;
+        }
+        match x11::probe() {
+            Ok(()) => return RegionBackend::X11,
+            Err(e) => info!("X11 区域读取不可用: {}", e),
+        }
+    }
+    RegionBackend::FullFrame
+}
+
+/// 区域截图的错误，调用方可以据此区分参数错误和截图失败
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RegionError {
+    /// 区域超出显示器范围
+    OutOfBounds(String),
+    Capture(String),
+}
+
+impl fmt::Display for RegionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RegionError::OutOfBounds(message) | RegionError::Capture(message) => {
+                f.write_str(message)
+            }
+        }
+    }
+}
+
+impl From<RegionError> for String {
+    fn from(error: RegionError) -> Self {
+        error.to_string()
+    }
+}
+
+/// 截取显示器上的指定区域，坐标是相对于显示器左上角的物理像素
+///
+/// 原生后端失败时回退到整屏截取后裁剪。
+pub fn capture_region(
+    monitor: &Monitor,
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
+) -> Result<RgbaImage, RegionError> {
+    #[cfg(all(unix, not(target_os = "macos")))]
+    match region_backend() {
+        RegionBackend::X11 => match x11::capture_region(monitor, x, y, width, height) {
+            Ok(image) => return Ok(image),
+            Err(e @ RegionError::OutOfBounds(_)) => return Err(e),
+            Err(RegionError::Capture(e)) => {
+                log::warn!("X11 区域读取失败，回退到整屏截取: {}", e)
+            }
+        },
+        RegionBackend::Portal => match portal::capture_frame() {
+            // 共享的是用户在授权对话框中选择的显示器，不一定是要截图的显示器
+            Ok(frame) if shows_monitor(frame.position, monitor) => {
+                return crop::crop_rgba(&frame.image, x, y, width, height)
+                    .map_err(RegionError::OutOfBounds)
+            }
+            Ok(_) => log::warn!("共享的显示器不是要截图的显示器，回退到整屏截取"),
+            Err(e) => log::warn!("ScreenCast 截图失败，回退到整屏截取: {}", e),
+        },
+        RegionBackend::FullFrame => {}
+    }
+
+    let frame = capture_monitor(monitor).map_err(RegionError::Capture)?;
+    crop::crop_rgba(&frame, x, y, width, height).map_err(RegionError::OutOfBounds)
+}
+
+/// 截取整个显示器
+///
+/// Wayland 下使用 Screenshot 门户截取整个桌面后裁剪出这个显示器；失败时再尝试 xcap。
+pub fn capture_monitor(monitor: &Monitor) -> Result<RgbaImage, String> {
+    #[cfg(all(unix, not(target_os = "macos")))]
+    if region_backend() == RegionBackend::Portal {
+        match portal::screenshot().and_then(|desktop| crop_to_monitor(&desktop, monitor)) {
+            Ok(image) => return Ok(image),
+            Err(e) => log::warn!("门户截图失败，尝试直接截图: {}", e),
+        }
+    }
+
+    monitor
+        .capture_image()
+        .map_err(|e| format!("截图失败: {}", e))
+}
+
+/// 显示器在虚拟屏幕中的 (x, y, width, height)
+#[cfg_attr(not(all(unix, not(target_os = "macos"))), allow(dead_code))]
+fn monitor_geometry(monitor: &Monitor) -> Result<(i32, i32, u32, u32), String> {
+    let geometry = || -> xcap::XCapResult<_> {
+        Ok((
+            monitor.x()?,
+            monitor.y()?,
+            monitor.width()?,
+            monitor.height()?,
+        ))
+    };
+    geometry().map_err(|e| format!("获取显示器位置失败: {}", e))
+}
+
+/// ScreenCast 共享的画面是否就是这个显示器；门户没有返回位置时只在单显示器下认为是
+#[cfg(all(unix, not(target_os = "macos")))]
+fn shows_monitor(position: Option<(i32, i32)>, monitor: &Monitor) -> bool {
+    match position {
+        Some(position) => monitor_geometry(monitor).is_ok_and(|(x, y, _, _)| (x, y) == position),
+        None => Monitor::all().is_ok_and(|monitors| monitors.len() == 1),
+    }
+}
+
+/// 从整个桌面的门户截图中裁剪出指定显示器
+#[cfg(all(unix, not(target_os = "macos")))]
+fn crop_to_monitor(desktop: &RgbaImage, monitor: &Monitor) -> Result<RgbaImage, String> {
+    let layout = Monitor::all()
+        .map_err(|e| format!("获取显示器列表失败: {}", e))?
+        .iter()
+        .map(monitor_geometry)
+        .collect::<Result<Vec<_>, _>>()?;
+    let (x, y, width, height) =
+        monitor_in_desktop(desktop.dimensions(), &layout, monitor_geometry(monitor)?)?;
+    crop::crop_rgba(desktop, x, y, width, height)
+}
+
+/// 计算显示器在整个桌面截图中的矩形
+///
+/// 截图尺寸与显示器布局的外接矩形不一致时（例如各显示器缩放不同）无法确定位置，返回错误。
+#[cfg_attr(not(all(unix, not(target_os = "macos"))), allow(dead_code))]
+fn monitor_in_desktop(
+    desktop: (u32, u32),
+    layout: &[(i32, i32, u32, u32)],
+    target: (i32, i32, u32, u32),
+) -> Result<(u32, u32, u32, u32), String> {
+    let left = layout.iter().map(|m| i64::from(m.0)).min();
+    let top = layout.iter().map(|m| i64::from(m.1)).min();
+    let right = layout.iter().map(|m| i64::from(m.0) + i64::from(m.2)).max();
+    let bottom = layout.iter().map(|m| i64::from(m.1) + i64::from(m.3)).max();
+    let (Some(left), Some(top), Some(right), Some(bottom)) = (left, top, right, bottom) else {
+        return Err("没有可用的显示器".to_string());
+    };
+
+    let layout_size = (right - left, bottom - top);
+    if layout_size != (i64::from(desktop.0), i64::from(desktop.1)) {
+        return Err(format!(
+            "门户截图尺寸 {}x{} 与显示器布局 {}x{} 不一致，无法确定显示器位置",
+            desktop.0, desktop.1, layout_size.0, layout_size.1
+        ));
+    }
+    Ok((
+        (i64::from(target.0) - left) as u32,
+        (i64::from(target.1) - top) as u32,
+        target.2,
+        target.3,
+    ))
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+pub(crate) mod x11 {
+    use super::RegionError;
+    use crate::crop;
+    use crate::warm::Grabber;
+    use image::RgbaImage;
+    use log::info;
+    use std::os::fd::AsRawFd;
+    use std::ptr;
+    use x11rb::connection::Connection;
+    use x11rb::protocol::shm::{self, ConnectionExt as _};
+    use x11rb::protocol::xproto::{
+        ConnectionExt, ImageFormat, ImageOrder, Screen, VisualClass, Window,
+    };
+    use x11rb::rust_connection::RustConnection;
+    use xcap::Monitor;
+
+    /// Wayland 会话中的 XWayland 只能读到 X11 窗口，不能用于截屏
+    pub fn is_wayland_session() -> bool {
+        std::env::var_os("WAYLAND_DISPLAY").is_some()
+            || std::env::var("XDG_SESSION_TYPE").is_ok_and(|session| session == "wayland")
+    }
+
+    pub fn connect() -> Result<(RustConnection, usize), String> {
+        if is_wayland_session() {
+            return Err("当前是 Wayland 会话".to_string());
+        }
+        x11rb::connect(None).map_err(|e| format!("无法连接 X 服务器: {}", e))
+    }
+
+    /// 检查根窗口是否是 32 位像素、低字节在前的 TrueColor 格式，即 `GetImage` 返回 BGRX 数据
+    fn check_format(conn: &RustConnection, screen: &Screen) -> Result<(), String> {
+        let setup = conn.setup();
+        if setup.image_byte_order != ImageOrder::LSB_FIRST {
+            return Err("不支持高字节在前的图像格式".to_string());
+        }
+        let bits_per_pixel = setup
+            .pixmap_formats
+            .iter()
+            .find(|format| format.depth == screen.root_depth)
+            .map(|format| format.bits_per_pixel);
+        if bits_per_pixel != Some(32) {
+            return Err(format!("不支持的像素位数: {:?}", bits_per_pixel));
+        }
+        let visual = screen
+            .allowed_depths
+            .iter()
+            .flat_map(|depth| &depth.visuals)
+            .find(|visual| visual.visual_id == screen.root_visual)
+            .ok_or("找不到根窗口的视觉类型".to_string())?;
+        let masks = (visual.red_mask, visual.green_mask, visual.blue_mask);
+        if visual.class != VisualClass::TRUE_COLOR || masks != (0xff0000, 0xff00, 0xff) {
+            return Err("不支持的视觉类型".to_string());
+        }
+        Ok(())
+    }
+
+    pub fn probe() -> Result<(), String> {
+        let (conn, screen_num) = connect()?;
+        check_format(&conn, &conn.setup().roots[screen_num])
+    }
+
+    pub fn capture_region(
+        monitor: &Monitor,
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<RgbaImage, RegionError> {
+        // X11 下显示器的尺寸就是物理像素
+        let (monitor_width, monitor_height) = match (monitor.width(), monitor.height()) {
+            (Ok(width), Ok(height)) => (width, height),
+            (Err(e), _) | (_, Err(e)) => return Err(RegionError::Capture(e.to_string())),
+        };
+        crop::check_bounds(monitor_width, monitor_height, x, y, width, height)
+            .map_err(RegionError::OutOfBounds)?;
+        read_region(monitor, x, y, width, height).map_err(RegionError::Capture)
+    }
+
+    fn read_region(
+        monitor: &Monitor,
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<RgbaImage, String> {
+        // 根窗口坐标 = 显示器在虚拟屏幕中的位置 + 区域在显示器内的偏移
+        let root_x = i64::from(monitor.x().map_err(|e| e.to_string())?) + i64::from(x);
+        let root_y = i64::from(monitor.y().map_err(|e| e.to_string())?) + i64::from(y);
+        let (root_x, root_y, width16, height16) = root_rect(root_x, root_y, width, height)?;
+
+        let (conn, screen_num) = connect()?;
+        let screen = &conn.setup().roots[screen_num];
+        check_format(&conn, screen)?;
+        let reply = conn
+            .get_image(
+                ImageFormat::Z_PIXMAP,
+                screen.root,
+                root_x,
+                root_y,
+                width16,
+                height16,
+                !0,
+            )
+            .map_err(|e| format!("发送 GetImage 请求失败: {}", e))?
+            .reply()
+            .map_err(|e| format!("GetImage 失败: {}", e))?;
+
+        RgbaImage::from_raw(width, height, bgrx_to_rgba(reply.data))
+            .ok_or("GetImage 返回的数据大小不正确".to_string())
+    }
+
+    fn root_rect(x: i64, y: i64, width: u32, height: u32) -> Result<(i16, i16, u16, u16), String> {
+        match (
+            i16::try_from(x),
+            i16::try_from(y),
+            u16::try_from(width),
+            u16::try_from(height),
+        ) {
+            (Ok(x), Ok(y), Ok(width), Ok(height)) => Ok((x, y, width, height)),
+            _ => Err("区域坐标超出 X11 的范围".to_string()),
+        }
+    }
+
+    /// 预热模式使用的 X11 连接，在截图之间保持打开
+    ///
+    /// 服务器支持 MIT-SHM 1.2 时创建一个覆盖整个根窗口的共享内存段，
+    /// 像素直接写入共享内存，不需要经过套接字传输。
+    pub struct WarmConnection {
+        conn: RustConnection,
+        root: Window,
+        shm: Option<ShmSegment>,
+    }
+
+    struct ShmSegment {
+        id: shm::Seg,
+        ptr: *mut libc::c_void,
+        len: usize,
+    }
+
+    // SAFETY: 映射只通过所属的 ShmSegment 访问，在哪个线程上读取或解除映射都一样
+    unsafe impl Send for ShmSegment {}
+
+    impl ShmSegment {
+        fn create(conn: &RustConnection, len: usize) -> Result<Self, String> {
+            let version = conn
+                .shm_query_version()
+                .map_err(|e| format!("发送 MIT-SHM 版本请求失败: {}", e))?
+                .reply()
+                .map_err(|e| format!("X 服务器不支持 MIT-SHM: {}", e))?;
+            if (version.major_version, version.minor_version) < (1, 2) {
+                return Err(format!(
+                    "MIT-SHM 版本 {}.{} 不支持由服务器创建共享内存",
+                    version.major_version, version.minor_version
+                ));
+            }
+
+            let size = u32::try_from(len).map_err(|_| "根窗口过大".to_string())?;
+            let id = conn
+                .generate_id()
+                .map_err(|e| format!("分配 X11 资源 ID 失败: {}", e))?;
+            let reply = conn
+                .shm_create_segment(id, size, false)
+                .map_err(|e| format!("发送创建共享内存请求失败: {}", e))?
+                .reply()
+                .map_err(|e| format!("创建共享内存失败: {}", e))?;
+
+            // SAFETY: shm_fd 是服务器创建的大小为 len 的共享内存，映射在 Drop 中解除
+            let ptr = unsafe {
+                libc::mmap(
+                    ptr::null_mut(),
+                    len,
+                    libc::PROT_READ,
+                    libc::MAP_SHARED,
+                    reply.shm_fd.as_raw_fd(),
+                    0,
+                )
+            };
+            if ptr == libc::MAP_FAILED {
+                return Err(format!(
+                    "映射共享内存失败: {}",
+                    std::io::Error::last_os_error()
+                ));
+            }
+            Ok(Self { id, ptr, len })
+        }
+
+        fn data(&self, len: usize) -> &[u8] {
+            // SAFETY: 调用方保证 len 不超过映射的大小
+            unsafe { std::slice::from_raw_parts(self.ptr.cast::<u8>(), len) }
+        }
+    }
+
+    impl Drop for ShmSegment {
+        fn drop(&mut self) {
+            // SAFETY: ptr 和 len 来自 create 中成功的 mmap
+            unsafe {
+                libc::munmap(self.ptr, self.len);
+            }
+        }
+    }
+
+    impl WarmConnection {
+        pub fn open() -> Result<Self, String> {
+            let (conn, screen_num) = connect()?;
+            let screen = &conn.setup().roots[screen_num];
+            check_format(&conn, screen)?;
+            let root = screen.root;
+            let len =
+                usize::from(screen.width_in_pixels) * usize::from(screen.height_in_pixels) * 4;
+
+            let shm = match ShmSegment::create(&conn, len) {
+                Ok(shm) => Some(shm),
+                Err(e) => {
+                    info!("MIT-SHM 不可用，预热截图使用 GetImage: {}", e);
+                    None
+                }
+            };
+            Ok(Self { conn, root, shm })
+        }
+    }
+
+    impl Grabber for WarmConnection {
+        fn grab(
+            &mut self,
+            x: i32,
+            y: i32,
+            width: u32,
+            height: u32,
+            out: &mut [u8],
+        ) -> Result<(), String> {
+            let (x, y, width16, height16) = root_rect(i64::from(x), i64::from(y), width, height)?;
+
+            match &self.shm {
+                Some(shm) if out.len() <= shm.len => {
+                    self.conn
+                        .shm_get_image(
+                            self.root,
+                            x,
+                            y,
+                            width16,
+                            height16,
+                            !0,
+                            ImageFormat::Z_PIXMAP.into(),
+                            shm.id,
+                            0,
+                        )
+                        .map_err(|e| format!("发送 ShmGetImage 请求失败: {}", e))?
+                        .reply()
+                        .map_err(|e| format!("ShmGetImage 失败: {}", e))?;
+                    bgrx_into_rgba(shm.data(out.len()), out);
+                }
+                _ => {
+                    let reply = self
+                        .conn
+                        .get_image(
+                            ImageFormat::Z_PIXMAP,
+                            self.root,
+                            x,
+                            y,
+                            width16,
+                            height16,
+                            !0,
+                        )
+                        .map_err(|e| format!("发送 GetImage 请求失败: {}", e))?
+                        .reply()
+                        .map_err(|e| format!("GetImage 失败: {}", e))?;
+                    if reply.data.len() != out.len() {
+                        return Err("GetImage 返回的数据大小不正确".to_string());
+                    }
+                    bgrx_into_rgba(&reply.data, out);
+                }
+            }
+            Ok(())
+        }
+    }
+
+    /// 将 BGRX 像素转换为不透明的 RGBA 写入 `out`
+    fn bgrx_into_rgba(data: &[u8], out: &mut [u8]) {
+        for (pixel, rgba) in data.chunks_exact(4).zip(out.chunks_exact_mut(4)) {
+            rgba.copy_from_slice(&[pixel[2], pixel[1], pixel[0], 255]);
+        }
+    }
+
+    /// 将 BGRX 像素原地转换为不透明的 RGBA
+    pub(super) fn bgrx_to_rgba(mut data: Vec<u8>) -> Vec<u8> {
+        for pixel in data.chunks_exact_mut(4) {
+            pixel.swap(0, 2);
+            pixel[3] = 255;
+        }
+        data
+    }
+}
+
+#[cfg(all(test, unix, not(target_os = "macos")))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_monitor_in_desktop() {
+        // 左边的副屏比主屏高，主屏在桌面截图中向右偏移
+        let layout = [(-1920, -200, 1920, 1200), (0, 0, 2560, 1440)];
+        assert_eq!(
+            monitor_in_desktop((4480, 1640), &layout, layout[1]),
+            Ok((1920, 200, 2560, 1440))
+        );
+        assert_eq!(
+            monitor_in_desktop((4480, 1640), &layout, layout[0]),
+            Ok((0, 0, 1920, 1200))
+        );
+        // 缩放后的截图尺寸与布局不一致
+        assert!(monitor_in_desktop((2240, 820), &layout, layout[1]).is_err());
+    }
+
+    #[test]
+    fn test_bgrx_to_rgba() {
+        assert_eq!(
+            x11::bgrx_to_rgba(vec![1, 2, 3, 0, 10, 20, 30, 7]),
+            vec![3, 2, 1, 255, 30, 20, 10, 255]
+        );
+    }
+}
@@ -0,0 +1,702 @@
+use crate::capture::{self, RegionError};
+use crate::encode::{self, Encoding};
+use crate::filename::{self, FilenameContext};
+use crate::metadata::{self, CaptureMetadata};
+use crate::monitors::{self, MonitorInfo};
+use crate::screenshot::{self, CaptureRegion, SavedScreenshot};
+use crate::settings::{self, Settings};
+use crate::storage;
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use image::{ImageFormat, RgbaImage};
+use log::{error, info};
+use serde::{Deserialize, Serialize};
+use std::ffi::OsString;
+use std::io::{Cursor, Write};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use xcap::{Monitor, Window};
+
+/// 成功
+pub const EXIT_OK: i32 = 0;
+/// 截图或编码失败
+pub const EXIT_FAILURE: i32 = 1;
+/// 参数错误（与 clap 的参数错误退出码一致）
+pub const EXIT_USAGE: i32 = 2;
+/// 找不到指定的显示器或窗口
+pub const EXIT_NOT_FOUND: i32 = 3;
+/// 写入文件或标准输出失败
+pub const EXIT_OUTPUT: i32 = 4;
+/// 复制到剪贴板失败
+pub const EXIT_CLIPBOARD: i32 = 5;
+
+/// 命令行模式，不创建任何窗口，执行完毕后以退出码表示结果
+///
+/// 已有实例在运行时，参数会由 `instance::forward` 转发给该实例执行。
+#[derive(Debug, Parser)]
+#[command(
+    name = "screenshot",
+    version,
+    about = "截图工具（不带参数运行时启动图形界面）"
+)]
+pub struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// 截图并保存到文件、标准输出或剪贴板
+    Capture {
+        #[command(subcommand)]
+        target: CaptureTarget,
+        #[command(flatten)]
+        options: CaptureOptions,
+    },
+    /// 列出所有显示器
+    ListMonitors {
+        /// 以 JSON 格式输出
+        #[arg(long)]
+        json: bool,
+    },
+    /// 从标准输入读取 RGBA 像素并保持在剪贴板中，直到被其他内容替换（无界面模式内部使用）
+    #[command(hide = true)]
+    ServeClipboard { width: u32, height: u32 },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum CaptureTarget {
+    /// 整个显示器
+    Full,
+    /// 显示器中的指定区域（物理像素，相对于显示器左上角）
+    Region {
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    },
+    /// 窗口，默认为当前获得焦点的窗口
+    Window {
+        /// 按标题匹配窗口（不区分大小写的子串）
+        #[arg(long)]
+        title: Option<String>,
+    },
+}
+
+/// 截图选项，也作为自动化接口截图方法的参数（字段名相同）
+#[derive(Debug, Default, Args, Deserialize)]
+#[serde(default)]
+pub struct CaptureOptions {
+    /// 显示器序号（从 0 开始，见 list-monitors）或名称，默认为主显示器
+    #[arg(long, global = true)]
+    pub monitor: Option<String>,
+    /// 输出格式
+    #[arg(long, value_enum, default_value_t = OutputFormat::Png, global = true)]
+    pub format: OutputFormat,
+    /// JPEG 质量 (1-100)，默认使用设置中的值
+    #[arg(long, value_parser = clap::value_parser!(u8).range(1..=100), global = true)]
+    pub quality: Option<u8>,
+    /// 输出文件路径，`-` 表示写入标准输出；不指定时按设置保存到截图目录
+    #[arg(long, short, global = true)]
+    pub output: Option<String>,
+    /// 覆盖已存在的同名文件，否则在文件名后追加序号（命令行参数为 `--force`）
+    #[arg(long = "force", global = true)]
+    pub overwrite: bool,
+    /// 截图前等待的秒数
+    #[arg(long, default_value_t = 0, global = true)]
+    pub delay: u64,
+    /// 复制到剪贴板；未同时指定 --output 时不保存文件
+    #[arg(long, global = true)]
+    pub clipboard: bool,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputFormat {
+    #[default]
+    Png,
+    Jpeg,
+    Webp,
+}
+
+impl OutputFormat {
+    pub fn image_format(self) -> ImageFormat {
+        match self {
+            OutputFormat::Png => ImageFormat::Png,
+            OutputFormat::Jpeg => ImageFormat::Jpeg,
+            OutputFormat::Webp => ImageFormat::WebP,
+        }
+    }
+}
+
+/// 带退出码的错误
+#[derive(Debug)]
+pub struct CliError {
+    pub code: i32,
+    pub message: String,
+}
+
+impl CliError {
+    fn new(code: i32, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+        }
+    }
+}
+
+/// 命令的执行环境
+///
+/// 无界面模式直接读写设置文件；由运行中的实例执行转发的命令时使用该实例的设置状态。
+pub struct CommandEnv<'a> {
+    /// 无界面模式：命令执行完进程就退出，复制到剪贴板的内容要交给后台进程继续提供
+    pub headless: bool,
+    pub settings: Settings,
+    /// 相对输出路径的基准目录（发起命令的进程的工作目录）
+    pub cwd: PathBuf,
+    /// 生成下一个文件名计数器的值
+    pub next_counter: &'a dyn Fn(&Settings) -> u64,
+}
+
+/// 截图结果及写入元数据所需的来源信息
+struct Capture {
+    image: RgbaImage,
+    monitor: Option<String>,
+    region: Option<CaptureRegion>,
+    scale_factor: Option<f32>,
+    window_title: Option<String>,
+    app_name: Option<String>,
+}
+
+/// 没有参数，或只有 macOS 启动时附带的 `-psn_` 参数时视为启动图形界面
+pub fn is_gui_launch(args: &[OsString]) -> bool {
+    args.get(1)
+        .map(|arg| arg.to_string_lossy().starts_with("-psn_"))
+        .unwrap_or(true)
+}
+
+impl Cli {
+    /// 截图前等待的时间，转发命令时据此延长等待结果的时间
+    pub fn delay(&self) -> Duration {
+        match &self.command {
+            Command::Capture { options, .. } => Duration::from_secs(options.delay),
+            _ => Duration::ZERO,
+        }
+    }
+}
+
+/// 在后台保持剪贴板内容的内部子命令
+const SERVE_CLIPBOARD: &str = "serve-clipboard";
+
+/// 内部使用的子命令，只在本进程中执行，不转发给运行中的实例
+pub fn is_internal(args: &[OsString]) -> bool {
+    args.get(1).is_some_and(|arg| arg == SERVE_CLIPBOARD)
+}
+
+/// 连接到启动本程序的终端，使命令行模式的输出可以显示出来
+///
+/// 发布版本使用 Windows 图形子系统，启动时没有控制台。终端不会等待图形子系统的程序退出，
+/// 输出可能出现在下一个提示符之后；`--output -` 重定向到文件或管道时不受影响。
+#[cfg(windows)]
+pub fn attach_parent_console() {
+    const ATTACH_PARENT_PROCESS: u32 = u32::MAX;
+    extern "system" {
+        fn AttachConsole(process_id: u32) -> i32;
+    }
+    // 不是从终端启动时没有可以连接的控制台，失败可以忽略
+    unsafe {
+        AttachConsole(ATTACH_PARENT_PROCESS);
+    }
+}
+
+pub fn parse(args: &[OsString]) -> Result<Cli, clap::Error> {
+    Cli::try_parse_from(args)
+}
+
+/// 判断是否以命令行模式运行；是则执行命令并返回退出码，否则返回 `None`
+pub fn run(args: Vec<OsString>) -> Option<i32> {
+    if is_gui_launch(&args) {
+        return None;
+    }
+
+    let cli = match parse(&args) {
+        Ok(cli) => cli,
+        Err(e) => {
+            let _ = e.print();
+            return Some(e.exit_code());
+        }
+    };
+
+    // 命令行模式的日志输出到标准错误，避免覆盖图形界面的日志文件
+    let _ = env_logger::Builder::new()
+        .filter_level(log::LevelFilter::Warn)
+        .parse_default_env()
+        .target(env_logger::Target::Stderr)
+        .try_init();
+
+    let next_counter = |settings: &Settings| {
+        let mut updated = settings.clone();
+        updated.filename_counter += 1;
+        if let Err(e) = settings::save(&updated) {
+            error!("更新文件名计数器失败: {}", e);
+        }
+        updated.filename_counter
+    };
+    let settings = settings::load();
+    encode::configure(settings.encode_threads);
+    #[cfg(all(unix, not(target_os = "macos")))]
+    crate::portal::configure(settings.portal_restore_token.clone(), |token| {
+        let mut settings = settings::load();
+        settings.portal_restore_token = Some(token.to_string());
+        if let Err(e) = settings::save(&settings) {
+            error!("保存屏幕共享恢复令牌失败: {}", e);
+        }
+    });
+    let env = CommandEnv {
+        headless: true,
+        settings,
+        cwd: std::env::current_dir().unwrap_or_default(),
+        next_counter: &next_counter,
+    };
+
+    let mut stdout = std::io::stdout().lock();
+    Some(match execute(cli, &env, &mut stdout) {
+        Ok(_) => EXIT_OK,
+        Err(e) => {
+            error!("{}", e.message);
+            eprintln!("错误: {}", e.message);
+            e.code
+        }
+    })
+}
+
+/// 执行命令，原本输出到标准输出的内容写入 `out`，保存了截图文件时返回其信息
+pub fn execute(
+    cli: Cli,
+    env: &CommandEnv,
+    out: &mut dyn Write,
+) -> Result<Option<SavedScreenshot>, CliError> {
+    match cli.command {
+        Command::Capture { target, options } => capture(target, options, env, out),
+        Command::ListMonitors { json } => list_monitors(json, out).map(|_| None),
+        Command::ServeClipboard { width, height } => serve_clipboard(width, height).map(|_| None),
+    }
+}
+
+/// 无界面模式下复制图像到剪贴板
+///
+/// X11 和 Wayland 的剪贴板内容由持有它的进程提供，进程退出后就会丢失，
+/// 因此交给后台运行的 `serve-clipboard` 子进程继续提供，直到剪贴板被其他内容替换。
+#[cfg(all(unix, not(target_os = "macos")))]
+fn hold_clipboard(image: &RgbaImage) -> Result<(), String> {
+    use std::os::unix::process::CommandExt;
+    use std::process::{Command as Process, Stdio};
+
+    let exe = std::env::current_exe().map_err(|e| format!("无法获取程序路径: {}", e))?;
+    let mut child = Process::new(exe)
+        .arg(SERVE_CLIPBOARD)
+        .arg(image.width().to_string())
+        .arg(image.height().to_string())
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        // 使用单独的进程组，在终端中按 Ctrl+C 不会结束它
+        .process_group(0)
+        .spawn()
+        .map_err(|e| format!("无法启动剪贴板进程: {}", e))?;
+    let mut stdin = child.stdin.take().ok_or("无法写入剪贴板进程".to_string())?;
+    stdin
+        .write_all(image.as_raw())
+        .map_err(|e| format!("无法写入剪贴板进程: {}", e))
+}
+
+/// Windows 和 macOS 的剪贴板内容在进程退出后仍然保留，直接复制
+#[cfg(not(all(unix, not(target_os = "macos"))))]
+fn hold_clipboard(image: &RgbaImage) -> Result<(), String> {
+    screenshot::copy_image_to_clipboard(image)
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+fn serve_clipboard(width: u32, height: u32) -> Result<(), CliError> {
+    use arboard::SetExtLinux;
+    use std::borrow::Cow;
+    use std::io::Read;
+
+    let mut bytes = Vec::new();
+    std::io::stdin()
+        .lock()
+        .read_to_end(&mut bytes)
+        .map_err(|e| CliError::new(EXIT_FAILURE, format!("读取图像数据失败: {}", e)))?;
+    if bytes.len() != width as usize * height as usize * 4 {
+        return Err(CliError::new(EXIT_USAGE, "图像数据大小与尺寸不符"));
+    }
+
+    let mut clipboard = arboard::Clipboard::new()
+        .map_err(|e| CliError::new(EXIT_CLIPBOARD, format!("无法打开剪贴板: {}", e)))?;
+    // 一直阻塞到剪贴板被其他程序的内容替换
+    clipboard
+        .set()
+        .wait()
+        .image(arboard::ImageData {
+            width: width as usize,
+            height: height as usize,
+            bytes: Cow::Owned(bytes),
+        })
+        .map_err(|e| CliError::new(EXIT_CLIPBOARD, format!("无法复制图像到剪贴板: {}", e)))
+}
+
+#[cfg(not(all(unix, not(target_os = "macos"))))]
+fn serve_clipboard(_width: u32, _height: u32) -> Result<(), CliError> {
+    Err(CliError::new(
+        EXIT_USAGE,
+        "只有 X11 和 Wayland 需要后台保持剪贴板",
+    ))
+}
+
+/// 获取所有显示器的信息，序号与 `--monitor` 参数一致
+fn monitor_infos() -> Result<Vec<MonitorInfo>, CliError> {
+    monitors::enumerate().map_err(|e| CliError::new(EXIT_FAILURE, e))
+}
+
+fn list_monitors(json: bool, out: &mut dyn Write) -> Result<(), CliError> {
+    let infos = monitor_infos()?;
+
+    let output = if json {
+        serde_json::to_string_pretty(&infos)
+            .map_err(|e| CliError::new(EXIT_FAILURE, e.to_string()))?
+    } else {
+        infos
+            .iter()
+            .map(|info| {
+                format!(
+                    "{}\t{}\t{}x{}+{}+{}\t{}x{}",
+                    info.index,
+                    info.name,
+                    info.width,
+                    info.height,
+                    info.x,
+                    info.y,
+                    info.scale_factor,
+                    if info.is_primary {
+                        "\t主显示器"
+                    } else {
+                        ""
+                    }
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    };
+    write_output(out, format!("{}\n", output).as_bytes())
+}
+
+/// 按序号或名称选择显示器，未指定时选择主显示器
+fn select_monitor(selector: Option<&str>) -> Result<Monitor, CliError> {
+    let monitors = Monitor::all()
+        .map_err(|e| CliError::new(EXIT_FAILURE, format!("获取显示器列表失败: {}", e)))?;
+
+    let selected = match selector {
+        Some(selector) => match selector.parse::<usize>() {
+            Ok(index) => monitors.into_iter().nth(index),
+            Err(_) => monitors.into_iter().find(|monitor| {
+                monitor
+                    .name()
+                    .is_ok_and(|name| name.eq_ignore_ascii_case(selector))
+            }),
+        },
+        None => {
+            let primary = monitors
+                .iter()
+                .position(|monitor| monitor.is_primary().unwrap_or(false))
+                .unwrap_or(0);
+            monitors.into_iter().nth(primary)
+        }
+    };
+    selected.ok_or_else(|| {
+        CliError::new(
+            EXIT_NOT_FOUND,
+            format!("未找到显示器: {}", selector.unwrap_or("主显示器")),
+        )
+    })
+}
+
+fn capture_monitor(
+    selector: Option<&str>,
+    region: Option<CaptureRegion>,
+) -> Result<Capture, CliError> {
+    let monitor = select_monitor(selector)?;
+    let image = match region {
+        Some(region) => {
+            capture::capture_region(&monitor, region.x, region.y, region.width, region.height)
+                .map_err(|e| match e {
+                    RegionError::OutOfBounds(message) => CliError::new(EXIT_USAGE, message),
+                    RegionError::Capture(message) => CliError::new(EXIT_FAILURE, message),
+                })?
+        }
+        None => capture::capture_monitor(&monitor).map_err(|e| CliError::new(EXIT_FAILURE, e))?,
+    };
+
+    Ok(Capture {
+        image,
+        monitor: monitor.name().ok(),
+        region,
+        scale_factor: monitor.scale_factor().ok(),
+        window_title: None,
+        app_name: None,
+    })
+}
+
+fn capture_window(title: Option<&str>) -> Result<Capture, CliError> {
+    let windows = Window::all()
+        .map_err(|e| CliError::new(EXIT_FAILURE, format!("获取窗口列表失败: {}", e)))?;
+
+    let window = match title {
+        Some(title) => {
+            let title = title.to_lowercase();
+            windows.into_iter().find(|window| {
+                !window.is_minimized().unwrap_or(true)
+                    && window
+                        .title()
+                        .is_ok_and(|window_title| window_title.to_lowercase().contains(&title))
+            })
+        }
+        None => windows.into_iter().find(|window| {
+            window.is_focused().unwrap_or(false) && !window.is_minimized().unwrap_or(true)
+        }),
+    }
+    .ok_or_else(|| CliError::new(EXIT_NOT_FOUND, "未找到匹配的窗口"))?;
+
+    let image = window
+        .capture_image()
+        .map_err(|e| CliError::new(EXIT_FAILURE, format!("窗口截图失败: {}", e)))?;
+    let monitor = window.current_monitor().ok();
+
+    Ok(Capture {
+        image,
+        monitor: monitor.as_ref().and_then(|monitor| monitor.name().ok()),
+        region: None,
+        scale_factor: monitor.and_then(|monitor| monitor.scale_factor().ok()),
+        window_title: window.title().ok(),
+        app_name: window.app_name().ok(),
+    })
+}
+
+fn encode(image: &RgbaImage, format: OutputFormat, quality: u8) -> Result<Vec<u8>, String> {
+    match format {
+        OutputFormat::Jpeg => {
+            encode::encode(Encoding::Jpeg { quality }, image).map(|(data, _)| data)
+        }
+        OutputFormat::Png => encode::encode(Encoding::Png, image).map(|(data, _)| data),
+        OutputFormat::Webp => {
+            let mut buffer = Vec::new();
+            image
+                .write_to(&mut Cursor::new(&mut buffer), format.image_format())
+                .map_err(|e| format!("图像编码失败: {}", e))?;
+            Ok(buffer)
+        }
+    }
+}
+
+fn write_output(out: &mut dyn Write, data: &[u8]) -> Result<(), CliError> {
+    out.write_all(data)
+        .and_then(|_| out.flush())
+        .map_err(|e| CliError::new(EXIT_OUTPUT, format!("写入标准输出失败: {}", e)))
+}
+
+/// 未指定输出路径时，按设置中的目录和文件名模板保存
+fn default_output(env: &CommandEnv, context: &mut FilenameContext) -> Result<PathBuf, CliError> {
+    let settings = &env.settings;
+    let dir =
+        screenshot::get_screenshots_dir(settings).map_err(|e| CliError::new(EXIT_OUTPUT, e))?;
+
+    if settings.filename_template.contains("{counter") {
+        context.counter = (env.next_counter)(settings);
+    }
+    let name = filename::render(&settings.filename_template, context)
+        .map_err(|e| CliError::new(EXIT_FAILURE, e))?;
+    let path = filename::create_unique(&dir, &name, false)
+        .map_err(|e| CliError::new(EXIT_OUTPUT, format!("保存文件失败: {}", e)))?
+        .0;
+    Ok(path)
+}
+
+/// 执行截图，`options.output` 为 `-` 时图像数据写入 `out`，否则写入保存的文件路径
+pub fn capture(
+    target: CaptureTarget,
+    options: CaptureOptions,
+    env: &CommandEnv,
+    out: &mut dyn Write,
+) -> Result<Option<SavedScreenshot>, CliError> {
+    let settings = &env.settings;
+
+    if options.delay > 0 {
+        info!("{} 秒后开始截图", options.delay);
+        std::thread::sleep(Duration::from_secs(options.delay));
+    }
+
+    let capture = match target {
+        CaptureTarget::Full => capture_monitor(options.monitor.as_deref(), None)?,
+        CaptureTarget::Region {
+            x,
+            y,
+            width,
+            height,
+        } => capture_monitor(
+            options.monitor.as_deref(),
+            Some(CaptureRegion {
+                x,
+                y,
+                width,
+                height,
+            }),
+        )?,
+        CaptureTarget::Window { title } => capture_window(title.as_deref())?,
+    };
+
+    let save_file = options.output.is_some() || !options.clipboard;
+    let mut context = FilenameContext::new(storage::extension_for(options.format.image_format()));
+    context.monitor = capture.monitor.clone();
+    context.window_title = capture.window_title.clone();
+    context.app_name = capture.app_name.clone();
+    context.width = Some(capture.image.width());
+    context.height = Some(capture.image.height());
+
+    let mut saved = None;
+    if save_file {
+        let quality = options.quality.unwrap_or(settings.jpeg_quality);
+        let data = encode(&capture.image, options.format, quality)
+            .map_err(|e| CliError::new(EXIT_FAILURE, e))?;
+        let format = options.format.image_format();
+        let data = if settings.strip_metadata {
+            data
+        } else {
+            let capture_metadata = CaptureMetadata {
+                captured_at: Some(context.now.to_rfc3339()),
+                monitor: capture.monitor.clone(),
+                region: capture.region,
+                scale_factor: capture.scale_factor,
+                window_title: capture.window_title.clone(),
+                app_name: capture.app_name.clone(),
+                app_version: Some(env!("CARGO_PKG_VERSION").to_string()),
+            };
+            metadata::embed(&data, format, &capture_metadata).unwrap_or(data)
+        };
+
+        match options.output.as_deref() {
+            Some("-") => write_output(out, &data)?,
+            output => {
+                let path = match output {
+                    Some(output) => env.cwd.join(output),
+                    None => default_output(env, &mut context)?,
+                };
+                let dir = path
+                    .parent()
+                    .filter(|dir| !dir.as_os_str().is_empty())
+                    .unwrap_or(Path::new("."));
+                let name = path
+                    .file_name()
+                    .ok_or_else(|| {
+                        CliError::new(EXIT_USAGE, format!("无效的输出路径: {:?}", path))
+                    })?
+                    .to_string_lossy();
+                let path = storage::write_atomic(dir, &name, &data, options.overwrite)
+                    .map_err(|e| CliError::new(EXIT_OUTPUT, format!("保存文件失败: {}", e)))?;
+                write_output(out, format!("{}\n", path.display()).as_bytes())?;
+
+                saved = Some(SavedScreenshot {
+                    path: path.to_string_lossy().to_string(),
+                    captured_at: context.now.to_rfc3339(),
+                    monitor: capture.monitor.clone(),
+                    region: capture.region,
+                    window_title: capture.window_title.clone(),
+                    app_name: capture.app_name.clone(),
+                    format: context.extension.clone(),
+                    width: context.width,
+                    height: context.height,
+                    action_chain: None,
+                });
+            }
+        }
+    }
+
+    if options.clipboard {
+        let result = if env.headless {
+            hold_clipboard(&capture.image)
+        } else {
+            screenshot::copy_image_to_clipboard(&capture.image)
+        };
+        result.map_err(|e| CliError::new(EXIT_CLIPBOARD, e))?;
+    }
+
+    Ok(saved)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Cli, clap::Error> {
+        Cli::try_parse_from(std::iter::once("screenshot").chain(args.iter().copied()))
+    }
+
+    #[test]
+    fn test_gui_launch_without_arguments() {
+        assert!(!is_internal(&["screenshot".into(), "capture".into()]));
+        assert!(is_internal(&["screenshot".into(), SERVE_CLIPBOARD.into()]));
+        assert!(parse(&[SERVE_CLIPBOARD, "4", "3"]).is_ok());
+        assert!(is_gui_launch(&["screenshot".into()]));
+        assert!(is_gui_launch(&["screenshot".into(), "-psn_0_12345".into()]));
+        assert!(!is_gui_launch(&[
+            "screenshot".into(),
+            "list-monitors".into()
+        ]));
+    }
+
+    #[test]
+    fn test_parse_capture_options() {
+        let cli = parse(&[
+            "capture", "region", "10", "20", "300", "200", "--format", "jpeg", "--output", "-",
+            "--delay", "2",
+        ])
+        .unwrap();
+        let Command::Capture { target, options } = cli.command else {
+            panic!("expected capture command");
+        };
+        assert!(matches!(
+            target,
+            CaptureTarget::Region {
+                x: 10,
+                y: 20,
+                width: 300,
+                height: 200
+            }
+        ));
+        assert_eq!(options.format, OutputFormat::Jpeg);
+        assert_eq!(options.output.as_deref(), Some("-"));
+        assert_eq!(options.delay, 2);
+        assert!(!options.overwrite);
+
+        let cli = parse(&["capture", "full", "--output", "shot.png", "--force"]).unwrap();
+        let Command::Capture { options, .. } = cli.command else {
+            panic!("expected capture command");
+        };
+        assert!(options.overwrite);
+
+        assert!(parse(&["capture", "full", "--quality", "0"]).is_err());
+        assert_eq!(
+            parse(&["capture", "everything"]).unwrap_err().exit_code(),
+            EXIT_USAGE
+        );
+    }
+
+    #[test]
+    fn test_encode_formats() {
+        let image = RgbaImage::new(4, 3);
+        for format in [OutputFormat::Png, OutputFormat::Jpeg, OutputFormat::Webp] {
+            let data = encode(&image, format, 90).unwrap();
+            assert_eq!(
+                storage::sniff_image_format(&data).unwrap(),
+                format.image_format()
+            );
+        }
+    }
+}
@@ -0,0 +1,119 @@
+use image::RgbaImage;
+use rayon::prelude::*;
+
+// 区域截图只需要整屏帧中的一小块，这里按行从原始缓冲区读取目标区域，
+// 只为结果分配一次内存，不再复制整帧。
+
+/// 校验区域是否完全位于帧内，使用溢出检查避免极大坐标绕过判断
+pub fn check_bounds(
+    frame_width: u32,
+    frame_height: u32,
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
+) -> Result<(), String> {
+    if width == 0 || height == 0 {
+        return Err("截图区域的宽高必须大于 0".to_string());
+    }
+    let right = x.checked_add(width);
+    let bottom = y.checked_add(height);
+    match (right, bottom) {
+        (Some(right), Some(bottom)) if right <= frame_width && bottom <= frame_height => Ok(()),
+        _ => Err(format!(
+            "指定区域超出屏幕范围: screen={}x{}, region=({}, {}, {}x{})",
+            frame_width, frame_height, x, y, width, height
+        )),
+    }
+}
+
+/// 区域在帧缓冲区中每一行的字节切片
+fn rows(
+    frame: &RgbaImage,
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
+) -> impl IndexedParallelIterator<Item = &[u8]> {
+    let stride = frame.width() as usize * 4;
+    let start = x as usize * 4;
+    let len = width as usize * 4;
+    frame
+        .as_raw()
+        .par_chunks_exact(stride)
+        .skip(y as usize)
+        .take(height as usize)
+        .map(move |row| &row[start..start + len])
+}
+
+/// 裁剪 RGBA 区域，结果是唯一的一次内存分配
+pub fn crop_rgba(
+    frame: &RgbaImage,
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
+) -> Result<RgbaImage, String> {
+    check_bounds(frame.width(), frame.height(), x, y, width, height)?;
+
+    let mut data = vec![0u8; width as usize * height as usize * 4];
+    data.par_chunks_exact_mut(width as usize * 4)
+        .zip(rows(frame, x, y, width, height))
+        .for_each(|(dst, src)| dst.copy_from_slice(src));
+    RgbaImage::from_raw(width, height, data).ok_or("无法创建图像".to_string())
+}
+
+/// 裁剪区域并同时去掉 alpha 通道，得到可直接交给 JPEG 编码器的 RGB 数据
+pub fn crop_rgb(
+    frame: &RgbaImage,
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
+) -> Result<Vec<u8>, String> {
+    check_bounds(frame.width(), frame.height(), x, y, width, height)?;
+
+    let mut data = vec![0u8; width as usize * height as usize * 3];
+    data.par_chunks_exact_mut(width as usize * 3)
+        .zip(rows(frame, x, y, width, height))
+        .for_each(|(dst, src)| {
+            for (rgb, rgba) in dst.chunks_exact_mut(3).zip(src.chunks_exact(4)) {
+                rgb.copy_from_slice(&rgba[..3]);
+            }
+        });
+    Ok(data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::imageops;
+
+    fn frame() -> RgbaImage {
+        RgbaImage::from_fn(17, 9, |x, y| {
+            image::Rgba([x as u8, y as u8, (x * y) as u8, 200])
+        })
+    }
+
+    #[test]
+    fn test_crop_matches_imageops() {
+        let frame = frame();
+        let expected = imageops::crop_imm(&frame, 3, 2, 10, 5).to_image();
+
+        assert_eq!(crop_rgba(&frame, 3, 2, 10, 5).unwrap(), expected);
+        let rgb: Vec<u8> = expected
+            .pixels()
+            .flat_map(|pixel| [pixel[0], pixel[1], pixel[2]])
+            .collect();
+        assert_eq!(crop_rgb(&frame, 3, 2, 10, 5).unwrap(), rgb);
+        assert_eq!(crop_rgba(&frame, 0, 0, 17, 9).unwrap(), frame);
+    }
+
+    #[test]
+    fn test_check_bounds() {
+        assert!(check_bounds(17, 9, 7, 0, 10, 9).is_ok());
+        assert!(check_bounds(17, 9, 8, 0, 10, 9).is_err());
+        assert!(check_bounds(17, 9, 0, 0, 0, 9).is_err());
+        assert!(check_bounds(17, 9, u32::MAX, 0, 2, 1).is_err());
+    }
+}
@@ -0,0 +1,565 @@
+use base64::engine::general_purpose;
+use base64::write::EncoderStringWriter;
+use flate2::{Compress, Compression, FlushCompress, Status};
+use image::RgbaImage;
+use log::{info, warn};
+use rayon::{Scope, ThreadPool, ThreadPoolBuilder};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+// 大尺寸截图按行切成若干分块，在线程池中并行编码，再按顺序拼接成一个文件：
+// PNG 的每个分块单独 deflate，除最后一块外以同步刷新结束，拼接后仍是一个合法的 zlib 流；
+// JPEG 的每个分块单独编码，拼接时在分块之间插入重启标记（RST），解码器在重启标记处重置 DC 预测。
+// 前面的分块编码完成后立即写出，Base64 也随写入逐段生成，不再保留完整的二进制缓冲区。
+
+/// 每个分块的行数，是所有 JPEG 色度采样方式下 MCU 高度（最大 32）的整数倍
+const STRIP_ROWS: u32 = 128;
+
+/// JPEG 重启间隔（DRI）能表示的最大 MCU 数
+const MAX_RESTART_INTERVAL: u32 = u16::MAX as u32;
+
+/// zlib 流头部（deflate，32K 窗口，默认压缩级别）
+const ZLIB_HEADER: [u8; 2] = [0x78, 0x9c];
+
+const PNG_SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1a, b'\n'];
+
+/// 编码线程数，0 表示使用全部 CPU 核心
+static THREADS: AtomicUsize = AtomicUsize::new(0);
+
+/// 设置编码线程数，与当前线程池不同时，下一次编码前重新创建线程池
+pub fn configure(threads: usize) {
+    THREADS.store(threads, Ordering::Relaxed);
+}
+
+/// 当前的编码线程池和创建它时设置的线程数；创建失败时为 `None`，使用全局线程池
+static POOL: Mutex<Option<(usize, Option<Arc<ThreadPool>>)>> = Mutex::new(None);
+
+/// 按设置的线程数取得线程池，正在进行的编码继续使用替换前的线程池直到完成
+fn pool() -> Option<Arc<ThreadPool>> {
+    let threads = THREADS.load(Ordering::Relaxed);
+    let mut current = POOL.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some((built, pool)) = &*current {
+        if *built == threads {
+            return pool.clone();
+        }
+    }
+
+    let pool = match ThreadPoolBuilder::new()
+        .num_threads(threads)
+        .thread_name(|index| format!("encode-{}", index))
+        .build()
+    {
+        Ok(pool) => {
+            info!("编码线程池已创建, 线程数: {}", pool.current_num_threads());
+            Some(Arc::new(pool))
+        }
+        Err(e) => {
+            warn!("创建编码线程池失败，使用全局线程池: {}", e);
+            None
+        }
+    };
+    *current = Some((threads, pool.clone()));
+    pool
+}
+
+/// 编码使用的线程数
+pub fn threads() -> usize {
+    pool().map_or_else(rayon::current_num_threads, |pool| {
+        pool.current_num_threads()
+    })
+}
+
+/// 输出格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Encoding {
+    Jpeg { quality: u8 },
+    Png,
+}
+
+impl Encoding {
+    pub fn name(self) -> &'static str {
+        match self {
+            Encoding::Jpeg { .. } => "jpeg",
+            Encoding::Png => "png",
+        }
+    }
+}
+
+/// 一次编码的统计信息，随截图结果一起返回
+#[derive(Debug, Clone, Serialize)]
+pub struct EncodeStats {
+    pub format: &'static str,
+    pub threads: usize,
+    pub strips: usize,
+    /// 编码后的字节数（Base64 之前）
+    pub bytes: usize,
+    /// 从开始编码到最后一个字节写出的耗时（毫秒），包括 Base64
+    pub latency_ms: f64,
+}
+
+/// 记录写入字节数的 Writer
+struct Counter<W> {
+    inner: W,
+    bytes: usize,
+}
+
+impl<W: Write> Write for Counter<W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.bytes += written;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// 编码图像并写入 `out`
+pub fn encode_to<W: Write>(
+    encoding: Encoding,
+    image: &RgbaImage,
+    out: W,
+) -> Result<EncodeStats, String> {
+    let start = Instant::now();
+    let mut out = Counter {
+        inner: out,
+        bytes: 0,
+    };
+    let strips = match encoding {
+        Encoding::Jpeg { quality } => write_jpeg(image, quality, &mut out)?,
+        Encoding::Png => write_png(image, &mut out)?,
+    };
+    out.flush()
+        .map_err(|e| format!("写入编码结果失败: {}", e))?;
+    Ok(EncodeStats {
+        format: encoding.name(),
+        threads: threads(),
+        strips,
+        bytes: out.bytes,
+        latency_ms: start.elapsed().as_secs_f64() * 1000.0,
+    })
+}
+
+/// 编码为字节
+pub fn encode(encoding: Encoding, image: &RgbaImage) -> Result<(Vec<u8>, EncodeStats), String> {
+    let mut buffer = Vec::new();
+    let stats = encode_to(encoding, image, &mut buffer)?;
+    Ok((buffer, stats))
+}
+
+/// 编码并逐段生成 Base64 字符串
+pub fn encode_base64(
+    encoding: Encoding,
+    image: &RgbaImage,
+) -> Result<(String, EncodeStats), String> {
+    let mut writer = EncoderStringWriter::new(&general_purpose::STANDARD);
+    let stats = encode_to(encoding, image, &mut writer)?;
+    Ok((writer.into_inner(), stats))
+}
+
+/// 在线程池中运行 `op`，当前线程不占用线程池的工作线程，可以阻塞等待分块结果
+fn in_pool<'scope, R>(op: impl FnOnce(&Scope<'scope>) -> R) -> R {
+    match pool() {
+        Some(pool) => pool.in_place_scope(op),
+        None => rayon::in_place_scope(op),
+    }
+}
+
+/// 并行处理 `count` 个分块，按分块顺序把结果交给 `sink`
+///
+/// 某个分块完成时，只要它之前的分块都已写出就立即写出，不等待全部完成。
+fn for_each_ordered<T, F, S>(count: usize, work: F, mut sink: S) -> Result<(), String>
+where
+    T: Send,
+    F: Fn(usize) -> Result<T, String> + Sync,
+    S: FnMut(usize, T) -> Result<(), String>,
+{
+    in_pool(|scope| {
+        let (sender, receiver) = mpsc::channel();
+        let work = &work;
+        for index in 0..count {
+            let sender = sender.clone();
+            scope.spawn(move |_| {
+                // 出错后接收端已丢弃，发送失败可以忽略
+                let _ = sender.send((index, work(index)));
+            });
+        }
+        drop(sender);
+
+        let mut pending = BTreeMap::new();
+        let mut next = 0;
+        for (index, result) in receiver {
+            pending.insert(index, result);
+            while let Some(result) = pending.remove(&next) {
+                sink(next, result?)?;
+                next += 1;
+            }
+        }
+        Ok(())
+    })
+}
+
+/// 按行切分的分块 (起始行, 行数)
+fn strips(height: u32, rows: u32) -> Vec<(u32, u32)> {
+    (0..height)
+        .step_by(rows as usize)
+        .map(|start| (start, rows.min(height - start)))
+        .collect()
+}
+
+fn write_all(out: &mut impl Write, data: &[u8]) -> Result<(), String> {
+    out.write_all(data)
+        .map_err(|e| format!("写入编码结果失败: {}", e))
+}
+
+fn write_chunk(out: &mut impl Write, kind: &[u8; 4], data: &[&[u8]]) -> Result<(), String> {
+    let len: usize = data.iter().map(|part| part.len()).sum();
+    let len = u32::try_from(len).map_err(|_| "PNG 数据块过大".to_string())?;
+    let mut crc = crc32fast::Hasher::new();
+    crc.update(kind);
+    write_all(out, &len.to_be_bytes())?;
+    write_all(out, kind)?;
+    for part in data {
+        crc.update(part);
+        write_all(out, part)?;
+    }
+    write_all(out, &crc.finalize().to_be_bytes())
+}
+
+fn paeth(a: u8, b: u8, c: u8) -> u8 {
+    let p = i16::from(a) + i16::from(b) - i16::from(c);
+    let (pa, pb, pc) = (
+        (p - i16::from(a)).abs(),
+        (p - i16::from(b)).abs(),
+        (p - i16::from(c)).abs(),
+    );
+    if pa <= pb && pa <= pc {
+        a
+    } else if pb <= pc {
+        b
+    } else {
+        c
+    }
+}
+
+/// 对一行应用 PNG 过滤器，`out` 不包含过滤类型字节
+fn filter_row(filter: u8, row: &[u8], prev: &[u8], out: &mut [u8]) {
+    const BPP: usize = 4;
+    for i in 0..row.len() {
+        let a = if i >= BPP { row[i - BPP] } else { 0 };
+        let b = prev[i];
+        let c = if i >= BPP { prev[i - BPP] } else { 0 };
+        out[i] = row[i].wrapping_sub(match filter {
+            0 => 0,
+            1 => a,
+            2 => b,
+            3 => ((u16::from(a) + u16::from(b)) / 2) as u8,
+            _ => paeth(a, b, c),
+        });
+    }
+}
+
+/// 按最小绝对值之和为每行选择过滤器，返回过滤后的数据
+fn filter_strip(image: &RgbaImage, start: u32, rows: u32) -> Vec<u8> {
+    let stride = image.width() as usize * 4;
+    let raw = image.as_raw();
+    let zero = vec![0u8; stride];
+    let mut candidate = vec![0u8; stride];
+    let mut filtered = Vec::with_capacity((stride + 1) * rows as usize);
+
+    for y in start..start + rows {
+        let offset = y as usize * stride;
+        let row = &raw[offset..offset + stride];
+        let prev = if y == 0 {
+            &zero[..]
+        } else {
+            &raw[offset - stride..offset]
+        };
+
+        let mut best = (u64::MAX, 0u8);
+        for filter in 0..5 {
+            filter_row(filter, row, prev, &mut candidate);
+            let score: u64 = candidate
+                .iter()
+                .map(|&byte| u64::from((byte as i8).unsigned_abs()))
+                .sum();
+            if score < best.0 {
+                best = (score, filter);
+            }
+        }
+        filtered.push(best.1);
+        let len = filtered.len();
+        filtered.resize(len + stride, 0);
+        filter_row(best.1, row, prev, &mut filtered[len..]);
+    }
+    filtered
+}
+
+/// 压缩一个分块，非最后一块以同步刷新结束，保证拼接后的流在字节边界上衔接
+fn deflate(data: &[u8], last: bool) -> Result<Vec<u8>, String> {
+    let mut compress = Compress::new(Compression::default(), false);
+    let flush = if last {
+        FlushCompress::Finish
+    } else {
+        FlushCompress::Sync
+    };
+    let mut output = Vec::with_capacity(data.len() / 4 + 1024);
+    loop {
+        if output.capacity() - output.len() < 1024 {
+            output.reserve(output.capacity().max(64 * 1024));
+        }
+        let input = &data[compress.total_in() as usize..];
+        let status = compress
+            .compress_vec(input, &mut output, flush)
+            .map_err(|e| format!("PNG 压缩失败: {}", e))?;
+        let drained =
+            compress.total_in() as usize == data.len() && output.len() < output.capacity();
+        if status == Status::StreamEnd || (!last && drained) {
+            return Ok(output);
+        }
+    }
+}
+
+fn write_png(image: &RgbaImage, out: &mut impl Write) -> Result<usize, String> {
+    let (width, height) = image.dimensions();
+    if width == 0 || height == 0 {
+        return Err("图像尺寸不能为 0".to_string());
+    }
+    let strips = strips(height, STRIP_ROWS);
+
+    write_all(out, &PNG_SIGNATURE)?;
+    let mut header = Vec::with_capacity(13);
+    header.extend_from_slice(&width.to_be_bytes());
+    header.extend_from_slice(&height.to_be_bytes());
+    // 8 位 RGBA，默认压缩方式和过滤方式，不隔行
+    header.extend_from_slice(&[8, 6, 0, 0, 0]);
+    write_chunk(out, b"IHDR", &[&header])?;
+
+    let last = strips.len() - 1;
+    // zlib 流的校验和按分块顺序累加，和写出顺序一致
+    let mut adler = adler2::Adler32::new();
+    for_each_ordered(
+        strips.len(),
+        |index| {
+            let (start, rows) = strips[index];
+            let filtered = filter_strip(image, start, rows);
+            let compressed = deflate(&filtered, index == last)?;
+            Ok((compressed, filtered))
+        },
+        |index, (compressed, filtered)| {
+            adler.write_slice(&filtered);
+            let prefix: &[u8] = if index == 0 { &ZLIB_HEADER } else { &[] };
+            let checksum = adler.checksum().to_be_bytes();
+            let suffix: &[u8] = if index == last { &checksum } else { &[] };
+            write_chunk(out, b"IDAT", &[prefix, &compressed, suffix])
+        },
+    )?;
+
+    write_chunk(out, b"IEND", &[])?;
+    Ok(strips.len())
+}
+
+/// 分块 JPEG 中与拼接有关的位置
+struct JpegLayout {
+    /// SOF 段中高度字段的偏移
+    height_offset: usize,
+    /// SOS 段的起始偏移
+    sos_start: usize,
+    /// 熵编码数据的起始偏移
+    data_start: usize,
+    /// MCU 的宽高（像素）
+    mcu: (u32, u32),
+}
+
+fn parse_jpeg(data: &[u8]) -> Result<JpegLayout, String> {
+    let invalid = || "无法解析 JPEG 分块".to_string();
+    if !data.starts_with(&[0xff, 0xd8]) || !data.ends_with(&[0xff, 0xd9]) {
+        return Err(invalid());
+    }
+    let mut pos = 2;
+    let mut frame = None;
+    loop {
+        let marker = *data.get(pos + 1).ok_or_else(invalid)?;
+        if data[pos] != 0xff {
+            return Err(invalid());
+        }
+        let len = usize::from(u16::from_be_bytes([
+            *data.get(pos + 2).ok_or_else(invalid)?,
+            *data.get(pos + 3).ok_or_else(invalid)?,
+        ]));
+        let segment = data.get(pos + 4..pos + 2 + len).ok_or_else(invalid)?;
+        match marker {
+            // 基线或扩展的顺序 DCT
+            0xc0 | 0xc1 => {
+                let components = usize::from(*segment.get(5).ok_or_else(invalid)?);
+                let sampling = segment.get(6..6 + components * 3).ok_or_else(invalid)?;
+                let (h, v) = sampling.chunks_exact(3).fold((1, 1), |(h, v), component| {
+                    (
+                        h.max(u32::from(component[1] >> 4)),
+                        v.max(u32::from(component[1] & 0x0f)),
+                    )
+                });
+                frame = Some((pos + 5, (h * 8, v * 8)));
+            }
+            0xda => {
+                let (height_offset, mcu) = frame.ok_or_else(invalid)?;
+                return Ok(JpegLayout {
+                    height_offset,
+                    sos_start: pos,
+                    data_start: pos + 2 + len,
+                    mcu,
+                });
+            }
+            _ => {}
+        }
+        pos += 2 + len;
+    }
+}
+
+/// JPEG 分块的行数，保证重启间隔在任何采样方式下都不超过 DRI 的上限
+fn jpeg_strip_rows(width: u32) -> u32 {
+    let max_mcu_columns = width.div_ceil(8);
+    let rows = MAX_RESTART_INTERVAL / max_mcu_columns * 8;
+    (rows / 32 * 32).clamp(32, STRIP_ROWS)
+}
+
+fn encode_jpeg_strip(
+    image: &RgbaImage,
+    start: u32,
+    rows: u32,
+    quality: u8,
+) -> Result<Vec<u8>, String> {
+    let stride = image.width() as usize * 4;
+    let data = &image.as_raw()[start as usize * stride..(start + rows) as usize * stride];
+    let mut buffer = Vec::new();
+    jpeg_encoder::Encoder::new(&mut buffer, quality)
+        .encode(
+            data,
+            image.width() as u16,
+            rows as u16,
+            jpeg_encoder::ColorType::Rgba,
+        )
+        .map_err(|e| format!("图像JPEG编码失败: {}", e))?;
+    Ok(buffer)
+}
+
+fn write_jpeg(image: &RgbaImage, quality: u8, out: &mut impl Write) -> Result<usize, String> {
+    let (width, height) = image.dimensions();
+    if width == 0 || height == 0 || width > u32::from(u16::MAX) || height > u32::from(u16::MAX) {
+        return Err(format!("不支持的 JPEG 图像尺寸: {}x{}", width, height));
+    }
+    let strip_rows = jpeg_strip_rows(width);
+    let strips = strips(height, strip_rows);
+    if strips.len() == 1 {
+        write_all(out, &encode_jpeg_strip(image, 0, height, quality)?)?;
+        return Ok(1);
+    }
+
+    for_each_ordered(
+        strips.len(),
+        |index| {
+            let (start, rows) = strips[index];
+            encode_jpeg_strip(image, start, rows, quality)
+        },
+        |index, strip| {
+            let layout = parse_jpeg(&strip)?;
+            let entropy = &strip[layout.data_start..strip.len() - 2];
+            if index > 0 {
+                // 每个分块是一个重启间隔，之间用 RST0..RST7 循环分隔
+                return write_all(out, &[0xff, 0xd0 + ((index - 1) % 8) as u8])
+                    .and_then(|_| write_all(out, entropy));
+            }
+
+            let (mcu_width, mcu_height) = layout.mcu;
+            if !strip_rows.is_multiple_of(mcu_height) {
+                return Err(format!(
+                    "不支持的 JPEG 采样方式: MCU {}x{}",
+                    mcu_width, mcu_height
+                ));
+            }
+            let interval = width.div_ceil(mcu_width) * (strip_rows / mcu_height);
+            let interval = u16::try_from(interval).map_err(|_| "JPEG 重启间隔过大".to_string())?;
+
+            // 第一个分块的文件头改为完整高度，并在扫描开始前插入重启间隔定义
+            let mut header = strip[..layout.sos_start].to_vec();
+            header[layout.height_offset..layout.height_offset + 2]
+                .copy_from_slice(&(height as u16).to_be_bytes());
+            let [high, low] = interval.to_be_bytes();
+            header.extend_from_slice(&[0xff, 0xdd, 0x00, 0x04, high, low]);
+            write_all(out, &header)?;
+            write_all(out, &strip[layout.sos_start..layout.data_start])?;
+            write_all(out, entropy)
+        },
+    )?;
+
+    write_all(out, &[0xff, 0xd9])?;
+    Ok(strips.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use base64::Engine as _;
+    use std::io::Read;
+
+    fn sample(width: u32, height: u32) -> RgbaImage {
+        RgbaImage::from_fn(width, height, |x, y| {
+            image::Rgba([(x * 7) as u8, (y * 3) as u8, ((x + y) / 5) as u8, 255])
+        })
+    }
+
+    #[test]
+    fn test_png_zlib_checksum() {
+        let image = sample(200, 300);
+        let (png, _) = encode(Encoding::Png, &image).unwrap();
+
+        // 拼接全部 IDAT 块，zlib 解码时会校验 Adler-32
+        let mut zlib = Vec::new();
+        let mut offset = PNG_SIGNATURE.len();
+        while offset < png.len() {
+            let len = u32::from_be_bytes(png[offset..offset + 4].try_into().unwrap()) as usize;
+            if &png[offset + 4..offset + 8] == b"IDAT" {
+                zlib.extend_from_slice(&png[offset + 8..offset + 8 + len]);
+            }
+            offset += 12 + len;
+        }
+        let mut raw = Vec::new();
+        flate2::read::ZlibDecoder::new(zlib.as_slice())
+            .read_to_end(&mut raw)
+            .unwrap();
+        assert_eq!(raw.len(), 300 * (1 + 200 * 4));
+    }
+
+    #[test]
+    fn test_parallel_png_and_jpeg_decode() {
+        // 高度不是分块行数的整数倍，覆盖最后一个不完整的分块
+        let image = sample(333, 300);
+
+        let (png, stats) = encode(Encoding::Png, &image).unwrap();
+        assert_eq!(stats.strips, 3);
+        assert_eq!(stats.bytes, png.len());
+        let decoded = image::load_from_memory_with_format(&png, image::ImageFormat::Png).unwrap();
+        assert_eq!(decoded.to_rgba8(), image);
+
+        let (jpeg, stats) = encode_base64(Encoding::Jpeg { quality: 90 }, &image).unwrap();
+        assert_eq!(stats.strips, 3);
+        let jpeg = general_purpose::STANDARD.decode(jpeg).unwrap();
+        let decoded = image::load_from_memory_with_format(&jpeg, image::ImageFormat::Jpeg)
+            .unwrap()
+            .to_rgb8();
+        assert_eq!(decoded.dimensions(), (333, 300));
+        // 有损压缩，只比较平均误差
+        let error: u64 = decoded
+            .pixels()
+            .zip(image.pixels())
+            .flat_map(|(a, b)| (0..3).map(move |i| u64::from(a[i].abs_diff(b[i]))))
+            .sum();
+        assert!(error / (333 * 300 * 3) < 8);
+    }
+}
@@ -0,0 +1,304 @@
+use chrono::format::{Item, StrftimeItems};
+use chrono::{DateTime, Local};
+use std::fs::{File, OpenOptions};
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// 默认文件名模板，与早期版本的 `screenshot_%Y%m%d_%H%M%S.jpg` 保持一致
+pub const DEFAULT_TEMPLATE: &str = "screenshot_{datetime}.{ext}";
+
+/// 文件名的最大长度（字节），留出去重后缀的空间
+const MAX_FILENAME_LEN: usize = 200;
+
+/// 自动去重时最多尝试的后缀数量
+const MAX_DEDUP_ATTEMPTS: u32 = 10_000;
+
+/// Windows 下不能作为文件名的保留名称
+const RESERVED_NAMES: [&str; 22] = [
+    "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8",
+    "COM9", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+/// 渲染文件名模板所需的截图信息
+///
+/// 支持的变量：
+/// - `{date}` `{time}` `{datetime}`，或 `{date:%Y年%m月}` 形式的自定义 chrono 格式
+/// - `{counter}`，或 `{counter:4}` 补零到指定位数
+/// - `{monitor}` `{window}` `{app}` 显示器名称、窗口标题、应用名称
+/// - `{width}` `{height}` 图像尺寸
+/// - `{ext}` 图像格式对应的扩展名
+#[derive(Debug, Clone)]
+pub struct FilenameContext {
+    pub now: DateTime<Local>,
+    pub counter: u64,
+    pub monitor: Option<String>,
+    pub window_title: Option<String>,
+    pub app_name: Option<String>,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub extension: String,
+}
+
+impl FilenameContext {
+    pub fn new(extension: impl Into<String>) -> Self {
+        Self {
+            now: Local::now(),
+            counter: 0,
+            monitor: None,
+            window_title: None,
+            app_name: None,
+            width: None,
+            height: None,
+            extension: extension.into(),
+        }
+    }
+}
+
+/// 模板中的一个片段
+enum Segment<'a> {
+    Literal(&'a str),
+    Token { name: &'a str, arg: Option<&'a str> },
+}
+
+fn parse(template: &str) -> Result<Vec<Segment<'_>>, String> {
+    let mut segments = Vec::new();
+    let mut rest = template;
+
+    while let Some(start) = rest.find(['{', '}']) {
+        if rest[start..].starts_with('}') {
+            return Err(format!("模板中存在多余的 '}}': {}", template));
+        }
+        if start > 0 {
+            segments.push(Segment::Literal(&rest[..start]));
+        }
+        let end = rest[start..]
+            .find('}')
+            .map(|end| start + end)
+            .ok_or_else(|| format!("模板中的 '{{' 没有闭合: {}", template))?;
+        let body = &rest[start + 1..end];
+        let (name, arg) = match body.split_once(':') {
+            Some((name, arg)) => (name, Some(arg)),
+            None => (body, None),
+        };
+        segments.push(Segment::Token { name, arg });
+        rest = &rest[end + 1..];
+    }
+    if !rest.is_empty() {
+        segments.push(Segment::Literal(rest));
+    }
+
+    Ok(segments)
+}
+
+fn format_time(now: &DateTime<Local>, format: &str) -> Result<String, String> {
+    if StrftimeItems::new(format).any(|item| item == Item::Error) {
+        return Err(format!("无效的时间格式: {}", format));
+    }
+    Ok(now.format(format).to_string())
+}
+
+/// 检查模板语法及变量名是否有效
+pub fn validate_template(template: &str) -> Result<(), String> {
+    if template.trim().is_empty() {
+        return Err("文件名模板不能为空".to_string());
+    }
+    if template.contains(['/', '\\']) {
+        return Err("文件名模板不能包含路径分隔符".to_string());
+    }
+    render(template, &FilenameContext::new("png")).map(|_| ())
+}
+
+/// 按模板生成文件名，变量值和最终结果都会经过 `sanitize` 处理
+///
+/// 模板中没有 `{ext}` 时会自动追加扩展名。
+pub fn render(template: &str, context: &FilenameContext) -> Result<String, String> {
+    let mut name = String::new();
+    let mut has_ext = false;
+
+    for segment in parse(template)? {
+        match segment {
+            Segment::Literal(text) => name.push_str(text),
+            Segment::Token { name: token, arg } => {
+                let value = match (token, arg) {
+                    ("date", None) => format_time(&context.now, "%Y-%m-%d")?,
+                    ("time", None) => format_time(&context.now, "%H-%M-%S")?,
+                    ("datetime", None) => format_time(&context.now, "%Y%m%d_%H%M%S")?,
+                    ("date" | "time" | "datetime", Some(format)) => {
+                        format_time(&context.now, format)?
+                    }
+                    ("counter", None) => context.counter.to_string(),
+                    ("counter", Some(width)) => {
+                        let width: usize = width
+                            .parse()
+                            .map_err(|_| format!("无效的计数器位数: {}", width))?;
+                        format!("{:0width$}", context.counter, width = width)
+                    }
+                    ("monitor", None) => context.monitor.clone().unwrap_or_default(),
+                    ("window", None) => context.window_title.clone().unwrap_or_default(),
+                    ("app", None) => context.app_name.clone().unwrap_or_default(),
+                    ("width", None) => context.width.map(|w| w.to_string()).unwrap_or_default(),
+                    ("height", None) => context.height.map(|h| h.to_string()).unwrap_or_default(),
+                    ("ext", None) => {
+                        has_ext = true;
+                        context.extension.clone()
+                    }
+                    _ => return Err(format!("未知的模板变量: {{{}}}", token)),
+                };
+                name.push_str(&sanitize(&value));
+            }
+        }
+    }
+
+    if !has_ext {
+        name.push('.');
+        name.push_str(&context.extension);
+    }
+
+    let name = sanitize(&name);
+    if name.is_empty() || name.starts_with('.') {
+        return Err(format!("模板生成的文件名无效: {}", template));
+    }
+    Ok(name)
+}
+
+/// 将非法路径字符替换为 `_`，去掉末尾的点和空格，并限制长度
+pub fn sanitize(name: &str) -> String {
+    let mut sanitized: String = name
+        .chars()
+        .map(|c| match c {
+            '<' | '>' | ':' | '"' | '/' | '\\' | '|' | '?' | '*' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect();
+
+    if sanitized.len() > MAX_FILENAME_LEN {
+        let mut end = MAX_FILENAME_LEN;
+        while !sanitized.is_char_boundary(end) {
+            end -= 1;
+        }
+        sanitized.truncate(end);
+    }
+
+    let trimmed_len = sanitized.trim_end_matches(['.', ' ']).len();
+    sanitized.truncate(trimmed_len);
+
+    let stem = sanitized.split('.').next().unwrap_or_default();
+    if RESERVED_NAMES
+        .iter()
+        .any(|reserved| reserved.eq_ignore_ascii_case(stem))
+    {
+        sanitized.insert(0, '_');
+    }
+
+    sanitized
+}
+
+/// 在目录中创建文件，文件已存在时追加 `_1`、`_2` 等后缀
+///
+/// 使用 `create_new` 打开文件，因此并发保存也不会互相覆盖。`overwrite` 为 true 时直接覆盖同名文件。
+pub fn create_unique(dir: &Path, filename: &str, overwrite: bool) -> io::Result<(PathBuf, File)> {
+    let path = dir.join(filename);
+    if overwrite {
+        let file = File::create(&path)?;
+        return Ok((path, file));
+    }
+
+    let (stem, extension) = match filename.rsplit_once('.') {
+        Some((stem, extension)) if !stem.is_empty() => (stem, Some(extension)),
+        _ => (filename, None),
+    };
+
+    for attempt in 0..MAX_DEDUP_ATTEMPTS {
+        let candidate = match (attempt, extension) {
+            (0, _) => path.clone(),
+            (n, Some(extension)) => dir.join(format!("{}_{}.{}", stem, n, extension)),
+            (n, None) => dir.join(format!("{}_{}", stem, n)),
+        };
+        match OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&candidate)
+        {
+            Ok(file) => return Ok((candidate, file)),
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(e),
+        }
+    }
+
+    Err(io::Error::new(
+        io::ErrorKind::AlreadyExists,
+        format!("无法为 {} 生成不重复的文件名", filename),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn context() -> FilenameContext {
+        FilenameContext {
+            now: Local.with_ymd_and_hms(2024, 5, 6, 7, 8, 9).unwrap(),
+            counter: 7,
+            monitor: Some("DELL U2720Q".to_string()),
+            window_title: Some("main.rs: src/app".to_string()),
+            app_name: Some("Code".to_string()),
+            width: Some(1920),
+            height: Some(1080),
+            extension: "png".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_render_template() {
+        let context = context();
+        assert_eq!(
+            render(DEFAULT_TEMPLATE, &context).unwrap(),
+            "screenshot_20240506_070809.png"
+        );
+        assert_eq!(
+            render("{app}-{window}-{counter:3}-{width}x{height}", &context).unwrap(),
+            "Code-main.rs_ src_app-007-1920x1080.png"
+        );
+        assert_eq!(
+            render("{date:%Y年%m月}_{monitor}.{ext}", &context).unwrap(),
+            "2024年05月_DELL U2720Q.png"
+        );
+    }
+
+    #[test]
+    fn test_render_rejects_invalid_templates() {
+        let context = context();
+        assert!(render("{unknown}", &context).is_err());
+        assert!(render("shot_{date", &context).is_err());
+        assert!(render("shot_}", &context).is_err());
+        assert!(render("{date:%Q}", &context).is_err());
+        assert!(validate_template("../{date}").is_err());
+    }
+
+    #[test]
+    fn test_sanitize() {
+        assert_eq!(sanitize("a<b>c:d\"e|f?g*h"), "a_b_c_d_e_f_g_h");
+        assert_eq!(sanitize("name. . "), "name");
+        assert_eq!(sanitize("con.png"), "_con.png");
+        assert!(sanitize(&"长".repeat(100)).len() <= MAX_FILENAME_LEN);
+    }
+
+    #[test]
+    fn test_create_unique_never_overwrites() {
+        let dir =
+            std::env::temp_dir().join(format!("screenshot-filename-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let (first, _) = create_unique(&dir, "shot.png", false).unwrap();
+        let (second, _) = create_unique(&dir, "shot.png", false).unwrap();
+        let (third, _) = create_unique(&dir, "shot.png", true).unwrap();
+
+        assert_eq!(first, dir.join("shot.png"));
+        assert_eq!(second, dir.join("shot_1.png"));
+        assert_eq!(third, dir.join("shot.png"));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,722 @@
+use crate::screenshot::{self, CaptureRegion, SavedScreenshot};
+use crate::settings;
+use crate::storage;
+use chrono::{DateTime, Local};
+use log::{error, info, warn};
+use rusqlite::types::Value as SqlValue;
+use rusqlite::{params, params_from_iter, Connection, OptionalExtension, Row};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tauri::{command, AppHandle, Manager, State};
+
+/// 数据库结构版本，保存在 `PRAGMA user_version` 中
+const SCHEMA_VERSION: i32 = 2;
+
+/// 单次查询最多返回的记录数
+const MAX_PAGE_SIZE: u32 = 500;
+
+/// 标签拼接时使用的分隔符（ASCII Unit Separator，不会出现在正常标签中）
+const TAG_SEPARATOR: char = '\u{1f}';
+
+/// 一条截图历史记录
+#[derive(Debug, Clone, Serialize)]
+pub struct HistoryEntry {
+    pub id: i64,
+    pub path: String,
+    pub captured_at: String,
+    pub monitor: Option<String>,
+    pub region: Option<CaptureRegion>,
+    pub window_title: Option<String>,
+    pub format: String,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub file_size: u64,
+    pub hash: String,
+    pub tags: Vec<String>,
+    pub starred: bool,
+    /// 文件已在应用外被删除或移动
+    pub missing: bool,
+}
+
+/// 历史记录查询条件，所有条件均为可选
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct HistoryQuery {
+    /// 匹配路径、窗口标题、显示器名称和标签
+    pub text: Option<String>,
+    pub tag: Option<String>,
+    pub starred: Option<bool>,
+    pub include_missing: bool,
+    /// RFC 3339 格式的起止时间
+    pub from: Option<String>,
+    pub to: Option<String>,
+    pub limit: Option<u32>,
+    pub offset: Option<u32>,
+}
+
+/// 对账结果
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ReconcileReport {
+    /// 在截图目录中发现但没有记录的文件
+    pub added: Vec<String>,
+    /// 记录存在但文件已不存在
+    pub missing: Vec<String>,
+    /// 之前标记为缺失、现在又出现的文件
+    pub restored: Vec<String>,
+}
+
+/// 截图历史数据库
+pub struct History {
+    conn: Connection,
+}
+
+fn db_error(e: rusqlite::Error) -> String {
+    error!("历史记录数据库错误: {}", e);
+    format!("历史记录数据库错误: {}", e)
+}
+
+/// 转义 LIKE 中的通配符
+fn like_pattern(text: &str) -> String {
+    let escaped = text
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_");
+    format!("%{}%", escaped)
+}
+
+const SELECT_COLUMNS: &str = "SELECT id, path, captured_at, monitor, region_x, region_y, \
+     region_width, region_height, window_title, format, width, height, file_size, hash, \
+     starred, missing, \
+     (SELECT group_concat(tag, char(31)) FROM tags WHERE capture_id = captures.id) \
+     FROM captures";
+
+fn entry_from_row(row: &Row<'_>) -> rusqlite::Result<HistoryEntry> {
+    let region = match (
+        row.get::<_, Option<u32>>(4)?,
+        row.get::<_, Option<u32>>(5)?,
+        row.get::<_, Option<u32>>(6)?,
+        row.get::<_, Option<u32>>(7)?,
+    ) {
+        (Some(x), Some(y), Some(width), Some(height)) => Some(CaptureRegion {
+            x,
+            y,
+            width,
+            height,
+        }),
+        _ => None,
+    };
+    let tags = row
+        .get::<_, Option<String>>(16)?
+        .map(|tags| tags.split(TAG_SEPARATOR).map(str::to_string).collect())
+        .unwrap_or_default();
+
+    Ok(HistoryEntry {
+        id: row.get(0)?,
+        path: row.get(1)?,
+        captured_at: row.get(2)?,
+        monitor: row.get(3)?,
+        region,
+        window_title: row.get(8)?,
+        format: row.get(9)?,
+        width: row.get(10)?,
+        height: row.get(11)?,
+        file_size: row.get(12)?,
+        hash: row.get(13)?,
+        starred: row.get(14)?,
+        missing: row.get(15)?,
+        tags,
+    })
+}
+
+impl History {
+    /// 打开（或创建）数据库并升级表结构
+    pub fn open(path: &Path) -> Result<Self, String> {
+        let conn = Connection::open(path).map_err(db_error)?;
+        Self::with_connection(conn)
+    }
+
+    #[cfg(test)]
+    fn open_in_memory() -> Result<Self, String> {
+        Self::with_connection(Connection::open_in_memory().map_err(db_error)?)
+    }
+
+    fn with_connection(conn: Connection) -> Result<Self, String> {
+        conn.pragma_update(None, "foreign_keys", true)
+            .map_err(db_error)?;
+
+        let version: i32 = conn
+            .pragma_query_value(None, "user_version", |row| row.get(0))
+            .map_err(db_error)?;
+        if version < 1 {
+            conn.execute_batch(
+                "CREATE TABLE IF NOT EXISTS captures (
+                    id INTEGER PRIMARY KEY AUTOINCREMENT,
+                    path TEXT NOT NULL UNIQUE,
+                    captured_at TEXT NOT NULL,
+                    monitor TEXT,
+                    region_x INTEGER,
+                    region_y INTEGER,
+                    region_width INTEGER,
+                    region_height INTEGER,
+                    window_title TEXT,
+                    format TEXT NOT NULL,
+                    width INTEGER,
+                    height INTEGER,
+                    file_size INTEGER NOT NULL,
+                    hash TEXT NOT NULL,
+                    starred INTEGER NOT NULL DEFAULT 0,
+                    missing INTEGER NOT NULL DEFAULT 0
+                );
+                CREATE INDEX IF NOT EXISTS idx_captures_captured_at ON captures(captured_at);
+                CREATE TABLE IF NOT EXISTS tags (
+                    capture_id INTEGER NOT NULL REFERENCES captures(id) ON DELETE CASCADE,
+                    tag TEXT NOT NULL,
+                    PRIMARY KEY (capture_id, tag)
+                );",
+            )
+            .map_err(db_error)?;
+        }
+        if version < 2 {
+            // v1 -> v2: 缩略图服务按内容哈希查找原图
+            conn.execute_batch("CREATE INDEX IF NOT EXISTS idx_captures_hash ON captures(hash);")
+                .map_err(db_error)?;
+        }
+        if version < SCHEMA_VERSION {
+            conn.pragma_update(None, "user_version", SCHEMA_VERSION)
+                .map_err(db_error)?;
+            info!("历史记录数据库已升级到版本 {}", SCHEMA_VERSION);
+        }
+
+        Ok(Self { conn })
+    }
+
+    /// 记录一次保存的截图，同一路径被覆盖保存时更新原记录
+    pub fn record(&self, saved: &SavedScreenshot, data: &[u8]) -> Result<i64, String> {
+        let region = saved.region;
+        self.conn
+            .query_row(
+                "INSERT INTO captures (path, captured_at, monitor, region_x, region_y,
+                    region_width, region_height, window_title, format, width, height,
+                    file_size, hash)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)
+                 ON CONFLICT(path) DO UPDATE SET
+                    captured_at = excluded.captured_at,
+                    monitor = excluded.monitor,
+                    region_x = excluded.region_x,
+                    region_y = excluded.region_y,
+                    region_width = excluded.region_width,
+                    region_height = excluded.region_height,
+                    window_title = excluded.window_title,
+                    format = excluded.format,
+                    width = excluded.width,
+                    height = excluded.height,
+                    file_size = excluded.file_size,
+                    hash = excluded.hash,
+                    missing = 0
+                 RETURNING id",
+                params![
+                    saved.path,
+                    saved.captured_at,
+                    saved.monitor,
+                    region.map(|r| r.x),
+                    region.map(|r| r.y),
+                    region.map(|r| r.width),
+                    region.map(|r| r.height),
+                    saved.window_title,
+                    saved.format,
+                    saved.width,
+                    saved.height,
+                    data.len() as u64,
+                    storage::content_hash(data),
+                ],
+                |row| row.get(0),
+            )
+            .map_err(db_error)
+    }
+
+    pub fn get(&self, id: i64) -> Result<Option<HistoryEntry>, String> {
+        self.conn
+            .query_row(
+                &format!("{} WHERE id = ?1", SELECT_COLUMNS),
+                [id],
+                entry_from_row,
+            )
+            .optional()
+            .map_err(db_error)
+    }
+
+    /// 按条件查询，按截图时间倒序返回
+    pub fn search(&self, query: &HistoryQuery) -> Result<Vec<HistoryEntry>, String> {
+        let mut conditions = Vec::new();
+        let mut values: Vec<SqlValue> = Vec::new();
+
+        if !query.include_missing {
+            conditions.push("missing = 0".to_string());
+        }
+        if let Some(text) = query.text.as_deref().filter(|text| !text.trim().is_empty()) {
+            values.push(SqlValue::Text(like_pattern(text.trim())));
+            let n = values.len();
+            conditions.push(format!(
+                "(path LIKE ?{n} ESCAPE '\\' OR window_title LIKE ?{n} ESCAPE '\\' \
+                 OR monitor LIKE ?{n} ESCAPE '\\' OR EXISTS (SELECT 1 FROM tags \
+                 WHERE capture_id = captures.id AND tag LIKE ?{n} ESCAPE '\\'))"
+            ));
+        }
+        if let Some(tag) = &query.tag {
+            values.push(SqlValue::Text(tag.clone()));
+            conditions.push(format!(
+                "EXISTS (SELECT 1 FROM tags WHERE capture_id = captures.id AND tag = ?{})",
+                values.len()
+            ));
+        }
+        if let Some(starred) = query.starred {
+            values.push(SqlValue::Integer(starred as i64));
+            conditions.push(format!("starred = ?{}", values.len()));
+        }
+        if let Some(from) = &query.from {
+            values.push(SqlValue::Text(from.clone()));
+            conditions.push(format!("captured_at >= ?{}", values.len()));
+        }
+        if let Some(to) = &query.to {
+            values.push(SqlValue::Text(to.clone()));
+            conditions.push(format!("captured_at <= ?{}", values.len()));
+        }
+
+        let mut sql = SELECT_COLUMNS.to_string();
+        if !conditions.is_empty() {
+            sql.push_str(" WHERE ");
+            sql.push_str(&conditions.join(" AND "));
+        }
+        sql.push_str(&format!(
+            " ORDER BY captured_at DESC, id DESC LIMIT {} OFFSET {}",
+            query.limit.unwrap_or(100).min(MAX_PAGE_SIZE),
+            query.offset.unwrap_or(0)
+        ));
+
+        let mut statement = self.conn.prepare(&sql).map_err(db_error)?;
+        let entries = statement
+            .query_map(params_from_iter(values), entry_from_row)
+            .map_err(db_error)?
+            .collect::<rusqlite::Result<Vec<_>>>()
+            .map_err(db_error)?;
+        Ok(entries)
+    }
+
+    /// 替换记录的全部标签
+    pub fn set_tags(&mut self, id: i64, tags: &[String]) -> Result<(), String> {
+        let tx = self.conn.transaction().map_err(db_error)?;
+        tx.execute("DELETE FROM tags WHERE capture_id = ?1", [id])
+            .map_err(db_error)?;
+        for tag in tags
+            .iter()
+            .map(|tag| tag.trim())
+            .filter(|tag| !tag.is_empty())
+        {
+            tx.execute(
+                "INSERT OR IGNORE INTO tags (capture_id, tag) VALUES (?1, ?2)",
+                params![id, tag],
+            )
+            .map_err(db_error)?;
+        }
+        tx.commit().map_err(db_error)
+    }
+
+    pub fn set_starred(&self, id: i64, starred: bool) -> Result<(), String> {
+        self.conn
+            .execute(
+                "UPDATE captures SET starred = ?2 WHERE id = ?1",
+                params![id, starred],
+            )
+            .map_err(db_error)?;
+        Ok(())
+    }
+
+    pub fn delete(&self, id: i64) -> Result<(), String> {
+        self.conn
+            .execute("DELETE FROM captures WHERE id = ?1", [id])
+            .map_err(db_error)?;
+        Ok(())
+    }
+
+    /// 按内容哈希查找仍然存在的截图文件
+    pub fn path_for_hash(&self, hash: &str) -> Result<Option<String>, String> {
+        self.conn
+            .query_row(
+                "SELECT path FROM captures WHERE hash = ?1 AND missing = 0 \
+                 ORDER BY id DESC LIMIT 1",
+                [hash],
+                |row| row.get(0),
+            )
+            .optional()
+            .map_err(db_error)
+    }
+
+    pub fn delete_by_path(&self, path: &str) -> Result<(), String> {
+        self.conn
+            .execute("DELETE FROM captures WHERE path = ?1", [path])
+            .map_err(db_error)?;
+        Ok(())
+    }
+
+    /// 对比数据库与截图目录：标记已被外部删除的文件，补录在外部添加的图片
+    pub fn reconcile(&self, screenshots_dir: &Path) -> Result<ReconcileReport, String> {
+        let mut report = ReconcileReport::default();
+
+        let known: Vec<(i64, String, bool)> = {
+            let mut statement = self
+                .conn
+                .prepare("SELECT id, path, missing FROM captures")
+                .map_err(db_error)?;
+            let rows = statement
+                .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))
+                .map_err(db_error)?
+                .collect::<rusqlite::Result<Vec<_>>>()
+                .map_err(db_error)?;
+            rows
+        };
+
+        for (id, path, missing) in &known {
+            let exists = Path::new(path).is_file();
+            if !exists && !missing {
+                self.conn
+                    .execute("UPDATE captures SET missing = 1 WHERE id = ?1", [id])
+                    .map_err(db_error)?;
+                report.missing.push(path.clone());
+            } else if exists && *missing {
+                self.conn
+                    .execute("UPDATE captures SET missing = 0 WHERE id = ?1", [id])
+                    .map_err(db_error)?;
+                report.restored.push(path.clone());
+            }
+        }
+
+        let known_paths: HashSet<&str> = known.iter().map(|(_, path, _)| path.as_str()).collect();
+        let mut files = Vec::new();
+        collect_image_files(screenshots_dir, &mut files);
+
+        for file in files {
+            let path = file.to_string_lossy().to_string();
+            if known_paths.contains(path.as_str()) {
+                continue;
+            }
+            let Ok(data) = fs::read(&file) else {
+                continue;
+            };
+            let Ok(format) = storage::sniff_image_format(&data) else {
+                continue;
+            };
+            let dimensions = image::ImageReader::with_format(std::io::Cursor::new(&data), format)
+                .into_dimensions()
+                .ok();
+            let captured_at = fs::metadata(&file)
+                .and_then(|metadata| metadata.modified())
+                .map(|modified| DateTime::<Local>::from(modified).to_rfc3339())
+                .unwrap_or_else(|_| Local::now().to_rfc3339());
+
+            let saved = SavedScreenshot {
+                path: path.clone(),
+                captured_at,
+                monitor: None,
+                region: None,
+                window_title: None,
+                app_name: None,
+                format: storage::extension_for(format).to_string(),
+                width: dimensions.map(|(width, _)| width),
+                height: dimensions.map(|(_, height)| height),
+                action_chain: None,
+            };
+            self.record(&saved, &data)?;
+            report.added.push(path);
+        }
+
+        Ok(report)
+    }
+}
+
+/// 递归收集目录中的图片文件，跳过隐藏文件（包括保存时的临时文件）
+fn collect_image_files(dir: &Path, files: &mut Vec<PathBuf>) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if entry.file_name().to_string_lossy().starts_with('.') {
+            continue;
+        }
+        if path.is_dir() {
+            collect_image_files(&path, files);
+        } else if path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .and_then(image::ImageFormat::from_extension)
+            .is_some()
+        {
+            files.push(path);
+        }
+    }
+}
+
+/// 托管在 Tauri 中的历史记录状态
+pub struct HistoryState(Mutex<History>);
+
+impl HistoryState {
+    /// 删除指定路径的记录，供托盘等应用内删除截图的入口使用
+    pub fn delete_by_path(&self, path: &str) -> Result<(), String> {
+        self.0.lock().unwrap().delete_by_path(path)
+    }
+
+    pub fn path_for_hash(&self, hash: &str) -> Result<Option<String>, String> {
+        self.0.lock().unwrap().path_for_hash(hash)
+    }
+
+    pub fn get(&self, id: i64) -> Result<Option<HistoryEntry>, String> {
+        self.0.lock().unwrap().get(id)
+    }
+}
+
+fn get_history_db_path() -> Option<PathBuf> {
+    crate::get_app_config_dir().map(|dir| dir.join("history.db"))
+}
+
+/// 打开历史数据库，并在后台执行一次对账
+pub fn init(app: &AppHandle) -> Result<(), String> {
+    let path = get_history_db_path().ok_or("无法获取配置目录".to_string())?;
+    let history = History::open(&path)?;
+    info!("历史记录数据库已打开: {:?}", path);
+    app.manage(HistoryState(Mutex::new(history)));
+
+    let handle = app.clone();
+    std::thread::spawn(move || match reconcile(&handle) {
+        Ok(report) => info!(
+            "历史记录对账完成: 新增 {}, 缺失 {}, 恢复 {}",
+            report.added.len(),
+            report.missing.len(),
+            report.restored.len()
+        ),
+        Err(e) => error!("历史记录对账失败: {}", e),
+    });
+
+    Ok(())
+}
+
+/// 记录刚保存的截图，由 `screenshot::announce_saved` 调用
+pub fn record_saved(app: &AppHandle, saved: &SavedScreenshot) {
+    let Some(state) = app.try_state::<HistoryState>() else {
+        return;
+    };
+    let data = match fs::read(&saved.path) {
+        Ok(data) => data,
+        Err(e) => {
+            warn!("读取截图文件失败，未记录历史: {}, {}", saved.path, e);
+            return;
+        }
+    };
+    if let Err(e) = state.0.lock().unwrap().record(saved, &data) {
+        error!("记录截图历史失败: {}", e);
+    }
+}
+
+fn reconcile(app: &AppHandle) -> Result<ReconcileReport, String> {
+    let screenshots_dir = screenshot::get_screenshots_dir(&settings::current(app))?;
+    let state = app.state::<HistoryState>();
+    let history = state.0.lock().unwrap();
+    history.reconcile(&screenshots_dir)
+}
+
+/// 分页列出历史记录
+#[command]
+pub fn list_history(
+    state: State<'_, HistoryState>,
+    limit: Option<u32>,
+    offset: Option<u32>,
+) -> Result<Vec<HistoryEntry>, String> {
+    state.0.lock().unwrap().search(&HistoryQuery {
+        limit,
+        offset,
+        ..Default::default()
+    })
+}
+
+/// 按条件搜索历史记录
+#[command]
+pub fn search_history(
+    state: State<'_, HistoryState>,
+    query: HistoryQuery,
+) -> Result<Vec<HistoryEntry>, String> {
+    info!("搜索截图历史: {:?}", query);
+    state.0.lock().unwrap().search(&query)
+}
+
+/// 设置历史记录的标签（替换原有标签）
+#[command]
+pub fn tag_history_entry(
+    state: State<'_, HistoryState>,
+    id: i64,
+    tags: Vec<String>,
+) -> Result<Option<HistoryEntry>, String> {
+    let mut history = state.0.lock().unwrap();
+    history.set_tags(id, &tags)?;
+    history.get(id)
+}
+
+/// 标记或取消标记为星标
+#[command]
+pub fn star_history_entry(
+    state: State<'_, HistoryState>,
+    id: i64,
+    starred: bool,
+) -> Result<Option<HistoryEntry>, String> {
+    let history = state.0.lock().unwrap();
+    history.set_starred(id, starred)?;
+    history.get(id)
+}
+
+/// 删除历史记录，`delete_file` 为 true 时同时删除截图文件
+///
+/// 只删除截图目录和允许的保存目录中的普通文件。
+#[command]
+pub fn delete_history_entry(
+    app: AppHandle,
+    state: State<'_, HistoryState>,
+    id: i64,
+    delete_file: bool,
+) -> Result<(), String> {
+    let history = state.0.lock().unwrap();
+    let Some(entry) = history.get(id)? else {
+        return Err(format!("历史记录不存在: {}", id));
+    };
+
+    if delete_file && Path::new(&entry.path).exists() {
+        screenshot::remove_saved_file(&settings::current(&app), Path::new(&entry.path))?;
+    }
+    history.delete(id)
+}
+
+/// 手动触发对账
+#[command]
+pub fn reconcile_history(app: AppHandle) -> Result<ReconcileReport, String> {
+    reconcile(&app)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn saved(path: &str, window_title: &str) -> SavedScreenshot {
+        SavedScreenshot {
+            path: path.to_string(),
+            captured_at: Local::now().to_rfc3339(),
+            monitor: Some("Built-in".to_string()),
+            region: Some(CaptureRegion {
+                x: 1,
+                y: 2,
+                width: 30,
+                height: 40,
+            }),
+            window_title: Some(window_title.to_string()),
+            app_name: None,
+            format: "png".to_string(),
+            width: Some(30),
+            height: Some(40),
+            action_chain: None,
+        }
+    }
+
+    #[test]
+    fn test_record_search_tag_and_star() {
+        let mut history = History::open_in_memory().unwrap();
+        let first = history
+            .record(&saved("/tmp/a.png", "Terminal"), b"a")
+            .unwrap();
+        let second = history
+            .record(&saved("/tmp/b.png", "Browser 100%"), b"b")
+            .unwrap();
+
+        // 覆盖保存同一路径时复用原记录
+        assert_eq!(
+            history
+                .record(&saved("/tmp/a.png", "Terminal"), b"c")
+                .unwrap(),
+            first
+        );
+
+        history
+            .set_tags(first, &["bug".to_string(), " ".to_string()])
+            .unwrap();
+        history.set_starred(second, true).unwrap();
+
+        let entry = history.get(first).unwrap().unwrap();
+        assert_eq!(entry.tags, vec!["bug"]);
+        assert_eq!(entry.hash, storage::content_hash(b"c"));
+        assert_eq!(entry.region.unwrap().width, 30);
+
+        let search = |query: HistoryQuery| -> Vec<i64> {
+            history
+                .search(&query)
+                .unwrap()
+                .iter()
+                .map(|entry| entry.id)
+                .collect()
+        };
+        assert_eq!(
+            search(HistoryQuery {
+                text: Some("bug".to_string()),
+                ..Default::default()
+            }),
+            vec![first]
+        );
+        // LIKE 通配符需要按字面匹配
+        assert_eq!(
+            search(HistoryQuery {
+                text: Some("100%".to_string()),
+                ..Default::default()
+            }),
+            vec![second]
+        );
+        assert_eq!(
+            search(HistoryQuery {
+                starred: Some(true),
+                ..Default::default()
+            }),
+            vec![second]
+        );
+
+        history.delete(first).unwrap();
+        assert!(history.get(first).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_reconcile() {
+        let dir =
+            std::env::temp_dir().join(format!("screenshot-history-test-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let history = History::open_in_memory().unwrap();
+
+        let mut png = Vec::new();
+        image::RgbaImage::new(2, 2)
+            .write_to(&mut std::io::Cursor::new(&mut png), image::ImageFormat::Png)
+            .unwrap();
+        let external = dir.join("external.png");
+        fs::write(&external, &png).unwrap();
+        fs::write(dir.join("notes.txt"), b"not an image").unwrap();
+
+        let removed = dir.join("removed.png");
+        history
+            .record(&saved(&removed.to_string_lossy(), "Editor"), &png)
+            .unwrap();
+
+        let report = history.reconcile(&dir).unwrap();
+        assert_eq!(report.added, vec![external.to_string_lossy().to_string()]);
+        assert_eq!(report.missing, vec![removed.to_string_lossy().to_string()]);
+
+        fs::write(&removed, &png).unwrap();
+        let report = history.reconcile(&dir).unwrap();
+        assert!(report.added.is_empty());
+        assert_eq!(report.restored, vec![removed.to_string_lossy().to_string()]);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,425 @@
+use crate::screenshot::{self, SavedScreenshot};
+use crate::settings;
+use log::{error, info, warn};
+use serde::{Deserialize, Serialize};
+use std::io::{Read, Write};
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::sync::{mpsc, Arc, Mutex};
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter};
+
+/// 每个钩子执行完毕后发送的事件，负载为 `HookResult`
+pub const HOOK_FINISHED_EVENT: &str = "hook-finished";
+
+/// 命令默认的超时时间（秒）
+pub const DEFAULT_TIMEOUT_SECS: u64 = 30;
+
+/// 命令超时时间的上限（秒）
+const MAX_TIMEOUT_SECS: u64 = 3600;
+
+/// 错误信息中保留的命令输出长度
+const MAX_ERROR_OUTPUT: usize = 512;
+
+/// 检查子进程是否退出的间隔
+const POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+/// 钩子接收截图的方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HookInput {
+    /// 文件路径作为第一个参数（`$1`）传入，标准输入为空
+    #[default]
+    Path,
+    /// 图像数据写入标准输入
+    Stdin,
+}
+
+/// 每次保存截图后运行的命令
+///
+/// 命令通过系统 shell（`sh -c` 或 `cmd /V:ON /C`）运行。`cmd` 没有位置参数，命令中的 `$1` 会被替换为
+/// `!SCREENSHOT_PATH!`：延迟展开在解析命令之后才取值，文件名中的 `&`、`^`、`%` 等字符不会被当作命令解析。
+/// Windows 上直接引用路径时也应使用 `!SCREENSHOT_PATH!`，`%SCREENSHOT_PATH%` 会在解析命令之前展开。
+/// 截图信息通过以下环境变量传入：
+/// `SCREENSHOT_PATH`、`SCREENSHOT_FORMAT`、`SCREENSHOT_CAPTURED_AT`、`SCREENSHOT_WIDTH`、
+/// `SCREENSHOT_HEIGHT`、`SCREENSHOT_MONITOR`、`SCREENSHOT_REGION`（`x,y,width,height`）、
+/// `SCREENSHOT_WINDOW_TITLE`、`SCREENSHOT_APP_NAME`，没有对应信息时不设置。
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CommandHook {
+    /// 用于日志和事件的名称
+    pub name: String,
+    pub enabled: bool,
+    pub command: String,
+    pub input: HookInput,
+    pub timeout_secs: u64,
+    /// 命令的标准输出非空时复制到剪贴板，替代原本的剪贴板内容
+    pub clipboard_from_stdout: bool,
+}
+
+impl Default for CommandHook {
+    fn default() -> Self {
+        Self {
+            name: String::new(),
+            enabled: true,
+            command: String::new(),
+            input: HookInput::Path,
+            timeout_secs: DEFAULT_TIMEOUT_SECS,
+            clipboard_from_stdout: false,
+        }
+    }
+}
+
+impl CommandHook {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.command.trim().is_empty() {
+            return Err(format!("钩子 {} 必须指定命令", self.name));
+        }
+        if !(1..=MAX_TIMEOUT_SECS).contains(&self.timeout_secs) {
+            return Err(format!(
+                "钩子 {} 的超时时间必须在 1 到 {} 秒之间",
+                self.name, MAX_TIMEOUT_SECS
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// 钩子的执行结果，随 `HOOK_FINISHED_EVENT` 一起发送
+#[derive(Debug, Clone, Serialize)]
+pub struct HookResult {
+    pub name: String,
+    pub path: String,
+    pub stdout: Option<String>,
+    pub error: Option<String>,
+}
+
+/// 描述截图的环境变量
+fn capture_env(saved: &SavedScreenshot) -> Vec<(&'static str, String)> {
+    let mut env = vec![
+        ("SCREENSHOT_FORMAT", saved.format.clone()),
+        ("SCREENSHOT_CAPTURED_AT", saved.captured_at.clone()),
+    ];
+    let optional = [
+        (
+            "SCREENSHOT_WIDTH",
+            saved.width.map(|width| width.to_string()),
+        ),
+        (
+            "SCREENSHOT_HEIGHT",
+            saved.height.map(|height| height.to_string()),
+        ),
+        ("SCREENSHOT_MONITOR", saved.monitor.clone()),
+        (
+            "SCREENSHOT_REGION",
+            saved.region.map(|region| {
+                format!(
+                    "{},{},{},{}",
+                    region.x, region.y, region.width, region.height
+                )
+            }),
+        ),
+        ("SCREENSHOT_WINDOW_TITLE", saved.window_title.clone()),
+        ("SCREENSHOT_APP_NAME", saved.app_name.clone()),
+    ];
+    env.extend(
+        optional
+            .into_iter()
+            .filter_map(|(name, value)| value.map(|value| (name, value))),
+    );
+    env
+}
+
+fn truncate(output: &[u8]) -> String {
+    String::from_utf8_lossy(output)
+        .trim()
+        .chars()
+        .take(MAX_ERROR_OUTPUT)
+        .collect()
+}
+
+/// 在后台线程中读取子进程的输出管道
+struct PipeReader {
+    output: Arc<Mutex<Vec<u8>>>,
+    /// 读取线程结束时断开
+    done: mpsc::Receiver<()>,
+}
+
+impl PipeReader {
+    fn spawn(pipe: Option<Box<dyn Read + Send>>) -> Self {
+        let output = Arc::new(Mutex::new(Vec::new()));
+        let (done_tx, done) = mpsc::channel::<()>();
+        let buffer = output.clone();
+        std::thread::spawn(move || {
+            let _done = done_tx;
+            let Some(mut pipe) = pipe else {
+                return;
+            };
+            let mut chunk = [0; 8192];
+            loop {
+                match pipe.read(&mut chunk) {
+                    Ok(0) => break,
+                    Ok(read) => buffer.lock().unwrap().extend_from_slice(&chunk[..read]),
+                    Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+                    Err(_) => break,
+                }
+            }
+        });
+        Self { output, done }
+    }
+
+    /// 等待管道关闭，最晚到 `deadline`，返回已经读取到的输出
+    fn finish(self, deadline: Instant) -> Vec<u8> {
+        let _ = self
+            .done
+            .recv_timeout(deadline.saturating_duration_since(Instant::now()));
+        std::mem::take(&mut *self.output.lock().unwrap())
+    }
+}
+
+/// 通过系统 shell 运行命令，返回去掉首尾空白的标准输出
+///
+/// 文件路径作为第一个参数（`$1`）和环境变量 `SCREENSHOT_PATH` 传入，`stdin` 不为空时写入标准输入。
+/// 超时后结束 shell 进程；命令启动的后台进程不受影响，它们继承的输出管道最多读取到超时为止。
+pub fn run_command(
+    command: &str,
+    path: &Path,
+    stdin: Option<Vec<u8>>,
+    env: &[(&str, String)],
+    timeout: Duration,
+) -> Result<String, String> {
+    #[cfg(windows)]
+    let mut shell = {
+        use std::os::windows::process::CommandExt;
+        // cmd 不解析 MSVC 风格的参数转义，原样传入命令，避免命令中的引号被转义；
+        // 路径不拼进命令，只通过环境变量延迟展开，文件名无法注入命令
+        let mut shell = Command::new("cmd");
+        shell
+            .args(["/V:ON", "/C"])
+            .raw_arg(command.replace("$1", "!SCREENSHOT_PATH!"));
+        shell
+    };
+    #[cfg(not(windows))]
+    let mut shell = {
+        let mut shell = Command::new("sh");
+        shell.arg("-c").arg(command).arg("screenshot").arg(path);
+        shell
+    };
+
+    let mut child = shell
+        .env("SCREENSHOT_PATH", path)
+        .envs(env.iter().map(|(name, value)| (name, value)))
+        .stdin(if stdin.is_some() {
+            Stdio::piped()
+        } else {
+            Stdio::null()
+        })
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("无法启动命令: {}", e))?;
+
+    // 标准输入和输出在单独的线程中读写，避免管道缓冲区写满后互相等待
+    if let (Some(data), Some(mut pipe)) = (stdin, child.stdin.take()) {
+        std::thread::spawn(move || {
+            // 命令可能不读取标准输入就退出，此时写入失败可以忽略
+            let _ = pipe.write_all(&data);
+        });
+    }
+    let stdout = PipeReader::spawn(child.stdout.take().map(|pipe| Box::new(pipe) as _));
+    let stderr = PipeReader::spawn(child.stderr.take().map(|pipe| Box::new(pipe) as _));
+
+    let deadline = Instant::now() + timeout;
+    let status = loop {
+        match child.try_wait() {
+            Ok(Some(status)) => break status,
+            Ok(None) if Instant::now() >= deadline => {
+                let _ = child.kill();
+                let _ = child.wait();
+                return Err(format!("命令超时（{} 秒）", timeout.as_secs()));
+            }
+            Ok(None) => std::thread::sleep(POLL_INTERVAL),
+            Err(e) => return Err(format!("等待命令结束失败: {}", e)),
+        }
+    };
+
+    // 命令启动的后台进程可能一直持有输出管道，最多等到超时时间
+    let stdout = stdout.finish(deadline);
+    let stderr = stderr.finish(deadline);
+    if status.success() {
+        return Ok(String::from_utf8_lossy(&stdout).trim().to_string());
+    }
+    Err(match status.code() {
+        Some(code) => format!("命令退出码 {}: {}", code, truncate(&stderr)),
+        None => format!("命令被信号终止: {}", truncate(&stderr)),
+    })
+}
+
+/// 对一张截图运行单个钩子
+fn run_hook(hook: &CommandHook, saved: &SavedScreenshot) -> Result<String, String> {
+    let path = Path::new(&saved.path);
+    let stdin = match hook.input {
+        HookInput::Path => None,
+        HookInput::Stdin => {
+            Some(std::fs::read(path).map_err(|e| format!("读取截图文件失败: {}", e))?)
+        }
+    };
+    run_command(
+        &hook.command,
+        path,
+        stdin,
+        &capture_env(saved),
+        Duration::from_secs(hook.timeout_secs),
+    )
+}
+
+/// 按顺序运行所有启用的钩子
+fn run_hooks(app: &AppHandle, saved: &SavedScreenshot) {
+    let hooks = settings::current(app).hooks;
+    for hook in hooks.iter().filter(|hook| hook.enabled) {
+        info!("运行钩子 {}: {}", hook.name, saved.path);
+        let start = Instant::now();
+        let result = run_hook(hook, saved);
+
+        match &result {
+            Ok(stdout) => {
+                info!("钩子 {} 完成, 耗时: {:?}", hook.name, start.elapsed());
+                if hook.clipboard_from_stdout && !stdout.is_empty() {
+                    if let Err(e) = screenshot::copy_text_to_clipboard(stdout) {
+                        warn!("复制钩子输出到剪贴板失败: {}", e);
+                    }
+                }
+            }
+            Err(e) => error!("钩子 {} 失败: {}", hook.name, e),
+        }
+
+        let payload = HookResult {
+            name: hook.name.clone(),
+            path: saved.path.clone(),
+            stdout: result.as_ref().ok().filter(|out| !out.is_empty()).cloned(),
+            error: result.err(),
+        };
+        if let Err(e) = app.emit(HOOK_FINISHED_EVENT, &payload) {
+            error!("发送钩子结果事件失败: {}", e);
+        }
+    }
+}
+
+/// 有启用的钩子时在后台线程中对截图运行钩子
+pub fn spawn(app: &AppHandle, saved: SavedScreenshot) {
+    if !settings::current(app).hooks.iter().any(|hook| hook.enabled) {
+        return;
+    }
+    let app = app.clone();
+    std::thread::spawn(move || run_hooks(&app, &saved));
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::screenshot::CaptureRegion;
+
+    fn saved(path: &str) -> SavedScreenshot {
+        SavedScreenshot {
+            path: path.to_string(),
+            captured_at: "2024-01-02T03:04:05+08:00".to_string(),
+            monitor: Some("DP-1".to_string()),
+            region: Some(CaptureRegion {
+                x: 1,
+                y: 2,
+                width: 30,
+                height: 40,
+            }),
+            window_title: None,
+            app_name: None,
+            format: "png".to_string(),
+            width: Some(30),
+            height: Some(40),
+            action_chain: None,
+        }
+    }
+
+    #[test]
+    fn test_run_command_env_and_stdin() {
+        let saved = saved("/tmp/shot 1.png");
+        let env = capture_env(&saved);
+        assert!(!env.iter().any(|(name, _)| *name == "SCREENSHOT_APP_NAME"));
+
+        let output = run_command(
+            "printf '%s|%s|%s|%s|' \"$1\" \"$SCREENSHOT_PATH\" \"$SCREENSHOT_REGION\" \"$SCREENSHOT_FORMAT\"; wc -c",
+            Path::new(&saved.path),
+            Some(vec![0; 100_000]),
+            &env,
+            Duration::from_secs(10),
+        )
+        .unwrap();
+        assert_eq!(
+            output.split_whitespace().collect::<Vec<_>>().join(" "),
+            "/tmp/shot 1.png|/tmp/shot 1.png|1,2,30,40|png|100000"
+        );
+    }
+
+    #[test]
+    fn test_run_command_failure_and_timeout() {
+        let path = Path::new("/tmp/shot.png");
+        let error = run_command(
+            "echo failed >&2; exit 3",
+            path,
+            None,
+            &[],
+            Duration::from_secs(10),
+        )
+        .unwrap_err();
+        assert!(error.contains('3') && error.contains("failed"));
+
+        let start = Instant::now();
+        let error =
+            run_command("exec sleep 5", path, None, &[], Duration::from_secs(1)).unwrap_err();
+        assert!(error.contains("超时"));
+        assert!(start.elapsed() < Duration::from_secs(4));
+
+        // 后台进程继承了标准输出，不会让钩子一直等待
+        let start = Instant::now();
+        let output = run_command(
+            "(sleep 5; echo late) & echo done",
+            path,
+            None,
+            &[],
+            Duration::from_secs(1),
+        )
+        .unwrap();
+        assert_eq!(output, "done");
+        assert!(start.elapsed() < Duration::from_secs(4));
+
+        let hook = CommandHook {
+            name: "slow".to_string(),
+            command: "true".to_string(),
+            timeout_secs: 0,
+            ..Default::default()
+        };
+        assert!(hook.validate().is_err());
+    }
+}
+
+#[cfg(all(test, windows))]
+mod windows_tests {
+    use super::*;
+
+    #[test]
+    fn test_run_command_does_not_parse_path() {
+        // 窗口标题进入文件名后可能带有 cmd 的特殊字符
+        let path = Path::new(r"C:\shots\x & echo injected ^| %PATH% (1).png");
+        let output = run_command("echo $1", path, None, &[], Duration::from_secs(10)).unwrap();
+        assert_eq!(output, path.to_string_lossy());
+
+        let output = run_command(
+            "echo \"!SCREENSHOT_PATH!\"",
+            path,
+            None,
+            &[],
+            Duration::from_secs(10),
+        )
+        .unwrap();
+        assert_eq!(output, format!("\"{}\"", path.to_string_lossy()));
+    }
+}
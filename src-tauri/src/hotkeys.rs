@@ -0,0 +1,372 @@
+use crate::actions::{self, CaptureMode};
+use crate::notifications;
+use crate::screenshot;
+use crate::settings::{self, SettingsState};
+use log::{error, info};
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+use tauri::{command, AppHandle, Emitter, Manager, State};
+use tauri_plugin_global_shortcut::{GlobalShortcutExt, Shortcut, ShortcutState};
+
+/// 快捷键可触发的截图动作
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HotkeyAction {
+    FullScreen,
+    Region,
+    ActiveWindow,
+    RepeatLastRegion,
+}
+
+impl HotkeyAction {
+    pub const ALL: [HotkeyAction; 4] = [
+        HotkeyAction::FullScreen,
+        HotkeyAction::Region,
+        HotkeyAction::ActiveWindow,
+        HotkeyAction::RepeatLastRegion,
+    ];
+
+    /// 用于日志和错误提示的中文名称
+    pub fn label(self) -> &'static str {
+        match self {
+            HotkeyAction::FullScreen => "全屏截图",
+            HotkeyAction::Region => "区域截图",
+            HotkeyAction::ActiveWindow => "活动窗口截图",
+            HotkeyAction::RepeatLastRegion => "重复上次区域截图",
+        }
+    }
+}
+
+/// 各动作绑定的快捷键，`None` 表示未绑定
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HotkeyBindings {
+    pub full_screen: Option<String>,
+    pub region: Option<String>,
+    pub active_window: Option<String>,
+    pub repeat_last_region: Option<String>,
+}
+
+impl Default for HotkeyBindings {
+    fn default() -> Self {
+        // 避开 macOS 系统自带的 Cmd+Shift+3/4/5 截图快捷键
+        Self {
+            full_screen: Some("Alt+Shift+1".to_string()),
+            region: Some("Alt+Shift+2".to_string()),
+            active_window: Some("Alt+Shift+3".to_string()),
+            repeat_last_region: Some("Alt+Shift+4".to_string()),
+        }
+    }
+}
+
+impl HotkeyBindings {
+    pub fn get(&self, action: HotkeyAction) -> Option<&str> {
+        match action {
+            HotkeyAction::FullScreen => self.full_screen.as_deref(),
+            HotkeyAction::Region => self.region.as_deref(),
+            HotkeyAction::ActiveWindow => self.active_window.as_deref(),
+            HotkeyAction::RepeatLastRegion => self.repeat_last_region.as_deref(),
+        }
+    }
+
+    pub fn set(&mut self, action: HotkeyAction, accelerator: Option<String>) {
+        let slot = match action {
+            HotkeyAction::FullScreen => &mut self.full_screen,
+            HotkeyAction::Region => &mut self.region,
+            HotkeyAction::ActiveWindow => &mut self.active_window,
+            HotkeyAction::RepeatLastRegion => &mut self.repeat_last_region,
+        };
+        *slot = accelerator;
+    }
+
+    /// 检查快捷键是否与其他动作的绑定冲突，返回冲突的动作
+    ///
+    /// 按解析后的快捷键比较，因此 `Alt+Shift+1` 与 `shift+alt+Digit1` 视为相同。
+    pub fn find_conflict(&self, action: HotkeyAction, shortcut: &Shortcut) -> Option<HotkeyAction> {
+        HotkeyAction::ALL.into_iter().find(|other| {
+            *other != action
+                && self
+                    .get(*other)
+                    .and_then(|accelerator| parse_shortcut(accelerator).ok())
+                    .is_some_and(|existing| existing.id() == shortcut.id())
+        })
+    }
+}
+
+/// 注册失败的快捷键（格式错误或已被其他程序占用）
+#[derive(Debug, Clone, Serialize)]
+pub struct HotkeyFailure {
+    pub action: HotkeyAction,
+    pub accelerator: String,
+    pub reason: String,
+}
+
+/// 返回给前端的快捷键状态
+#[derive(Debug, Clone, Serialize)]
+pub struct HotkeyStatus {
+    pub bindings: HotkeyBindings,
+    pub failures: Vec<HotkeyFailure>,
+}
+
+/// 快捷键截图完成后发送给前端的事件内容
+#[derive(Debug, Clone, Serialize)]
+struct HotkeyCaptureResult {
+    action: HotkeyAction,
+    path: Option<String>,
+    error: Option<String>,
+}
+
+/// 托管在 Tauri 中的快捷键注册状态，绑定本身保存在设置中
+pub struct HotkeyState {
+    failures: Mutex<Vec<HotkeyFailure>>,
+}
+
+fn parse_shortcut(accelerator: &str) -> Result<Shortcut, String> {
+    accelerator
+        .parse::<Shortcut>()
+        .map_err(|e| format!("无法解析快捷键 {}: {}", accelerator, e))
+}
+
+/// 注册单个快捷键，按下时触发对应的截图动作
+fn register(app: &AppHandle, action: HotkeyAction, shortcut: Shortcut) -> Result<(), String> {
+    app.global_shortcut()
+        .on_shortcut(shortcut, move |app, _shortcut, event| {
+            if event.state == ShortcutState::Pressed {
+                trigger(app, action);
+            }
+        })
+        .map_err(|e| e.to_string())
+}
+
+/// 在应用启动时注册所有已配置的快捷键
+///
+/// 单个快捷键注册失败不会影响其他快捷键，失败信息会通过 `get_hotkeys` 返回给前端。
+pub fn init(app: &AppHandle) {
+    let bindings = settings::current(app).hotkeys;
+    let mut failures = Vec::new();
+
+    for action in HotkeyAction::ALL {
+        let Some(accelerator) = bindings.get(action) else {
+            continue;
+        };
+
+        let result = parse_shortcut(accelerator).and_then(|shortcut| {
+            if let Some(other) = bindings.find_conflict(action, &shortcut) {
+                return Err(format!("与「{}」的快捷键冲突", other.label()));
+            }
+            register(app, action, shortcut)
+        });
+
+        match result {
+            Ok(()) => info!("已注册快捷键 {}: {}", action.label(), accelerator),
+            Err(reason) => {
+                error!(
+                    "注册快捷键失败 {}: {}, {}",
+                    action.label(),
+                    accelerator,
+                    reason
+                );
+                failures.push(HotkeyFailure {
+                    action,
+                    accelerator: accelerator.to_string(),
+                    reason,
+                });
+            }
+        }
+    }
+
+    app.manage(HotkeyState {
+        failures: Mutex::new(failures),
+    });
+}
+
+/// 执行快捷键对应的截图动作（托盘菜单也复用此入口）
+///
+/// 区域截图需要前端显示选区界面，因此只发送事件；其余动作在后台线程中直接截图，
+/// 再执行设置中对应截图方式的动作链。
+pub fn trigger(app: &AppHandle, action: HotkeyAction) {
+    info!("快捷键触发: {}", action.label());
+
+    if action == HotkeyAction::Region {
+        if let Err(e) = app.emit("hotkey-region", ()) {
+            error!("发送区域截图事件失败: {}", e);
+        }
+        return;
+    }
+
+    let app = app.clone();
+    std::thread::spawn(move || {
+        let captured = match action {
+            HotkeyAction::FullScreen => screenshot::grab_full_screen(&app)
+                .map(|captured| (CaptureMode::FullScreen, captured)),
+            HotkeyAction::ActiveWindow => screenshot::grab_active_window(&app)
+                .map(|captured| (CaptureMode::ActiveWindow, captured)),
+            HotkeyAction::RepeatLastRegion => match screenshot::last_region() {
+                Some((x, y, width, height)) => {
+                    screenshot::grab_region(&app, x, y, width, height, false)
+                        .map(|captured| (CaptureMode::Region, captured))
+                }
+                None => Err("尚未进行过区域截图".to_string()),
+            },
+            HotkeyAction::Region => return,
+        };
+        // 截图成功后执行对应的动作链，任一动作失败都作为快捷键截图的错误报告
+        let result = captured.and_then(|(mode, captured)| {
+            let report = actions::run(&app, mode, captured);
+            match report.first_error() {
+                Some(e) => Err(e),
+                None => Ok(report.path),
+            }
+        });
+        notifications::notify_capture(&app, &result);
+
+        let payload = match result {
+            Ok(path) => {
+                info!("快捷键截图完成: {:?}", path);
+                HotkeyCaptureResult {
+                    action,
+                    path,
+                    error: None,
+                }
+            }
+            Err(e) => {
+                error!("快捷键截图失败 {}: {}", action.label(), e);
+                HotkeyCaptureResult {
+                    action,
+                    path: None,
+                    error: Some(e),
+                }
+            }
+        };
+
+        if let Err(e) = app.emit("hotkey-capture-finished", payload) {
+            error!("发送快捷键截图结果事件失败: {}", e);
+        }
+    });
+}
+
+/// 获取当前快捷键绑定及注册失败的快捷键
+#[command]
+pub fn get_hotkeys(app: AppHandle, state: State<'_, HotkeyState>) -> HotkeyStatus {
+    HotkeyStatus {
+        bindings: settings::current(&app).hotkeys,
+        failures: state.failures.lock().unwrap().clone(),
+    }
+}
+
+/// 运行时重新绑定快捷键，`accelerator` 为 `None` 时解除绑定
+///
+/// 新快捷键注册失败时会恢复原来的绑定。
+#[command]
+pub fn rebind_hotkey(
+    app: AppHandle,
+    state: State<'_, HotkeyState>,
+    action: HotkeyAction,
+    accelerator: Option<String>,
+) -> Result<HotkeyStatus, String> {
+    info!("重新绑定快捷键 {}: {:?}", action.label(), accelerator);
+
+    let bindings = settings::current(&app).hotkeys;
+
+    let new_shortcut = match accelerator.as_deref() {
+        Some(accelerator) => {
+            let shortcut = parse_shortcut(accelerator)?;
+            if let Some(other) = bindings.find_conflict(action, &shortcut) {
+                error!("快捷键 {} 已被「{}」占用", accelerator, other.label());
+                return Err(format!(
+                    "快捷键 {} 已被「{}」占用",
+                    accelerator,
+                    other.label()
+                ));
+            }
+            Some(shortcut)
+        }
+        None => None,
+    };
+
+    let old_shortcut = bindings
+        .get(action)
+        .and_then(|accelerator| parse_shortcut(accelerator).ok());
+    let shortcuts = app.global_shortcut();
+
+    if let Some(old) = old_shortcut {
+        if shortcuts.is_registered(old) {
+            shortcuts.unregister(old).map_err(|e| {
+                error!("注销快捷键失败: {}", e);
+                format!("注销快捷键失败: {}", e)
+            })?;
+        }
+    }
+
+    if let Some(shortcut) = new_shortcut {
+        if let Err(e) = register(&app, action, shortcut) {
+            error!("快捷键注册失败，可能已被其他程序占用: {}", e);
+            if let Some(old) = old_shortcut {
+                if let Err(e) = register(&app, action, old) {
+                    error!("恢复原快捷键失败: {}", e);
+                }
+            }
+            return Err(format!("快捷键注册失败，可能已被其他程序占用: {}", e));
+        }
+    }
+
+    // 保存失败时恢复原来的注册，保证已注册的快捷键与设置文件一致
+    let settings = match app
+        .state::<SettingsState>()
+        .update(|settings| settings.hotkeys.set(action, accelerator))
+    {
+        Ok(settings) => settings,
+        Err(e) => {
+            error!("保存快捷键设置失败: {}", e);
+            if let Some(shortcut) = new_shortcut {
+                if let Err(e) = shortcuts.unregister(shortcut) {
+                    error!("注销新快捷键失败: {}", e);
+                }
+            }
+            if let Some(old) = old_shortcut {
+                if let Err(e) = register(&app, action, old) {
+                    error!("恢复原快捷键失败: {}", e);
+                }
+            }
+            return Err(e);
+        }
+    };
+
+    let mut failures = state.failures.lock().unwrap();
+    failures.retain(|failure| failure.action != action);
+
+    Ok(HotkeyStatus {
+        bindings: settings.hotkeys,
+        failures: failures.clone(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_conflict() {
+        let bindings = HotkeyBindings::default();
+
+        // 写法不同但解析后相同的快捷键应视为冲突
+        let shortcut = parse_shortcut("shift+alt+Digit2").unwrap();
+        assert_eq!(
+            bindings.find_conflict(HotkeyAction::FullScreen, &shortcut),
+            Some(HotkeyAction::Region)
+        );
+
+        // 与自身当前绑定相同不算冲突
+        assert_eq!(
+            bindings.find_conflict(HotkeyAction::Region, &shortcut),
+            None
+        );
+
+        // 未被占用的快捷键
+        let shortcut = parse_shortcut("Alt+Shift+9").unwrap();
+        assert_eq!(
+            bindings.find_conflict(HotkeyAction::FullScreen, &shortcut),
+            None
+        );
+    }
+}
@@ -0,0 +1,263 @@
+use crate::cli::{self, CommandEnv};
+use crate::screenshot;
+use crate::settings;
+use base64::{engine::general_purpose, Engine as _};
+use interprocess::local_socket::{prelude::*, ListenerOptions, Name, Stream};
+use log::{error, info, warn};
+use serde::{Deserialize, Serialize};
+use std::ffi::OsString;
+use std::fs::{self, File, TryLockError};
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::PathBuf;
+use std::sync::OnceLock;
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+
+/// 第二个进程转发给运行中实例的请求，每个请求占一行 JSON
+#[derive(Debug, Serialize, Deserialize)]
+struct ForwardRequest {
+    args: Vec<String>,
+    cwd: PathBuf,
+}
+
+/// 运行中实例的执行结果，由发起转发的进程原样输出并作为退出码
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct ForwardResponse {
+    code: i32,
+    /// Base64 编码的标准输出内容（`--output -` 时为图像数据）
+    stdout: String,
+    stderr: String,
+}
+
+/// 发送请求和读取请求的超时时间，对方卡住时不会一直阻塞
+const IO_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// 等待运行中实例返回结果的时间，截图命令的 `--delay` 另外计入
+const RESPONSE_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// 持有期间表示本进程负责监听实例套接字，进程退出时由系统释放
+static INSTANCE_LOCK: OnceLock<File> = OnceLock::new();
+
+/// 监听实例套接字前必须持有的锁文件，防止同时启动的实例互相覆盖套接字
+fn lock_path() -> io::Result<PathBuf> {
+    crate::get_app_config_dir()
+        .map(|dir| dir.join("instance.lock"))
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "无法获取配置目录"))
+}
+
+/// 尝试获取实例锁，已被其他进程持有时返回 `Ok(None)`
+fn try_lock() -> io::Result<Option<File>> {
+    let file = fs::OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(lock_path()?)?;
+    match file.try_lock() {
+        Ok(()) => Ok(Some(file)),
+        Err(TryLockError::WouldBlock) => Ok(None),
+        Err(TryLockError::Error(e)) => Err(e),
+    }
+}
+
+/// 实例之间通信使用的本地套接字
+///
+/// Unix 上为配置目录下的 Unix 域套接字，只有当前用户可以访问；Windows 上为按用户名区分的命名管道。
+fn socket_name() -> io::Result<Name<'static>> {
+    #[cfg(unix)]
+    {
+        use interprocess::local_socket::GenericFilePath;
+        let dir = crate::get_app_config_dir()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "无法获取配置目录"))?;
+        dir.join("instance.sock").to_fs_name::<GenericFilePath>()
+    }
+
+    #[cfg(not(unix))]
+    {
+        use interprocess::local_socket::GenericNamespaced;
+        let user = std::env::var("USERNAME").unwrap_or_default();
+        format!("screenshot-app-{}", user).to_ns_name::<GenericNamespaced>()
+    }
+}
+
+/// 如果已有实例在运行，把命令行参数转发给它并返回退出码；没有运行中的实例时返回 `None`
+///
+/// 不带参数启动时，运行中的实例会显示并聚焦主窗口。
+pub fn forward(args: &[OsString]) -> Option<i32> {
+    let stream = Stream::connect(socket_name().ok()?).ok()?;
+
+    let request = ForwardRequest {
+        args: args
+            .iter()
+            .map(|arg| arg.to_string_lossy().to_string())
+            .collect(),
+        cwd: std::env::current_dir().unwrap_or_default(),
+    };
+    let timeout = RESPONSE_TIMEOUT + cli::parse(args).map(|cli| cli.delay()).unwrap_or_default();
+    let response = match send(stream, &request, timeout) {
+        Ok(response) => response,
+        Err(e) => {
+            eprintln!("错误: 转发命令到运行中的实例失败: {}", e);
+            return Some(cli::EXIT_FAILURE);
+        }
+    };
+
+    let stdout = general_purpose::STANDARD
+        .decode(&response.stdout)
+        .unwrap_or_default();
+    let _ = io::stdout().lock().write_all(&stdout);
+    if !response.stderr.is_empty() {
+        eprint!("{}", response.stderr);
+    }
+    Some(response.code)
+}
+
+fn send(
+    stream: Stream,
+    request: &ForwardRequest,
+    timeout: Duration,
+) -> io::Result<ForwardResponse> {
+    stream.set_send_timeout(Some(IO_TIMEOUT))?;
+    stream.set_recv_timeout(Some(timeout))?;
+    let mut reader = BufReader::new(stream);
+    let mut line = serde_json::to_string(request)?;
+    line.push('\n');
+    reader.get_mut().write_all(line.as_bytes())?;
+
+    let mut line = String::new();
+    reader.read_line(&mut line)?;
+    Ok(serde_json::from_str(&line)?)
+}
+
+/// 监听本地套接字，执行其他进程转发过来的命令
+pub fn listen(app: &AppHandle) {
+    let name = match socket_name() {
+        Ok(name) => name,
+        Err(e) => {
+            error!("无法确定实例套接字名称: {}", e);
+            return;
+        }
+    };
+    // 持有锁才能覆盖套接字：同时启动的实例中只有一个会监听，另一个不会删掉它的套接字
+    match try_lock() {
+        Ok(Some(lock)) => {
+            let _ = INSTANCE_LOCK.set(lock);
+        }
+        Ok(None) => {
+            warn!("另一个实例正在监听实例套接字，本实例不接收转发的命令");
+            return;
+        }
+        Err(e) => {
+            error!("获取实例锁失败，无法接收转发的命令: {}", e);
+            return;
+        }
+    }
+    // 持有锁时不会有其他实例在监听，残留的套接字文件来自异常退出的进程，可以直接覆盖
+    let listener = match ListenerOptions::new()
+        .name(name)
+        .try_overwrite(true)
+        .create_sync()
+    {
+        Ok(listener) => listener,
+        Err(e) => {
+            error!("创建实例套接字失败，无法接收转发的命令: {}", e);
+            return;
+        }
+    };
+    info!("单实例监听已启动");
+
+    let app = app.clone();
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            match stream {
+                Ok(stream) => {
+                    let app = app.clone();
+                    std::thread::spawn(move || {
+                        if let Err(e) = serve(&app, stream) {
+                            warn!("处理转发的命令失败: {}", e);
+                        }
+                    });
+                }
+                Err(e) => warn!("接受实例连接失败: {}", e),
+            }
+        }
+    });
+}
+
+fn serve(app: &AppHandle, stream: Stream) -> io::Result<()> {
+    // 执行命令期间不计时，只限制读取请求和写回结果
+    stream.set_recv_timeout(Some(IO_TIMEOUT))?;
+    stream.set_send_timeout(Some(IO_TIMEOUT))?;
+    let mut reader = BufReader::new(stream);
+    let mut line = String::new();
+    reader.read_line(&mut line)?;
+    let request: ForwardRequest = serde_json::from_str(&line)?;
+    info!("收到转发的命令: {:?}", request.args);
+
+    let response = handle(app, request);
+    let mut line = serde_json::to_string(&response)?;
+    line.push('\n');
+    reader.get_mut().write_all(line.as_bytes())
+}
+
+fn handle(app: &AppHandle, request: ForwardRequest) -> ForwardResponse {
+    let args: Vec<OsString> = request.args.into_iter().map(OsString::from).collect();
+
+    if cli::is_gui_launch(&args) {
+        if let Some(window) = app.get_webview_window("main") {
+            let _ = window.show();
+            let _ = window.set_focus();
+        }
+        return ForwardResponse::default();
+    }
+
+    let command = match cli::parse(&args) {
+        Ok(command) => command,
+        Err(e) => {
+            // --help / --version 的内容输出到标准输出，参数错误输出到标准错误
+            let message = e.render().to_string();
+            let (stdout, stderr) = if e.use_stderr() {
+                (String::new(), message)
+            } else {
+                (general_purpose::STANDARD.encode(message), String::new())
+            };
+            return ForwardResponse {
+                code: e.exit_code(),
+                stdout,
+                stderr,
+            };
+        }
+    };
+
+    let next_counter =
+        |settings: &settings::Settings| screenshot::next_filename_counter(app, settings);
+    let env = CommandEnv {
+        headless: false,
+        settings: settings::current(app),
+        cwd: request.cwd,
+        next_counter: &next_counter,
+    };
+    let mut stdout = Vec::new();
+    let result = cli::execute(command, &env, &mut stdout);
+    let stdout = general_purpose::STANDARD.encode(&stdout);
+
+    match result {
+        Ok(saved) => {
+            if let Some(saved) = saved {
+                screenshot::announce_saved(app, &saved);
+            }
+            ForwardResponse {
+                code: cli::EXIT_OK,
+                stdout,
+                stderr: String::new(),
+            }
+        }
+        Err(e) => {
+            error!("执行转发的命令失败: {}", e.message);
+            ForwardResponse {
+                code: e.code,
+                stdout,
+                stderr: format!("错误: {}\n", e.message),
+            }
+        }
+    }
+}
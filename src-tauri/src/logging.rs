@@ -0,0 +1,453 @@
+use crate::settings::Settings;
+use chrono::{DateTime, Local, NaiveDate};
+use env_logger::{Builder, Target};
+use log::{error, info, Level, LevelFilter, Log, Metadata, Record};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock, RwLock};
+use tauri::command;
+
+/// `get_recent_logs` 默认返回的行数
+const DEFAULT_RECENT_LINES: usize = 200;
+/// `get_recent_logs` 最多返回的行数
+const MAX_RECENT_LINES: usize = 5000;
+/// 最多保留的旧日志文件数量
+const MAX_RETAINED_FILES: usize = 100;
+
+/// 日志文件的轮转和输出格式
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LogSettings {
+    /// 单个日志文件的大小上限（MB），超过后轮转
+    pub max_file_mb: u64,
+    /// 除当前文件外保留的旧日志文件数量
+    pub max_files: usize,
+    /// 日期变化时轮转日志文件
+    pub rotate_daily: bool,
+    /// 每行输出一个 JSON 对象，便于用工具检索
+    pub json: bool,
+}
+
+impl Default for LogSettings {
+    fn default() -> Self {
+        Self {
+            max_file_mb: 10,
+            max_files: 5,
+            rotate_daily: true,
+            json: false,
+        }
+    }
+}
+
+impl LogSettings {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.max_file_mb == 0 {
+            return Err("日志文件大小上限必须大于 0".to_string());
+        }
+        if !(1..=MAX_RETAINED_FILES).contains(&self.max_files) {
+            return Err(format!(
+                "保留的日志文件数量必须在 1 到 {} 之间",
+                MAX_RETAINED_FILES
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// 解析日志级别设置：全局级别和 `模块=级别` 组成的逗号分隔列表，如 `info,xcap=warn`
+pub fn parse_filters(spec: &str) -> Result<Vec<(Option<String>, LevelFilter)>, String> {
+    let level = |level: &str| {
+        level.trim().parse::<LevelFilter>().map_err(|_| {
+            format!(
+                "日志级别必须是 off/error/warn/info/debug/trace 之一: {}",
+                level.trim()
+            )
+        })
+    };
+
+    let mut filters = Vec::new();
+    for part in spec
+        .split(',')
+        .map(str::trim)
+        .filter(|part| !part.is_empty())
+    {
+        match part.split_once('=') {
+            Some((module, _)) if module.trim().is_empty() => {
+                return Err(format!("日志过滤规则缺少模块名: {}", part));
+            }
+            Some((module, value)) => {
+                filters.push((Some(module.trim().to_string()), level(value)?));
+            }
+            None => filters.push((None, level(part)?)),
+        }
+    }
+    if filters.is_empty() {
+        return Err("日志级别不能为空".to_string());
+    }
+    Ok(filters)
+}
+
+/// 第 `index` 个日志文件：0 是当前文件 screenshot.log，1 是最近轮转的 screenshot.1.log
+fn file_path(dir: &Path, index: usize) -> PathBuf {
+    if index == 0 {
+        dir.join("screenshot.log")
+    } else {
+        dir.join(format!("screenshot.{}.log", index))
+    }
+}
+
+fn open_append(path: &Path) -> io::Result<File> {
+    OpenOptions::new().create(true).append(true).open(path)
+}
+
+/// 按大小和日期轮转的日志文件，启动时追加写入，保留上次运行（包括崩溃）的日志
+struct RotatingFile {
+    dir: PathBuf,
+    file: File,
+    size: u64,
+    /// 当前文件开始写入的日期
+    day: NaiveDate,
+    max_bytes: u64,
+    max_files: usize,
+    daily: bool,
+}
+
+impl RotatingFile {
+    fn open(dir: &Path, max_bytes: u64, max_files: usize, daily: bool) -> io::Result<Self> {
+        fs::create_dir_all(dir)?;
+        let path = file_path(dir, 0);
+        let today = Local::now().date_naive();
+        let (size, day) = match fs::metadata(&path) {
+            Ok(metadata) => (
+                metadata.len(),
+                metadata
+                    .modified()
+                    .map(|modified| DateTime::<Local>::from(modified).date_naive())
+                    .unwrap_or(today),
+            ),
+            Err(_) => (0, today),
+        };
+
+        let mut file = Self {
+            dir: dir.to_path_buf(),
+            file: open_append(&path)?,
+            size,
+            day,
+            max_bytes,
+            max_files,
+            daily,
+        };
+        if file.needs_rotation(0) {
+            file.rotate()?;
+        }
+        Ok(file)
+    }
+
+    fn needs_rotation(&self, incoming: usize) -> bool {
+        self.size > 0
+            && (self.size + incoming as u64 > self.max_bytes
+                || (self.daily && Local::now().date_naive() != self.day))
+    }
+
+    /// screenshot.log -> screenshot.1.log -> screenshot.2.log ...，超出数量的旧文件被删除
+    fn rotate(&mut self) -> io::Result<()> {
+        self.file.flush()?;
+        let mut index = self.max_files;
+        while file_path(&self.dir, index).exists() {
+            fs::remove_file(file_path(&self.dir, index))?;
+            index += 1;
+        }
+        for index in (1..self.max_files).rev() {
+            let from = file_path(&self.dir, index);
+            if from.exists() {
+                fs::rename(from, file_path(&self.dir, index + 1))?;
+            }
+        }
+        fs::rename(file_path(&self.dir, 0), file_path(&self.dir, 1))?;
+
+        self.file = open_append(&file_path(&self.dir, 0))?;
+        self.size = 0;
+        self.day = Local::now().date_naive();
+        Ok(())
+    }
+}
+
+impl Write for RotatingFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.needs_rotation(buf.len()) {
+            // 轮转失败时继续写入当前文件，不丢失日志
+            if let Err(e) = self.rotate() {
+                eprintln!("日志文件轮转失败: {}", e);
+            }
+        }
+        let written = self.file.write(buf)?;
+        self.size += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+fn format_text(buf: &mut env_logger::fmt::Formatter, record: &Record) -> io::Result<()> {
+    writeln!(
+        buf,
+        "{} [{}] {} - {}",
+        Local::now().format("%Y-%m-%d %H:%M:%S"),
+        record.level(),
+        record.target(),
+        record.args()
+    )
+}
+
+fn format_json(buf: &mut env_logger::fmt::Formatter, record: &Record) -> io::Result<()> {
+    let line = serde_json::json!({
+        "time": Local::now().to_rfc3339(),
+        "level": record.level().as_str(),
+        "target": record.target(),
+        "message": record.args().to_string(),
+    });
+    writeln!(buf, "{}", line)
+}
+
+/// 日志文件所在的目录
+///
+/// 优先使用应用配置目录，其次是可执行文件所在目录，最后回退到当前工作目录。
+fn log_dir() -> PathBuf {
+    if let Some(app_config_dir) = crate::get_app_config_dir() {
+        return app_config_dir;
+    }
+    if let Ok(current_exe) = std::env::current_exe() {
+        if let Some(parent) = current_exe.parent() {
+            return parent.to_path_buf();
+        }
+    }
+    PathBuf::from(".")
+}
+
+/// 日志初始化后使用的目录
+static LOG_DIR: OnceLock<PathBuf> = OnceLock::new();
+
+/// 日志初始化前最多缓存的记录数
+const MAX_PENDING_RECORDS: usize = 1000;
+
+/// 日志初始化前缓存的记录：(级别, 目标, 内容)
+type PendingRecord = (Level, String, String);
+
+/// 全局日志记录器，实际输出交给按设置创建的 env_logger，修改设置后整体替换
+///
+/// 初始化之前（如加载设置时）的日志先缓存起来，初始化后按新的过滤规则补写。
+struct AppLogger {
+    inner: RwLock<Option<env_logger::Logger>>,
+    pending: Mutex<Vec<PendingRecord>>,
+}
+
+static LOGGER: AppLogger = AppLogger {
+    inner: RwLock::new(None),
+    pending: Mutex::new(Vec::new()),
+};
+
+impl Log for AppLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        match &*self.inner.read().unwrap_or_else(|e| e.into_inner()) {
+            Some(logger) => logger.enabled(metadata),
+            None => true,
+        }
+    }
+
+    fn log(&self, record: &Record) {
+        if let Some(logger) = &*self.inner.read().unwrap_or_else(|e| e.into_inner()) {
+            logger.log(record);
+            return;
+        }
+        let mut pending = self.pending.lock().unwrap_or_else(|e| e.into_inner());
+        if pending.len() < MAX_PENDING_RECORDS {
+            pending.push((
+                record.level(),
+                record.target().to_string(),
+                record.args().to_string(),
+            ));
+        }
+    }
+
+    fn flush(&self) {
+        if let Some(logger) = &*self.inner.read().unwrap_or_else(|e| e.into_inner()) {
+            logger.flush();
+        }
+    }
+}
+
+/// 安装全局日志记录器，在加载设置之前调用，此后的日志会在 `init` 之后补写
+pub fn install() {
+    if log::set_logger(&LOGGER).is_ok() {
+        log::set_max_level(LevelFilter::Trace);
+    }
+}
+
+/// 按设置创建日志记录器，写入按大小和日期轮转的日志文件
+///
+/// 设置了 `RUST_LOG` 环境变量时使用它的过滤规则，否则使用设置中的日志级别。
+/// 无法打开日志文件时输出到标准错误，同时返回打开失败的原因。
+fn build(settings: &Settings, dir: &Path) -> (env_logger::Logger, Option<io::Error>) {
+    let mut builder = Builder::new();
+    match std::env::var("RUST_LOG") {
+        Ok(spec) if !spec.trim().is_empty() => {
+            builder.parse_filters(&spec);
+        }
+        _ => {
+            let filters = parse_filters(&settings.log_level)
+                .unwrap_or_else(|_| vec![(None, LevelFilter::Info)]);
+            for (module, level) in filters {
+                builder.filter(module.as_deref(), level);
+            }
+        }
+    }
+    if settings.logging.json {
+        builder.format(format_json);
+    } else {
+        builder.format(format_text);
+    }
+
+    let logging = &settings.logging;
+    match RotatingFile::open(
+        dir,
+        logging.max_file_mb.saturating_mul(1024 * 1024),
+        logging.max_files,
+        logging.rotate_daily,
+    ) {
+        Ok(file) => {
+            builder.target(Target::Pipe(Box::new(file)));
+            (builder.build(), None)
+        }
+        Err(e) => (builder.build(), Some(e)),
+    }
+}
+
+/// 替换实际输出的日志记录器，原来的记录器（和它打开的日志文件）随之关闭
+fn swap(logger: env_logger::Logger) {
+    log::set_max_level(logger.filter());
+    *LOGGER.inner.write().unwrap_or_else(|e| e.into_inner()) = Some(logger);
+}
+
+/// 初始化日志，并补写初始化之前缓存的日志
+pub fn init(settings: &Settings) {
+    install();
+    let dir = log_dir();
+    let (logger, error) = build(settings, &dir);
+    swap(logger);
+    match error {
+        None => {
+            info!("日志文件位于: {:?}", file_path(&dir, 0));
+            let _ = LOG_DIR.set(dir);
+        }
+        Some(e) => {
+            eprintln!(
+                "警告: 无法打开日志文件 {:?}，将使用标准输出记录日志: {}",
+                file_path(&dir, 0),
+                e
+            );
+        }
+    }
+
+    let pending = std::mem::take(&mut *LOGGER.pending.lock().unwrap_or_else(|e| e.into_inner()));
+    for (level, target, message) in pending {
+        let metadata = Metadata::builder().level(level).target(&target).build();
+        if LOGGER.enabled(&metadata) {
+            LOGGER.log(
+                &Record::builder()
+                    .metadata(metadata)
+                    .args(format_args!("(启动时) {}", message))
+                    .build(),
+            );
+        }
+    }
+}
+
+/// 修改设置后立即应用新的日志级别和输出格式
+pub fn apply(settings: &Settings) {
+    let dir = LOG_DIR.get().cloned().unwrap_or_else(log_dir);
+    match build(settings, &dir) {
+        (logger, None) => {
+            swap(logger);
+            info!("已应用新的日志设置: {}", settings.log_level);
+        }
+        (_, Some(e)) => error!("重新打开日志文件失败，继续使用原来的日志设置: {}", e),
+    }
+}
+
+/// 从当前日志文件开始向前读取最后 `count` 行，按时间顺序返回
+fn read_recent(dir: &Path, count: usize) -> Vec<String> {
+    let mut lines = VecDeque::with_capacity(count);
+    for index in 0.. {
+        let Ok(content) = fs::read(file_path(dir, index)) else {
+            break;
+        };
+        for line in String::from_utf8_lossy(&content).lines().rev() {
+            if lines.len() >= count {
+                return lines.into();
+            }
+            lines.push_front(line.to_string());
+        }
+    }
+    lines.into()
+}
+
+/// 返回最近的日志，用于提交问题报告；默认 200 行，最多 5000 行
+#[command]
+pub fn get_recent_logs(lines: Option<usize>) -> Result<Vec<String>, String> {
+    let dir = LOG_DIR.get().ok_or("日志文件不可用".to_string())?;
+    let count = lines.unwrap_or(DEFAULT_RECENT_LINES).min(MAX_RECENT_LINES);
+    Ok(read_recent(dir, count))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_filters() {
+        assert_eq!(
+            parse_filters("info, screenshot_lib::capture=debug,xcap=warn").unwrap(),
+            vec![
+                (None, LevelFilter::Info),
+                (
+                    Some("screenshot_lib::capture".to_string()),
+                    LevelFilter::Debug
+                ),
+                (Some("xcap".to_string()), LevelFilter::Warn),
+            ]
+        );
+        assert!(parse_filters("verbose").is_err());
+        assert!(parse_filters("=debug").is_err());
+        assert!(parse_filters(" , ").is_err());
+    }
+
+    #[test]
+    fn test_rotation_keeps_recent_files() {
+        let dir = std::env::temp_dir().join(format!("screenshot-log-test-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+
+        // 每行 7 字节，每个文件最多 20 字节即两行；env_logger 每条日志只调用一次 write_all
+        let mut file = RotatingFile::open(&dir, 20, 2, false).unwrap();
+        for i in 0..7 {
+            file.write_all(format!("line {}\n", i).as_bytes()).unwrap();
+        }
+        drop(file);
+        assert!(file_path(&dir, 2).exists());
+        assert!(!file_path(&dir, 3).exists());
+        assert_eq!(read_recent(&dir, 3), vec!["line 4", "line 5", "line 6"]);
+
+        // 重新打开时追加写入，不丢失上次运行的日志
+        let mut file = RotatingFile::open(&dir, 20, 2, false).unwrap();
+        file.write_all(b"line 7\n").unwrap();
+        assert_eq!(
+            read_recent(&dir, 100),
+            (2..8).map(|i| format!("line {}", i)).collect::<Vec<_>>()
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
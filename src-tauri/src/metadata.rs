@@ -0,0 +1,680 @@
+use crate::screenshot::CaptureRegion;
+use crate::storage;
+use chrono::DateTime;
+use image::ImageFormat;
+use log::info;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use tauri::command;
+
+/// 写入图像的截图来源信息，统一以 XMP 保存，PNG/JPEG 另外写入常用的文本字段和 EXIF
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct CaptureMetadata {
+    /// RFC 3339 格式的截图时间
+    pub captured_at: Option<String>,
+    pub monitor: Option<String>,
+    pub region: Option<CaptureRegion>,
+    pub scale_factor: Option<f32>,
+    pub window_title: Option<String>,
+    pub app_name: Option<String>,
+    /// 生成截图的本应用版本
+    pub app_version: Option<String>,
+}
+
+/// 本应用的名称和版本，写入 `Software` / `CreatorTool`
+const SOFTWARE: &str = concat!(env!("CARGO_PKG_NAME"), " ", env!("CARGO_PKG_VERSION"));
+
+/// 自定义 XMP 命名空间
+const XMP_NAMESPACE: &str = "https://github.com/think-next/screenshot/ns/1.0/";
+
+/// PNG 中保存 XMP 的 iTXt 关键字
+const PNG_XMP_KEYWORD: &str = "XML:com.adobe.xmp";
+
+/// JPEG APP1 段中 XMP / EXIF 的标识
+const JPEG_XMP_HEADER: &[u8] = b"http://ns.adobe.com/xap/1.0/\0";
+const JPEG_EXIF_HEADER: &[u8] = b"Exif\0\0";
+
+/// PNG 中会被移除的元数据块
+const PNG_METADATA_CHUNKS: [&[u8; 4]; 5] = [b"tEXt", b"iTXt", b"zTXt", b"eXIf", b"tIME"];
+
+/// WebP VP8X 标志位
+const WEBP_FLAG_ALPHA: u8 = 0x10;
+const WEBP_FLAG_EXIF: u8 = 0x08;
+const WEBP_FLAG_XMP: u8 = 0x04;
+
+impl CaptureMetadata {
+    fn xmp_fields(&self) -> Vec<(&'static str, String)> {
+        let mut fields = Vec::new();
+        if let Some(captured_at) = &self.captured_at {
+            fields.push(("xmp:CreateDate", captured_at.clone()));
+        }
+        fields.push(("xmp:CreatorTool", SOFTWARE.to_string()));
+        if let Some(app_version) = &self.app_version {
+            fields.push(("ss:AppVersion", app_version.clone()));
+        }
+        if let Some(monitor) = &self.monitor {
+            fields.push(("ss:Monitor", monitor.clone()));
+        }
+        if let Some(region) = &self.region {
+            fields.push((
+                "ss:Region",
+                format!(
+                    "{},{},{},{}",
+                    region.x, region.y, region.width, region.height
+                ),
+            ));
+        }
+        if let Some(scale_factor) = self.scale_factor {
+            fields.push(("ss:ScaleFactor", scale_factor.to_string()));
+        }
+        if let Some(window_title) = &self.window_title {
+            fields.push(("ss:WindowTitle", window_title.clone()));
+        }
+        if let Some(app_name) = &self.app_name {
+            fields.push(("ss:AppName", app_name.clone()));
+        }
+        fields
+    }
+
+    /// 生成 XMP 数据包
+    fn to_xmp(&self) -> String {
+        let mut xmp = String::from(
+            "<?xpacket begin=\"\u{feff}\" id=\"W5M0MpCehiHzreSzNTczkc9d\"?>\n\
+             <x:xmpmeta xmlns:x=\"adobe:ns:meta/\">\n\
+             <rdf:RDF xmlns:rdf=\"http://www.w3.org/1999/02/22-rdf-syntax-ns#\">\n",
+        );
+        xmp.push_str(&format!(
+            "<rdf:Description rdf:about=\"\" xmlns:xmp=\"http://ns.adobe.com/xap/1.0/\" xmlns:ss=\"{}\">\n",
+            XMP_NAMESPACE
+        ));
+        for (name, value) in self.xmp_fields() {
+            xmp.push_str(&format!("<{0}>{1}</{0}>\n", name, escape_xml(&value)));
+        }
+        xmp.push_str("</rdf:Description>\n</rdf:RDF>\n</x:xmpmeta>\n<?xpacket end=\"w\"?>");
+        xmp
+    }
+
+    /// 从 XMP 数据包中解析本应用写入的字段
+    fn from_xmp(xmp: &str) -> Self {
+        let field = |name: &str| -> Option<String> {
+            let open = format!("<{}>", name);
+            let close = format!("</{}>", name);
+            let start = xmp.find(&open)? + open.len();
+            let end = start + xmp[start..].find(&close)?;
+            Some(unescape_xml(&xmp[start..end]))
+        };
+
+        let region = field("ss:Region").and_then(|region| {
+            let values: Vec<u32> = region
+                .split(',')
+                .map(|value| value.trim().parse())
+                .collect::<Result<_, _>>()
+                .ok()?;
+            match values[..] {
+                [x, y, width, height] => Some(CaptureRegion {
+                    x,
+                    y,
+                    width,
+                    height,
+                }),
+                _ => None,
+            }
+        });
+
+        Self {
+            captured_at: field("xmp:CreateDate"),
+            monitor: field("ss:Monitor"),
+            region,
+            scale_factor: field("ss:ScaleFactor").and_then(|value| value.parse().ok()),
+            window_title: field("ss:WindowTitle"),
+            app_name: field("ss:AppName"),
+            app_version: field("ss:AppVersion"),
+        }
+    }
+
+    /// EXIF 的 DateTime 格式：`YYYY:MM:DD HH:MM:SS`
+    fn exif_datetime(&self) -> Option<String> {
+        self.captured_at
+            .as_deref()
+            .and_then(|captured_at| DateTime::parse_from_rfc3339(captured_at).ok())
+            .map(|time| time.format("%Y:%m:%d %H:%M:%S").to_string())
+    }
+}
+
+fn escape_xml(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn unescape_xml(value: &str) -> String {
+    value
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&amp;", "&")
+}
+
+/// 写入元数据，替换图像中原有的同类元数据；JPEG 的 ICC 色彩配置等其它信息保留
+pub fn embed(
+    data: &[u8],
+    format: ImageFormat,
+    metadata: &CaptureMetadata,
+) -> Result<Vec<u8>, String> {
+    match format {
+        ImageFormat::Png => png::rewrite(data, Some(metadata)),
+        ImageFormat::Jpeg => jpeg::rewrite(data, Some(metadata)),
+        ImageFormat::WebP => webp::rewrite(data, Some(metadata)),
+        _ => Err(format!("不支持写入元数据的图像格式: {:?}", format)),
+    }
+}
+
+/// 移除图像中的全部元数据（文本块、EXIF、XMP 等）
+pub fn strip(data: &[u8], format: ImageFormat) -> Result<Vec<u8>, String> {
+    match format {
+        ImageFormat::Png => png::rewrite(data, None),
+        ImageFormat::Jpeg => jpeg::rewrite(data, None),
+        ImageFormat::WebP => webp::rewrite(data, None),
+        _ => Err(format!("不支持移除元数据的图像格式: {:?}", format)),
+    }
+}
+
+/// 读取本应用写入的元数据，没有元数据时返回 `None`
+pub fn read(data: &[u8], format: ImageFormat) -> Result<Option<CaptureMetadata>, String> {
+    let xmp = match format {
+        ImageFormat::Png => png::read_xmp(data)?,
+        ImageFormat::Jpeg => jpeg::read_xmp(data)?,
+        ImageFormat::WebP => webp::read_xmp(data)?,
+        _ => return Err(format!("不支持读取元数据的图像格式: {:?}", format)),
+    };
+    Ok(xmp
+        .filter(|xmp| xmp.contains(XMP_NAMESPACE))
+        .map(|xmp| CaptureMetadata::from_xmp(&xmp)))
+}
+
+/// 读取截图文件中的元数据
+#[command]
+pub fn read_screenshot_metadata(path: String) -> Result<Option<CaptureMetadata>, String> {
+    info!("读取截图元数据: {}", path);
+    let data = fs::read(&path).map_err(|e| format!("读取文件失败: {}", e))?;
+    let format = storage::sniff_image_format(&data)?;
+    read(&data, format)
+}
+
+/// 生成只包含 Software 和 DateTime 的最小 EXIF（小端 TIFF）
+fn build_exif(metadata: &CaptureMetadata) -> Vec<u8> {
+    let mut entries: Vec<(u16, Vec<u8>)> = vec![(0x0131, SOFTWARE.as_bytes().to_vec())];
+    if let Some(datetime) = metadata.exif_datetime() {
+        entries.push((0x0132, datetime.into_bytes()));
+    }
+
+    let mut exif = b"II*\0".to_vec();
+    exif.extend_from_slice(&8u32.to_le_bytes());
+    exif.extend_from_slice(&(entries.len() as u16).to_le_bytes());
+
+    let mut value_offset = 8 + 2 + entries.len() * 12 + 4;
+    let mut values = Vec::new();
+    for (tag, mut value) in entries {
+        value.push(0);
+        exif.extend_from_slice(&tag.to_le_bytes());
+        exif.extend_from_slice(&2u16.to_le_bytes()); // ASCII
+        exif.extend_from_slice(&(value.len() as u32).to_le_bytes());
+        exif.extend_from_slice(&(value_offset as u32).to_le_bytes());
+        value_offset += value.len();
+        values.extend_from_slice(&value);
+    }
+    exif.extend_from_slice(&0u32.to_le_bytes());
+    exif.extend_from_slice(&values);
+    exif
+}
+
+fn truncated() -> String {
+    "图像数据不完整，无法处理元数据".to_string()
+}
+
+mod png {
+    use super::*;
+
+    const SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+
+    fn write_chunk(out: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+        out.extend_from_slice(&(data.len() as u32).to_be_bytes());
+        let start = out.len();
+        out.extend_from_slice(kind);
+        out.extend_from_slice(data);
+        let crc = crc32fast::hash(&out[start..]);
+        out.extend_from_slice(&crc.to_be_bytes());
+    }
+
+    fn text_chunk(keyword: &str, text: &str) -> Vec<u8> {
+        [keyword.as_bytes(), b"\0", text.as_bytes()].concat()
+    }
+
+    /// 未压缩的 iTXt，语言和翻译关键字为空
+    fn itxt_chunk(keyword: &str, text: &str) -> Vec<u8> {
+        [keyword.as_bytes(), b"\0\0\0\0\0", text.as_bytes()].concat()
+    }
+
+    /// (类型, 数据, 整个块的字节范围)
+    type Chunk<'a> = ([u8; 4], &'a [u8], std::ops::Range<usize>);
+
+    fn chunks(data: &[u8]) -> Result<Vec<Chunk<'_>>, String> {
+        if !data.starts_with(SIGNATURE) {
+            return Err("不是有效的 PNG 数据".to_string());
+        }
+        let mut chunks = Vec::new();
+        let mut offset = SIGNATURE.len();
+        while offset < data.len() {
+            let header = data.get(offset..offset + 8).ok_or_else(truncated)?;
+            let length = u32::from_be_bytes(header[..4].try_into().unwrap()) as usize;
+            let kind: [u8; 4] = header[4..].try_into().unwrap();
+            let end = offset + 12 + length;
+            let body = data
+                .get(offset + 8..offset + 8 + length)
+                .ok_or_else(truncated)?;
+            if end > data.len() {
+                return Err(truncated());
+            }
+            chunks.push((kind, body, offset..end));
+            offset = end;
+            if &kind == b"IEND" {
+                break;
+            }
+        }
+        Ok(chunks)
+    }
+
+    pub fn rewrite(data: &[u8], metadata: Option<&CaptureMetadata>) -> Result<Vec<u8>, String> {
+        let mut out = SIGNATURE.to_vec();
+        for (kind, _, range) in chunks(data)? {
+            if PNG_METADATA_CHUNKS.contains(&&kind) {
+                continue;
+            }
+            out.extend_from_slice(&data[range]);
+
+            if let (b"IHDR", Some(metadata)) = (&kind, metadata) {
+                write_chunk(&mut out, b"tEXt", &text_chunk("Software", SOFTWARE));
+                if let Some(captured_at) = &metadata.captured_at {
+                    write_chunk(&mut out, b"tEXt", &text_chunk("Creation Time", captured_at));
+                }
+                if let Some(window_title) = &metadata.window_title {
+                    write_chunk(&mut out, b"iTXt", &itxt_chunk("Source", window_title));
+                }
+                write_chunk(
+                    &mut out,
+                    b"iTXt",
+                    &itxt_chunk(PNG_XMP_KEYWORD, &metadata.to_xmp()),
+                );
+            }
+        }
+        Ok(out)
+    }
+
+    pub fn read_xmp(data: &[u8]) -> Result<Option<String>, String> {
+        for (kind, body, _) in chunks(data)? {
+            if &kind != b"iTXt" {
+                continue;
+            }
+            let Some(rest) = body
+                .strip_prefix(PNG_XMP_KEYWORD.as_bytes())
+                .and_then(|rest| rest.strip_prefix(b"\0"))
+            else {
+                continue;
+            };
+            // 压缩标志、压缩方法、语言标签、翻译关键字
+            if rest.first() != Some(&0) {
+                continue;
+            }
+            let mut rest = rest.get(2..).ok_or_else(truncated)?;
+            for _ in 0..2 {
+                let end = rest.iter().position(|&b| b == 0).ok_or_else(truncated)?;
+                rest = &rest[end + 1..];
+            }
+            return Ok(Some(String::from_utf8_lossy(rest).to_string()));
+        }
+        Ok(None)
+    }
+
+    #[cfg(test)]
+    pub fn has_chunk(data: &[u8], kind: &[u8; 4]) -> bool {
+        chunks(data)
+            .map(|chunks| chunks.iter().any(|(k, _, _)| k == kind))
+            .unwrap_or(false)
+    }
+}
+
+mod jpeg {
+    use super::*;
+
+    const SOI: u8 = 0xd8;
+    const SOS: u8 = 0xda;
+    const APP0: u8 = 0xe0;
+    const APP1: u8 = 0xe1;
+    const APP14: u8 = 0xee;
+    const APP15: u8 = 0xef;
+    const COM: u8 = 0xfe;
+
+    /// (标记, 段数据, 整个段的字节范围)
+    type Segment<'a> = (u8, &'a [u8], std::ops::Range<usize>);
+
+    /// 遍历 SOS 之前的段，同时返回图像数据开始的位置
+    fn segments(data: &[u8]) -> Result<(Vec<Segment<'_>>, usize), String> {
+        if data.get(..2) != Some(&[0xff, SOI]) {
+            return Err("不是有效的 JPEG 数据".to_string());
+        }
+        let mut segments = Vec::new();
+        let mut offset = 2;
+        loop {
+            let header = data.get(offset..offset + 4).ok_or_else(truncated)?;
+            if header[0] != 0xff {
+                return Err("JPEG 段标记无效".to_string());
+            }
+            let marker = header[1];
+            if marker == 0xff {
+                // 填充字节
+                offset += 1;
+                continue;
+            }
+            if marker == SOS {
+                return Ok((segments, offset));
+            }
+            let length = u16::from_be_bytes([header[2], header[3]]) as usize;
+            let end = offset + 2 + length;
+            let body = data.get(offset + 4..end).ok_or_else(truncated)?;
+            segments.push((marker, body, offset..end));
+            offset = end;
+        }
+    }
+
+    fn write_segment(
+        out: &mut Vec<u8>,
+        marker: u8,
+        header: &[u8],
+        body: &[u8],
+    ) -> Result<(), String> {
+        let length = 2 + header.len() + body.len();
+        if length > u16::MAX as usize {
+            return Err("元数据过大，无法写入 JPEG".to_string());
+        }
+        out.extend_from_slice(&[0xff, marker]);
+        out.extend_from_slice(&(length as u16).to_be_bytes());
+        out.extend_from_slice(header);
+        out.extend_from_slice(body);
+        Ok(())
+    }
+
+    /// 移除元数据时，APP1-APP13、APP15 和注释段都视为元数据；APP0 (JFIF) 和 APP14 (Adobe 色彩信息) 保留
+    fn is_metadata(marker: u8) -> bool {
+        (APP1..=APP15).contains(&marker) && marker != APP14 || marker == COM
+    }
+
+    /// 写入元数据时只替换 EXIF 和 XMP 所在的 APP1 段，ICC 色彩配置（APP2）等其它段保留
+    fn is_replaced(marker: u8, body: &[u8]) -> bool {
+        marker == APP1 && (body.starts_with(JPEG_EXIF_HEADER) || body.starts_with(JPEG_XMP_HEADER))
+    }
+
+    pub fn rewrite(data: &[u8], metadata: Option<&CaptureMetadata>) -> Result<Vec<u8>, String> {
+        let (segments, image_start) = segments(data)?;
+        let mut out = vec![0xff, SOI];
+        let mut inserted = metadata.is_none();
+
+        for (marker, body, range) in segments {
+            let dropped = match metadata {
+                Some(_) => is_replaced(marker, body),
+                None => is_metadata(marker),
+            };
+            if dropped {
+                continue;
+            }
+            if !inserted && marker != APP0 {
+                insert(&mut out, metadata.unwrap())?;
+                inserted = true;
+            }
+            out.extend_from_slice(&data[range]);
+        }
+        if !inserted {
+            insert(&mut out, metadata.unwrap())?;
+        }
+
+        out.extend_from_slice(&data[image_start..]);
+        Ok(out)
+    }
+
+    fn insert(out: &mut Vec<u8>, metadata: &CaptureMetadata) -> Result<(), String> {
+        write_segment(out, APP1, JPEG_EXIF_HEADER, &build_exif(metadata))?;
+        write_segment(out, APP1, JPEG_XMP_HEADER, metadata.to_xmp().as_bytes())
+    }
+
+    pub fn read_xmp(data: &[u8]) -> Result<Option<String>, String> {
+        let (segments, _) = segments(data)?;
+        Ok(segments
+            .into_iter()
+            .filter(|(marker, _, _)| *marker == APP1)
+            .find_map(|(_, body, _)| body.strip_prefix(JPEG_XMP_HEADER))
+            .map(|xmp| String::from_utf8_lossy(xmp).to_string()))
+    }
+}
+
+mod webp {
+    use super::*;
+
+    #[cfg(test)]
+    fn read_u24(bytes: &[u8]) -> u32 {
+        u32::from_le_bytes([bytes[0], bytes[1], bytes[2], 0])
+    }
+
+    /// (类型, 数据)
+    type Chunk<'a> = ([u8; 4], &'a [u8]);
+
+    /// 遍历 RIFF 中的块
+    fn chunks(data: &[u8]) -> Result<Vec<Chunk<'_>>, String> {
+        if data.get(..4) != Some(b"RIFF") || data.get(8..12) != Some(b"WEBP") {
+            return Err("不是有效的 WebP 数据".to_string());
+        }
+        let mut chunks = Vec::new();
+        let mut offset = 12;
+        while offset + 8 <= data.len() {
+            let kind: [u8; 4] = data[offset..offset + 4].try_into().unwrap();
+            let length =
+                u32::from_le_bytes(data[offset + 4..offset + 8].try_into().unwrap()) as usize;
+            let body = data
+                .get(offset + 8..offset + 8 + length)
+                .ok_or_else(truncated)?;
+            chunks.push((kind, body));
+            offset += 8 + length + (length & 1);
+        }
+        Ok(chunks)
+    }
+
+    fn write_chunk(out: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+        out.extend_from_slice(kind);
+        out.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        out.extend_from_slice(data);
+        if data.len() & 1 == 1 {
+            out.push(0);
+        }
+    }
+
+    /// 简单格式的 WebP 需要补充 VP8X 头才能携带 XMP，画布尺寸和透明度取自图像数据
+    fn build_vp8x(kind: &[u8; 4], body: &[u8]) -> Result<Vec<u8>, String> {
+        let (width, height, alpha) = match kind {
+            b"VP8L" => {
+                let bits =
+                    u32::from_le_bytes(body.get(1..5).ok_or_else(truncated)?.try_into().unwrap());
+                (
+                    (bits & 0x3fff) + 1,
+                    ((bits >> 14) & 0x3fff) + 1,
+                    (bits >> 28) & 1 == 1,
+                )
+            }
+            b"VP8 " => {
+                let header = body.get(6..10).ok_or_else(truncated)?;
+                (
+                    (u16::from_le_bytes([header[0], header[1]]) & 0x3fff) as u32,
+                    (u16::from_le_bytes([header[2], header[3]]) & 0x3fff) as u32,
+                    false,
+                )
+            }
+            _ => return Err("无法识别的 WebP 图像数据".to_string()),
+        };
+
+        let mut vp8x = vec![if alpha { WEBP_FLAG_ALPHA } else { 0 }, 0, 0, 0];
+        vp8x.extend_from_slice(&(width - 1).to_le_bytes()[..3]);
+        vp8x.extend_from_slice(&(height - 1).to_le_bytes()[..3]);
+        Ok(vp8x)
+    }
+
+    pub fn rewrite(data: &[u8], metadata: Option<&CaptureMetadata>) -> Result<Vec<u8>, String> {
+        let chunks = chunks(data)?;
+        let mut out = b"RIFF\0\0\0\0WEBP".to_vec();
+
+        if metadata.is_some() && chunks.first().map(|(kind, _)| kind) != Some(b"VP8X") {
+            let (kind, body) = chunks.first().ok_or_else(truncated)?;
+            let mut vp8x = build_vp8x(kind, body)?;
+            vp8x[0] |= WEBP_FLAG_XMP;
+            write_chunk(&mut out, b"VP8X", &vp8x);
+        }
+
+        for (kind, body) in &chunks {
+            match kind {
+                b"EXIF" | b"XMP " => continue,
+                b"VP8X" => {
+                    let mut vp8x = body.to_vec();
+                    vp8x[0] &= !(WEBP_FLAG_EXIF | WEBP_FLAG_XMP);
+                    if metadata.is_some() {
+                        vp8x[0] |= WEBP_FLAG_XMP;
+                    }
+                    write_chunk(&mut out, kind, &vp8x);
+                }
+                _ => write_chunk(&mut out, kind, body),
+            }
+        }
+
+        if let Some(metadata) = metadata {
+            write_chunk(&mut out, b"XMP ", metadata.to_xmp().as_bytes());
+        }
+
+        let riff_size = (out.len() - 8) as u32;
+        out[4..8].copy_from_slice(&riff_size.to_le_bytes());
+        Ok(out)
+    }
+
+    pub fn read_xmp(data: &[u8]) -> Result<Option<String>, String> {
+        Ok(chunks(data)?
+            .into_iter()
+            .find(|(kind, _)| kind == b"XMP ")
+            .map(|(_, body)| String::from_utf8_lossy(body).to_string()))
+    }
+
+    #[cfg(test)]
+    pub fn canvas_size(data: &[u8]) -> Option<(u32, u32)> {
+        chunks(data)
+            .ok()?
+            .into_iter()
+            .find(|(kind, _)| kind == b"VP8X")
+            .map(|(_, body)| (read_u24(&body[4..7]) + 1, read_u24(&body[7..10]) + 1))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    fn encode(format: ImageFormat) -> Vec<u8> {
+        let image = image::RgbImage::from_pixel(33, 17, image::Rgb([10, 200, 30]));
+        let mut buffer = Vec::new();
+        image
+            .write_to(&mut Cursor::new(&mut buffer), format)
+            .unwrap();
+        buffer
+    }
+
+    fn metadata() -> CaptureMetadata {
+        CaptureMetadata {
+            captured_at: Some("2024-05-06T07:08:09+08:00".to_string()),
+            monitor: Some("DELL U2720Q".to_string()),
+            region: Some(CaptureRegion {
+                x: 10,
+                y: 20,
+                width: 33,
+                height: 17,
+            }),
+            scale_factor: Some(1.5),
+            window_title: Some("<main.rs> & 终端".to_string()),
+            app_name: Some("Code".to_string()),
+            app_version: Some(env!("CARGO_PKG_VERSION").to_string()),
+        }
+    }
+
+    #[test]
+    fn test_embed_and_read_roundtrip() {
+        for format in [ImageFormat::Png, ImageFormat::Jpeg, ImageFormat::WebP] {
+            let original = encode(format);
+            assert_eq!(read(&original, format).unwrap(), None);
+
+            let embedded = embed(&original, format, &metadata()).unwrap();
+            assert_eq!(
+                read(&embedded, format).unwrap(),
+                Some(metadata()),
+                "{:?}",
+                format
+            );
+
+            // 写入元数据后图像仍然可以正常解码
+            let decoded = image::load_from_memory_with_format(&embedded, format).unwrap();
+            assert_eq!((decoded.width(), decoded.height()), (33, 17));
+
+            // 重复写入不会产生多份元数据
+            let twice = embed(&embedded, format, &metadata()).unwrap();
+            assert_eq!(twice.len(), embedded.len(), "{:?}", format);
+        }
+    }
+
+    #[test]
+    fn test_strip_removes_metadata() {
+        for format in [ImageFormat::Png, ImageFormat::Jpeg, ImageFormat::WebP] {
+            let embedded = embed(&encode(format), format, &metadata()).unwrap();
+            let stripped = strip(&embedded, format).unwrap();
+            assert_eq!(read(&stripped, format).unwrap(), None);
+            assert!(!stripped
+                .windows(b"DELL".len())
+                .any(|window| window == b"DELL"));
+            image::load_from_memory_with_format(&stripped, format).unwrap();
+        }
+        let png = strip(
+            &embed(&encode(ImageFormat::Png), ImageFormat::Png, &metadata()).unwrap(),
+            ImageFormat::Png,
+        )
+        .unwrap();
+        assert!(!png::has_chunk(&png, b"tEXt"));
+    }
+
+    #[test]
+    fn test_jpeg_embed_keeps_icc_profile() {
+        // 在 APP0 之后插入一个 ICC 配置段
+        let mut original = encode(ImageFormat::Jpeg);
+        let app0_end = 4 + u16::from_be_bytes([original[4], original[5]]) as usize;
+        let icc = [&[0xff, 0xe2, 0x00, 0x14][..], b"ICC_PROFILE\0\x01\x01abcd"].concat();
+        original.splice(app0_end..app0_end, icc.iter().copied());
+
+        let contains = |data: &[u8]| data.windows(icc.len()).any(|window| window == icc);
+        let embedded = embed(&original, ImageFormat::Jpeg, &metadata()).unwrap();
+        assert!(contains(&embedded));
+        assert_eq!(
+            read(&embedded, ImageFormat::Jpeg).unwrap(),
+            Some(metadata())
+        );
+        image::load_from_memory_with_format(&embedded, ImageFormat::Jpeg).unwrap();
+
+        assert!(!contains(&strip(&embedded, ImageFormat::Jpeg).unwrap()));
+    }
+
+    #[test]
+    fn test_webp_vp8x_canvas() {
+        let embedded = embed(&encode(ImageFormat::WebP), ImageFormat::WebP, &metadata()).unwrap();
+        assert_eq!(webp::canvas_size(&embedded), Some((33, 17)));
+    }
+}
@@ -0,0 +1,358 @@
+use crate::warm;
+use image::RgbaImage;
+use log::{error, info};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::thread;
+use std::time::{Duration, Instant};
+use tauri::{command, AppHandle, Emitter};
+use xcap::Monitor;
+
+/// 显示器增加、移除或布局变化时发出的事件，载荷为 `MonitorsChanged`
+pub const MONITORS_CHANGED_EVENT: &str = "monitors-changed";
+
+/// 冻结画面的有效期，超过后区域截图重新截屏
+const FROZEN_FRAME_TTL: Duration = Duration::from_secs(60);
+
+/// 无法接收显示器变化通知时，定期检查显示器的间隔
+const POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+/// 显示器信息，序号与 `--monitor` 参数一致
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct MonitorInfo {
+    pub index: usize,
+    pub name: String,
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+    pub scale_factor: f32,
+    pub is_primary: bool,
+}
+
+impl MonitorInfo {
+    fn same_geometry(&self, other: &MonitorInfo) -> bool {
+        (self.x, self.y, self.width, self.height, self.scale_factor)
+            == (
+                other.x,
+                other.y,
+                other.width,
+                other.height,
+                other.scale_factor,
+            )
+    }
+}
+
+/// `monitors-changed` 事件的载荷
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct MonitorsChanged {
+    pub monitors: Vec<MonitorInfo>,
+    /// 新增显示器的名称
+    pub added: Vec<String>,
+    /// 移除显示器的名称
+    pub removed: Vec<String>,
+}
+
+/// 全屏截图时保存的画面，选区确定后从中裁剪
+struct FrozenFrame {
+    monitor: MonitorInfo,
+    frame: Arc<RgbaImage>,
+    captured_at: Instant,
+}
+
+/// 缓存的显示器布局和冻结画面
+///
+/// 布局在启动时和显示器变化时刷新，命令不需要每次都重新枚举显示器。
+#[derive(Default)]
+pub struct MonitorRegistry {
+    layout: Mutex<Option<Vec<MonitorInfo>>>,
+    frozen: Mutex<HashMap<String, FrozenFrame>>,
+    app: OnceLock<AppHandle>,
+}
+
+impl MonitorRegistry {
+    /// 缓存的显示器布局，尚未枚举时先枚举一次
+    pub fn monitors(&self) -> Result<Vec<MonitorInfo>, String> {
+        let mut layout = self.layout.lock().unwrap();
+        if let Some(monitors) = layout.as_ref() {
+            return Ok(monitors.clone());
+        }
+        let monitors = enumerate()?;
+        *layout = Some(monitors.clone());
+        Ok(monitors)
+    }
+
+    /// 主显示器（没有主显示器时为第一个显示器）
+    pub fn primary(&self) -> Result<MonitorInfo, String> {
+        let monitors = self.monitors()?;
+        let primary = monitors
+            .iter()
+            .position(|monitor| monitor.is_primary)
+            .unwrap_or(0);
+        monitors
+            .into_iter()
+            .nth(primary)
+            .ok_or("未找到可用显示器".to_string())
+    }
+
+    /// 重新枚举显示器，布局有变化时发出 `monitors-changed` 事件
+    pub fn refresh(&self) -> Result<Option<MonitorsChanged>, String> {
+        let monitors = enumerate()?;
+        let previous = self.layout.lock().unwrap().replace(monitors.clone());
+        // 第一次枚举不算变化
+        let Some(previous) = previous else {
+            return Ok(None);
+        };
+        let change = self.apply(&previous, monitors);
+
+        if let Some(change) = &change {
+            info!(
+                "显示器布局已变化: 新增 {:?}, 移除 {:?}, 当前 {} 个显示器",
+                change.added,
+                change.removed,
+                change.monitors.len()
+            );
+            warm::invalidate();
+            if let Some(app) = self.app.get() {
+                if let Err(e) = app.emit(MONITORS_CHANGED_EVENT, change) {
+                    error!("发送显示器变化事件失败: {}", e);
+                }
+            }
+        }
+        Ok(change)
+    }
+
+    /// 比较新旧布局，丢弃已经不对应屏幕内容的冻结画面
+    fn apply(
+        &self,
+        previous: &[MonitorInfo],
+        current: Vec<MonitorInfo>,
+    ) -> Option<MonitorsChanged> {
+        if previous == current.as_slice() {
+            return None;
+        }
+
+        let names = |monitors: &[MonitorInfo], others: &[MonitorInfo]| -> Vec<String> {
+            monitors
+                .iter()
+                .filter(|monitor| !others.iter().any(|other| other.name == monitor.name))
+                .map(|monitor| monitor.name.clone())
+                .collect()
+        };
+        let added = names(&current, previous);
+        let removed = names(previous, &current);
+
+        // 显示器被移除或位置、尺寸变化后，冻结的画面不能再用来裁剪
+        self.frozen.lock().unwrap().retain(|name, frozen| {
+            let valid = current
+                .iter()
+                .any(|monitor| monitor.name == *name && monitor.same_geometry(&frozen.monitor));
+            if !valid {
+                info!("显示器 {} 已变化，丢弃冻结画面", name);
+            }
+            valid
+        });
+
+        Some(MonitorsChanged {
+            monitors: current,
+            added,
+            removed,
+        })
+    }
+
+    /// 保存显示器的冻结画面，替换之前的画面
+    pub fn freeze(&self, monitor: &MonitorInfo, frame: Arc<RgbaImage>) {
+        self.frozen.lock().unwrap().insert(
+            monitor.name.clone(),
+            FrozenFrame {
+                monitor: monitor.clone(),
+                frame,
+                captured_at: Instant::now(),
+            },
+        );
+    }
+
+    /// 取出显示器的冻结画面，每个画面只使用一次，过期或布局已变化时返回 `None`
+    pub fn take_frozen(&self, monitor: &MonitorInfo) -> Option<Arc<RgbaImage>> {
+        let frozen = self.frozen.lock().unwrap().remove(&monitor.name)?;
+        if frozen.captured_at.elapsed() > FROZEN_FRAME_TTL || !frozen.monitor.same_geometry(monitor)
+        {
+            info!("显示器 {} 的冻结画面已失效", monitor.name);
+            return None;
+        }
+        Some(frozen.frame)
+    }
+}
+
+/// 进程内共享的显示器注册表
+pub fn registry() -> &'static MonitorRegistry {
+    static REGISTRY: OnceLock<MonitorRegistry> = OnceLock::new();
+    REGISTRY.get_or_init(MonitorRegistry::default)
+}
+
+/// 枚举所有显示器
+pub fn enumerate() -> Result<Vec<MonitorInfo>, String> {
+    let monitors = Monitor::all().map_err(|e| {
+        error!("获取显示器列表失败: {}", e);
+        format!("获取显示器列表失败: {}", e)
+    })?;
+    Ok(monitors
+        .iter()
+        .enumerate()
+        .map(|(index, monitor)| MonitorInfo {
+            index,
+            name: monitor.name().unwrap_or_default(),
+            x: monitor.x().unwrap_or_default(),
+            y: monitor.y().unwrap_or_default(),
+            width: monitor.width().unwrap_or_default(),
+            height: monitor.height().unwrap_or_default(),
+            scale_factor: monitor.scale_factor().unwrap_or(1.0),
+            is_primary: monitor.is_primary().unwrap_or(false),
+        })
+        .collect())
+}
+
+/// 已从缓存布局中选出的主显示器对应的 xcap 句柄
+///
+/// 按缓存的位置查找显示器，失败时说明布局已过期，刷新后重新选择主显示器再试一次。
+pub fn primary_handle(monitor: MonitorInfo) -> Result<(MonitorInfo, Monitor), String> {
+    let registry = registry();
+    match Monitor::from_point(monitor.x, monitor.y) {
+        Ok(handle) => return Ok((monitor, handle)),
+        Err(e) => info!("按缓存的位置获取显示器 {} 失败: {}", monitor.name, e),
+    }
+
+    registry.refresh()?;
+    let monitor = registry.primary()?;
+    let handle = Monitor::from_point(monitor.x, monitor.y).map_err(|e| {
+        error!("获取显示器 {} 失败: {}", monitor.name, e);
+        format!("获取显示器失败: {}", e)
+    })?;
+    Ok((monitor, handle))
+}
+
+fn refresh_logged() {
+    if let Err(e) = registry().refresh() {
+        error!("刷新显示器列表失败: {}", e);
+    }
+}
+
+/// 枚举显示器并开始监听显示器变化
+pub fn init(app: &AppHandle) {
+    let _ = registry().app.set(app.clone());
+
+    thread::spawn(|| {
+        refresh_logged();
+
+        #[cfg(all(unix, not(target_os = "macos")))]
+        if let Err(e) = randr::watch(refresh_logged) {
+            info!("无法监听 XRandR 显示器变化通知: {}，改为定期检查", e);
+        }
+
+        loop {
+            thread::sleep(POLL_INTERVAL);
+            refresh_logged();
+        }
+    });
+}
+
+/// 获取缓存的显示器列表
+#[command]
+pub fn get_monitors() -> Result<Vec<MonitorInfo>, String> {
+    registry().monitors()
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+mod randr {
+    use crate::capture::x11;
+    use log::info;
+    use std::thread;
+    use std::time::Duration;
+    use x11rb::connection::Connection;
+    use x11rb::protocol::randr::{ConnectionExt, NotifyMask};
+    use x11rb::protocol::Event;
+
+    /// 一次插拔会连续产生多个通知，等待这段时间后合并处理
+    const DEBOUNCE: Duration = Duration::from_millis(300);
+
+    /// 订阅 XRandR 通知，显示器变化时调用 `on_change`，只在连接出错时返回
+    pub fn watch(on_change: fn()) -> Result<(), String> {
+        let (conn, screen_num) = x11::connect()?;
+        let root = conn.setup().roots[screen_num].root;
+        conn.randr_query_version(1, 2)
+            .map_err(|e| format!("发送 XRandR 版本请求失败: {}", e))?
+            .reply()
+            .map_err(|e| format!("X 服务器不支持 XRandR: {}", e))?;
+        conn.randr_select_input(
+            root,
+            NotifyMask::SCREEN_CHANGE | NotifyMask::OUTPUT_CHANGE | NotifyMask::CRTC_CHANGE,
+        )
+        .map_err(|e| format!("订阅 XRandR 通知失败: {}", e))?;
+        conn.flush()
+            .map_err(|e| format!("订阅 XRandR 通知失败: {}", e))?;
+        info!("正在监听 XRandR 显示器变化通知");
+
+        loop {
+            let event = conn
+                .wait_for_event()
+                .map_err(|e| format!("读取 X11 事件失败: {}", e))?;
+            if !matches!(
+                event,
+                Event::RandrScreenChangeNotify(_) | Event::RandrNotify(_)
+            ) {
+                continue;
+            }
+
+            thread::sleep(DEBOUNCE);
+            while conn
+                .poll_for_event()
+                .map_err(|e| format!("读取 X11 事件失败: {}", e))?
+                .is_some()
+            {}
+            on_change();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn monitor(index: usize, name: &str, x: i32, width: u32) -> MonitorInfo {
+        MonitorInfo {
+            index,
+            name: name.to_string(),
+            x,
+            y: 0,
+            width,
+            height: 1080,
+            scale_factor: 1.0,
+            is_primary: index == 0,
+        }
+    }
+
+    #[test]
+    fn test_apply_reports_changes_and_drops_stale_frames() {
+        let registry = MonitorRegistry::default();
+        let previous = vec![
+            monitor(0, "eDP-1", 0, 1920),
+            monitor(1, "HDMI-1", 1920, 1920),
+        ];
+        let frame = Arc::new(RgbaImage::new(1, 1));
+        registry.freeze(&previous[0], frame.clone());
+        registry.freeze(&previous[1], frame);
+
+        assert_eq!(registry.apply(&previous, previous.clone()), None);
+
+        let current = vec![monitor(0, "eDP-1", 0, 1920), monitor(1, "DP-2", 1920, 2560)];
+        let change = registry.apply(&previous, current.clone()).unwrap();
+        assert_eq!(change.added, vec!["DP-2".to_string()]);
+        assert_eq!(change.removed, vec!["HDMI-1".to_string()]);
+        assert_eq!(change.monitors, current);
+
+        assert!(registry.take_frozen(&current[0]).is_some());
+        assert!(registry.take_frozen(&current[0]).is_none());
+        assert!(registry.take_frozen(&previous[1]).is_none());
+    }
+}
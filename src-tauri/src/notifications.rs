@@ -0,0 +1,172 @@
+use crate::screenshot;
+use crate::settings;
+use crate::tray;
+use log::{error, info, warn};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use tauri::AppHandle;
+use tauri_plugin_notification::NotificationExt;
+use tauri_plugin_opener::OpenerExt;
+
+/// 截图完成时是否显示系统通知
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NotificationSettings {
+    pub on_success: bool,
+    pub on_failure: bool,
+}
+
+impl Default for NotificationSettings {
+    fn default() -> Self {
+        Self {
+            on_success: true,
+            on_failure: true,
+        }
+    }
+}
+
+/// 截图完成通知上的操作按钮
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NotificationAction {
+    Open,
+    CopyPath,
+    ShowInFolder,
+    Delete,
+}
+
+impl NotificationAction {
+    pub const ALL: [NotificationAction; 4] = [
+        NotificationAction::Open,
+        NotificationAction::CopyPath,
+        NotificationAction::ShowInFolder,
+        NotificationAction::Delete,
+    ];
+
+    /// 通知服务回传的操作标识
+    pub fn id(self) -> &'static str {
+        match self {
+            NotificationAction::Open => "open",
+            NotificationAction::CopyPath => "copy_path",
+            NotificationAction::ShowInFolder => "show_in_folder",
+            NotificationAction::Delete => "delete",
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            NotificationAction::Open => "打开",
+            NotificationAction::CopyPath => "复制路径",
+            NotificationAction::ShowInFolder => "在文件夹中显示",
+            NotificationAction::Delete => "删除",
+        }
+    }
+
+    pub fn from_id(id: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|action| action.id() == id)
+    }
+}
+
+/// 执行用户在通知上点击的操作
+pub fn handle_action(app: &AppHandle, action: NotificationAction, path: &Path) {
+    info!("通知操作「{}」: {:?}", action.label(), path);
+    let result = match action {
+        NotificationAction::Open => app
+            .opener()
+            .open_path(path.to_string_lossy(), None::<&str>)
+            .map_err(|e| e.to_string()),
+        NotificationAction::CopyPath => screenshot::copy_text_to_clipboard(&path.to_string_lossy()),
+        NotificationAction::ShowInFolder => app
+            .opener()
+            .reveal_item_in_dir(path)
+            .map_err(|e| e.to_string()),
+        NotificationAction::Delete => {
+            tray::delete_screenshot(app, path);
+            Ok(())
+        }
+    };
+    if let Err(e) = result {
+        error!("通知操作「{}」失败: {}", action.label(), e);
+    }
+}
+
+/// 显示带缩略图和操作按钮的通知，在后台线程中等待用户点击
+#[cfg(all(unix, not(target_os = "macos")))]
+fn show_saved(app: &AppHandle, path: &Path) -> Result<(), String> {
+    let mut notification = notify_rust::Notification::new();
+    notification
+        .appname("screenshot")
+        .summary("截图已保存")
+        .body(&path.to_string_lossy());
+    match crate::thumbnails::thumbnail_for_file(app, path) {
+        Ok(thumbnail) => {
+            notification.image_path(&thumbnail.to_string_lossy());
+        }
+        Err(e) => warn!("生成通知缩略图失败: {}", e),
+    }
+    for action in NotificationAction::ALL {
+        notification.action(action.id(), action.label());
+    }
+    let handle = notification
+        .show()
+        .map_err(|e| format!("显示通知失败: {}", e))?;
+
+    let app = app.clone();
+    let path = path.to_path_buf();
+    std::thread::spawn(move || {
+        handle.wait_for_action(|id| {
+            // 通知被关闭时回传 "__closed"，不对应任何操作
+            if let Some(action) = NotificationAction::from_id(id) {
+                handle_action(&app, action, &path);
+            }
+        });
+    });
+    Ok(())
+}
+
+/// 其他平台的通知插件不支持操作按钮，只显示保存路径
+#[cfg(not(all(unix, not(target_os = "macos"))))]
+fn show_saved(app: &AppHandle, path: &Path) -> Result<(), String> {
+    app.notification()
+        .builder()
+        .title("截图已保存")
+        .body(path.to_string_lossy())
+        .show()
+        .map_err(|e| format!("显示通知失败: {}", e))
+}
+
+fn show_failed(app: &AppHandle, message: &str) -> Result<(), String> {
+    app.notification()
+        .builder()
+        .title("截图失败")
+        .body(message)
+        .show()
+        .map_err(|e| format!("显示通知失败: {}", e))
+}
+
+/// 按设置为截图结果显示通知，`Ok(None)` 表示没有保存文件，不显示通知
+///
+/// 截图时窗口是隐藏的，通知是用户得知截图结果的唯一途径。
+pub fn notify_capture(app: &AppHandle, result: &Result<Option<String>, String>) {
+    let settings = settings::current(app).notifications;
+    let shown = match result {
+        Ok(Some(path)) if settings.on_success => show_saved(app, Path::new(path)),
+        Err(e) if settings.on_failure => show_failed(app, e),
+        _ => return,
+    };
+    if let Err(e) = shown {
+        warn!("{}", e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_action_ids_round_trip() {
+        for action in NotificationAction::ALL {
+            assert_eq!(NotificationAction::from_id(action.id()), Some(action));
+        }
+        assert_eq!(NotificationAction::from_id("__closed"), None);
+    }
+}
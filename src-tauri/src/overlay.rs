@@ -0,0 +1,695 @@
+use log::{error, info, warn};
+use std::panic;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, PoisonError, TryLockError};
+use std::thread;
+use std::time::Duration;
+use tauri::{command, Emitter, WebviewWindow};
+
+/// 进入覆盖模式前需要恢复的内容
+enum Restore {
+    /// macOS 演示模式，退出时恢复默认选项
+    #[cfg(target_os = "macos")]
+    Presentation,
+    /// X11 窗口上由我们添加的 `_NET_WM_STATE`
+    #[cfg(all(unix, not(target_os = "macos")))]
+    X11(x11::Restore),
+    /// Wayland 下窗口已经成为 layer-shell 的覆盖层
+    #[cfg(all(unix, not(target_os = "macos")))]
+    LayerShell,
+    /// 只能把窗口置顶
+    #[cfg_attr(target_os = "macos", allow(dead_code))]
+    AlwaysOnTop,
+}
+
+/// 覆盖模式的事件：前端没有按时退出覆盖模式，已由看门狗自动恢复
+pub const OVERLAY_EXPIRED_EVENT: &str = "overlay-mode-expired";
+
+/// 进入覆盖模式后前端必须在这段时间内退出，否则自动恢复
+const WATCHDOG_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// 覆盖模式的守卫，被丢弃时恢复进入前的状态
+struct OverlayGuard {
+    /// 第几次进入覆盖模式，看门狗据此判断是否仍是它负责的那一次
+    generation: u64,
+    restore: Option<Box<dyn FnOnce() -> Result<(), String> + Send>>,
+}
+
+impl OverlayGuard {
+    fn new(generation: u64, restore: impl FnOnce() -> Result<(), String> + Send + 'static) -> Self {
+        Self {
+            generation,
+            restore: Some(Box::new(restore)),
+        }
+    }
+
+    /// 立即恢复并返回结果
+    fn release(mut self) -> Result<(), String> {
+        self.restore.take().map_or(Ok(()), |restore| restore())
+    }
+}
+
+impl Drop for OverlayGuard {
+    fn drop(&mut self) {
+        if let Some(restore) = self.restore.take() {
+            if let Err(e) = restore() {
+                error!("恢复覆盖模式前的状态失败: {}", e);
+            }
+        }
+    }
+}
+
+/// 覆盖模式的状态：`None` 为未进入，`Some` 为已进入并持有恢复用的守卫
+static ACTIVE: Mutex<Option<OverlayGuard>> = Mutex::new(None);
+static GENERATION: AtomicU64 = AtomicU64::new(0);
+
+/// 进入覆盖模式：选区窗口盖住所有显示器，包括面板、Dock 和菜单栏
+pub fn enter(window: &WebviewWindow) -> Result<(), String> {
+    let mut active = ACTIVE.lock().unwrap_or_else(PoisonError::into_inner);
+    if active.is_some() {
+        info!("已处于覆盖模式");
+        return Ok(());
+    }
+    let restore = platform_enter(window)?;
+    let generation = GENERATION.fetch_add(1, Ordering::SeqCst) + 1;
+    let target = window.clone();
+    *active = Some(OverlayGuard::new(generation, move || {
+        platform_exit(&target, restore)
+    }));
+    info!("已进入覆盖模式");
+
+    let window = window.clone();
+    thread::spawn(move || {
+        thread::sleep(WATCHDOG_TIMEOUT);
+        expire(&window, generation);
+    });
+    Ok(())
+}
+
+/// 退出覆盖模式，恢复进入前的窗口和系统状态
+pub fn exit() -> Result<(), String> {
+    let guard = ACTIVE.lock().unwrap_or_else(PoisonError::into_inner).take();
+    let Some(guard) = guard else {
+        return Ok(());
+    };
+    guard.release()?;
+    info!("已退出覆盖模式");
+    Ok(())
+}
+
+/// 窗口关闭、应用退出等情况下恢复覆盖模式，未进入时什么也不做
+pub fn restore(reason: &str) {
+    let guard = ACTIVE.lock().unwrap_or_else(PoisonError::into_inner).take();
+    if let Some(guard) = guard {
+        info!("{}，恢复覆盖模式前的状态", reason);
+        drop(guard);
+    }
+}
+
+/// 看门狗：前端没有在超时前退出这一次覆盖模式时自动恢复
+fn expire(window: &WebviewWindow, generation: u64) {
+    let mut active = ACTIVE.lock().unwrap_or_else(PoisonError::into_inner);
+    if active.as_ref().map(|guard| guard.generation) != Some(generation) {
+        return;
+    }
+    let guard = active.take();
+    drop(active);
+    warn!(
+        "覆盖模式已持续 {:?}，前端没有退出，自动恢复",
+        WATCHDOG_TIMEOUT
+    );
+    drop(guard);
+    if let Err(e) = window.emit(OVERLAY_EXPIRED_EVENT, ()) {
+        error!("发送覆盖模式超时事件失败: {}", e);
+    }
+}
+
+/// 是否正在重新创建窗口（Wayland 下退出 layer-shell 覆盖层时），此时没有窗口也不应退出应用
+pub fn recreating_window() -> bool {
+    #[cfg(all(unix, not(target_os = "macos")))]
+    {
+        layer_shell::RECREATING.load(Ordering::SeqCst)
+    }
+    #[cfg(not(all(unix, not(target_os = "macos"))))]
+    {
+        false
+    }
+}
+
+/// 安装 panic 钩子，崩溃时先恢复 Dock、菜单栏和窗口状态
+pub fn install_panic_hook() {
+    let previous = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        previous(info);
+        // 正在进入或退出覆盖模式的线程持有锁时不能等待，否则会死锁
+        let guard = match ACTIVE.try_lock() {
+            Ok(mut active) => active.take(),
+            Err(TryLockError::Poisoned(active)) => active.into_inner().take(),
+            Err(TryLockError::WouldBlock) => None,
+        };
+        if let Some(guard) = guard {
+            error!("程序崩溃，恢复覆盖模式前的状态");
+            drop(guard);
+        }
+    }));
+}
+
+/// 设置选区窗口的覆盖模式
+///
+/// macOS 隐藏 Dock 和菜单栏；X11 设置 `_NET_WM_STATE_FULLSCREEN`/`_NET_WM_STATE_ABOVE`
+/// 并铺满所有显示器；Wayland 在合成器支持时使用 layer-shell 的覆盖层。
+/// 窗口关闭、应用退出、程序崩溃或超过 2 分钟没有退出时会自动恢复。
+#[command]
+pub async fn set_overlay_mode(window: WebviewWindow, enabled: bool) -> Result<(), String> {
+    info!("set_overlay_mode 被调用，参数: enabled={}", enabled);
+    if enabled {
+        enter(&window)
+    } else {
+        exit()
+    }
+}
+
+#[cfg(target_os = "macos")]
+fn platform_enter(window: &WebviewWindow) -> Result<Restore, String> {
+    on_main_thread(window, |_| {
+        macos::set_presentation_mode(true);
+        Ok(())
+    })?;
+    Ok(Restore::Presentation)
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+fn platform_enter(window: &WebviewWindow) -> Result<Restore, String> {
+    if crate::capture::x11::is_wayland_session() {
+        match layer_shell::enter(window) {
+            Ok(true) => return Ok(Restore::LayerShell),
+            Ok(false) => info!("合成器不支持 layer-shell，改为置顶窗口"),
+            Err(e) => warn!("设置 layer-shell 覆盖层失败，改为置顶窗口: {}", e),
+        }
+    } else {
+        match x11::window_id(window).and_then(x11::enter) {
+            Ok(restore) => return Ok(Restore::X11(restore)),
+            Err(e) => warn!("设置 X11 全屏覆盖失败，改为置顶窗口: {}", e),
+        }
+    }
+    always_on_top(window)
+}
+
+#[cfg(not(unix))]
+fn platform_enter(window: &WebviewWindow) -> Result<Restore, String> {
+    always_on_top(window)
+}
+
+#[cfg_attr(target_os = "macos", allow(dead_code))]
+fn always_on_top(window: &WebviewWindow) -> Result<Restore, String> {
+    window
+        .set_always_on_top(true)
+        .map_err(|e| format!("窗口置顶失败: {}", e))?;
+    Ok(Restore::AlwaysOnTop)
+}
+
+fn platform_exit(window: &WebviewWindow, restore: Restore) -> Result<(), String> {
+    match restore {
+        // 看门狗和 panic 钩子不在主线程上，AppKit 只能在主线程上调用
+        #[cfg(target_os = "macos")]
+        Restore::Presentation => on_main_thread(window, |_| {
+            macos::set_presentation_mode(false);
+            Ok(())
+        }),
+        #[cfg(all(unix, not(target_os = "macos")))]
+        Restore::X11(restore) => x11::exit(restore),
+        #[cfg(all(unix, not(target_os = "macos")))]
+        Restore::LayerShell => layer_shell::exit(window),
+        Restore::AlwaysOnTop => window
+            .set_always_on_top(false)
+            .map_err(|e| format!("取消窗口置顶失败: {}", e)),
+    }
+}
+
+/// 计算 `_NET_WM_FULLSCREEN_MONITORS` 的参数：最上、最下、最左、最右的显示器序号
+///
+/// 显示器是 (x, y, width, height)，没有显示器时返回 `None`。
+#[cfg_attr(not(all(unix, not(target_os = "macos"))), allow(dead_code))]
+fn fullscreen_monitors(monitors: &[(i32, i32, u32, u32)]) -> Option<[u32; 4]> {
+    let index_by = |key: &dyn Fn(&(i32, i32, u32, u32)) -> i64, max: bool| {
+        let indexed = monitors.iter().enumerate();
+        let found = if max {
+            indexed.max_by_key(|(_, monitor)| key(monitor))
+        } else {
+            indexed.min_by_key(|(_, monitor)| key(monitor))
+        };
+        found.map(|(index, _)| index as u32)
+    };
+    Some([
+        index_by(&|&(_, y, _, _)| y.into(), false)?,
+        index_by(&|&(_, y, _, h)| i64::from(y) + i64::from(h), true)?,
+        index_by(&|&(x, _, _, _)| x.into(), false)?,
+        index_by(&|&(x, _, w, _)| i64::from(x) + i64::from(w), true)?,
+    ])
+}
+
+/// 等待主线程执行 GTK / AppKit 操作的最长时间
+#[cfg(unix)]
+const MAIN_THREAD_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// 在主线程上执行 GTK / AppKit 操作并等待结果
+#[cfg(unix)]
+fn on_main_thread<T: Send + 'static>(
+    window: &WebviewWindow,
+    f: impl FnOnce(&WebviewWindow) -> Result<T, String> + Send + 'static,
+) -> Result<T, String> {
+    let (tx, rx) = std::sync::mpsc::channel();
+    let target = window.clone();
+    window
+        .run_on_main_thread(move || {
+            let _ = tx.send(f(&target));
+        })
+        .map_err(|e| format!("无法在主线程上执行: {}", e))?;
+    // 主线程卡住或已经退出时不能一直等待，例如在 panic 钩子中恢复
+    rx.recv_timeout(MAIN_THREAD_TIMEOUT)
+        .map_err(|_| "主线程没有返回结果".to_string())?
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+mod x11 {
+    use super::on_main_thread;
+    use log::{info, warn};
+    use tauri::WebviewWindow;
+    use x11rb::connection::Connection;
+    use x11rb::protocol::randr::ConnectionExt as _;
+    use x11rb::protocol::xproto::{
+        Atom, AtomEnum, ClientMessageEvent, ConnectionExt as _, EventMask, Window,
+    };
+    use x11rb::rust_connection::RustConnection;
+
+    const NET_WM_STATE_REMOVE: u32 = 0;
+    const NET_WM_STATE_ADD: u32 = 1;
+    /// EWMH 消息的来源：普通应用
+    const SOURCE_APPLICATION: u32 = 1;
+
+    /// 退出覆盖模式时需要撤销的窗口状态
+    pub struct Restore {
+        window: Window,
+        /// 进入前窗口没有、由我们添加的 `_NET_WM_STATE` 原子
+        added: Vec<Atom>,
+    }
+
+    struct Atoms {
+        wm_state: Atom,
+        fullscreen: Atom,
+        above: Atom,
+        fullscreen_monitors: Atom,
+    }
+
+    impl Atoms {
+        fn intern(conn: &RustConnection) -> Result<Self, String> {
+            let atom = |name: &[u8]| -> Result<Atom, String> {
+                conn.intern_atom(false, name)
+                    .map_err(|e| e.to_string())?
+                    .reply()
+                    .map(|reply| reply.atom)
+                    .map_err(|e| format!("获取 {} 原子失败: {}", String::from_utf8_lossy(name), e))
+            };
+            Ok(Self {
+                wm_state: atom(b"_NET_WM_STATE")?,
+                fullscreen: atom(b"_NET_WM_STATE_FULLSCREEN")?,
+                above: atom(b"_NET_WM_STATE_ABOVE")?,
+                fullscreen_monitors: atom(b"_NET_WM_FULLSCREEN_MONITORS")?,
+            })
+        }
+    }
+
+    /// 获取 GTK 窗口对应的 X11 窗口
+    pub fn window_id(window: &WebviewWindow) -> Result<Window, String> {
+        on_main_thread(window, |window| {
+            use gtk::prelude::*;
+
+            let gdk_window = window
+                .gtk_window()
+                .map_err(|e| format!("获取 GTK 窗口失败: {}", e))?
+                .window()
+                .ok_or("窗口尚未显示".to_string())?;
+            let x11_window = gdk_window
+                .downcast::<gdkx11::X11Window>()
+                .map_err(|_| "窗口不是 X11 窗口".to_string())?;
+            Ok(x11_window.xid() as Window)
+        })
+    }
+
+    /// 向窗口管理器发送 EWMH 客户端消息
+    fn send(
+        conn: &RustConnection,
+        root: Window,
+        window: Window,
+        message_type: Atom,
+        data: [u32; 5],
+    ) -> Result<(), String> {
+        let event = ClientMessageEvent::new(32, window, message_type, data);
+        conn.send_event(
+            false,
+            root,
+            EventMask::SUBSTRUCTURE_REDIRECT | EventMask::SUBSTRUCTURE_NOTIFY,
+            event,
+        )
+        .map_err(|e| format!("发送窗口管理器消息失败: {}", e))?;
+        Ok(())
+    }
+
+    pub fn enter(window: Window) -> Result<Restore, String> {
+        let (conn, screen_num) = crate::capture::x11::connect()?;
+        let root = conn.setup().roots[screen_num].root;
+        let atoms = Atoms::intern(&conn)?;
+
+        let current: Vec<Atom> = conn
+            .get_property(false, window, atoms.wm_state, AtomEnum::ATOM, 0, 1024)
+            .map_err(|e| e.to_string())?
+            .reply()
+            .map_err(|e| format!("读取窗口状态失败: {}", e))?
+            .value32()
+            .map(|atoms| atoms.collect())
+            .unwrap_or_default();
+
+        // 全屏默认只覆盖窗口所在的显示器，通过 _NET_WM_FULLSCREEN_MONITORS 扩展到所有显示器
+        match conn
+            .randr_get_monitors(root, true)
+            .map_err(|e| e.to_string())
+            .and_then(|cookie| cookie.reply().map_err(|e| e.to_string()))
+        {
+            Ok(reply) => {
+                let monitors: Vec<(i32, i32, u32, u32)> = reply
+                    .monitors
+                    .iter()
+                    .map(|m| (m.x.into(), m.y.into(), m.width.into(), m.height.into()))
+                    .collect();
+                if let Some([top, bottom, left, right]) = super::fullscreen_monitors(&monitors) {
+                    send(
+                        &conn,
+                        root,
+                        window,
+                        atoms.fullscreen_monitors,
+                        [top, bottom, left, right, SOURCE_APPLICATION],
+                    )?;
+                }
+            }
+            Err(e) => warn!("获取显示器列表失败，只覆盖当前显示器: {}", e),
+        }
+
+        let added: Vec<Atom> = [atoms.fullscreen, atoms.above]
+            .into_iter()
+            .filter(|atom| !current.contains(atom))
+            .collect();
+        for &atom in &added {
+            send(
+                &conn,
+                root,
+                window,
+                atoms.wm_state,
+                [NET_WM_STATE_ADD, atom, 0, SOURCE_APPLICATION, 0],
+            )?;
+        }
+        conn.flush().map_err(|e| e.to_string())?;
+        info!("已设置 X11 全屏覆盖，窗口: {:#x}", window);
+        Ok(Restore { window, added })
+    }
+
+    pub fn exit(restore: Restore) -> Result<(), String> {
+        let (conn, screen_num) = crate::capture::x11::connect()?;
+        let root = conn.setup().roots[screen_num].root;
+        let atoms = Atoms::intern(&conn)?;
+
+        for &atom in &restore.added {
+            send(
+                &conn,
+                root,
+                restore.window,
+                atoms.wm_state,
+                [NET_WM_STATE_REMOVE, atom, 0, SOURCE_APPLICATION, 0],
+            )?;
+        }
+        // 之后普通的全屏只覆盖一个显示器
+        conn.delete_property(restore.window, atoms.fullscreen_monitors)
+            .map_err(|e| e.to_string())?;
+        conn.flush().map_err(|e| e.to_string())?;
+        info!("已恢复 X11 窗口状态，窗口: {:#x}", restore.window);
+        Ok(())
+    }
+}
+
+/// Wayland 下通过 layer-shell 把窗口放到覆盖层，位于面板和 Dock 之上
+///
+/// 覆盖层由合成器放在当前聚焦的显示器上。layer-shell 只能在窗口显示前初始化，
+/// 并且初始化后无法变回普通窗口，退出时关闭这个窗口，再按配置重新创建一个普通窗口。
+#[cfg(all(unix, not(target_os = "macos")))]
+mod layer_shell {
+    use super::on_main_thread;
+    use gtk::prelude::*;
+    use gtk_layer_shell::{Edge, KeyboardMode, Layer, LayerShell};
+    use log::{error, info};
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use tauri::{Manager, WebviewWindow, WebviewWindowBuilder, WindowEvent};
+
+    const EDGES: [Edge; 4] = [Edge::Top, Edge::Bottom, Edge::Left, Edge::Right];
+
+    /// 正在重新创建窗口，旧窗口关闭后、新窗口创建前应用没有任何窗口
+    pub static RECREATING: AtomicBool = AtomicBool::new(false);
+
+    /// 合成器不支持 layer-shell 时返回 `Ok(false)`
+    pub fn enter(window: &WebviewWindow) -> Result<bool, String> {
+        on_main_thread(window, |window| {
+            if !gtk_layer_shell::is_supported() {
+                return Ok(false);
+            }
+            let gtk_window = window
+                .gtk_window()
+                .map_err(|e| format!("获取 GTK 窗口失败: {}", e))?;
+            if !gtk_window.is_layer_window() {
+                gtk_window.hide();
+                gtk_window.unrealize();
+                gtk_window.init_layer_shell();
+                gtk_window.set_namespace("screenshot-overlay");
+            }
+            gtk_window.set_layer(Layer::Overlay);
+            for edge in EDGES {
+                gtk_window.set_anchor(edge, true);
+            }
+            // -1 表示不被其他层的独占区域推开，盖住面板
+            gtk_window.set_exclusive_zone(-1);
+            gtk_window.set_keyboard_mode(KeyboardMode::Exclusive);
+            gtk_window.show();
+            info!("已设置 layer-shell 覆盖层");
+            Ok(true)
+        })
+    }
+
+    pub fn exit(window: &WebviewWindow) -> Result<(), String> {
+        // 先隐藏覆盖层，重新创建窗口期间不再挡住桌面
+        on_main_thread(window, |window| {
+            window
+                .gtk_window()
+                .map_err(|e| format!("获取 GTK 窗口失败: {}", e))?
+                .hide();
+            Ok(())
+        })?;
+        recreate(window)
+    }
+
+    /// 关闭窗口，在它销毁后按配置创建同名的新窗口
+    fn recreate(window: &WebviewWindow) -> Result<(), String> {
+        let app = window.app_handle().clone();
+        let config = app
+            .config()
+            .app
+            .windows
+            .iter()
+            .find(|config| config.label == window.label())
+            .cloned()
+            .ok_or_else(|| format!("找不到窗口 {} 的配置", window.label()))?;
+
+        RECREATING.store(true, Ordering::SeqCst);
+        window.on_window_event(move |event| {
+            if !matches!(event, WindowEvent::Destroyed) {
+                return;
+            }
+            // 等旧窗口从应用中移除后再创建，避免标签冲突
+            let handle = app.clone();
+            let config = config.clone();
+            let scheduled = app.run_on_main_thread(move || {
+                match WebviewWindowBuilder::from_config(&handle, &config)
+                    .and_then(|builder| builder.build())
+                {
+                    Ok(_) => info!("已重新创建窗口 {}", config.label),
+                    Err(e) => error!("重新创建窗口失败: {}", e),
+                }
+                RECREATING.store(false, Ordering::SeqCst);
+            });
+            if let Err(e) = scheduled {
+                error!("无法在主线程上重新创建窗口: {}", e);
+                RECREATING.store(false, Ordering::SeqCst);
+            }
+        });
+        window.destroy().map_err(|e| {
+            RECREATING.store(false, Ordering::SeqCst);
+            format!("关闭 layer-shell 窗口失败: {}", e)
+        })?;
+        info!("已关闭 layer-shell 窗口");
+        Ok(())
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod macos {
+    use cocoa::appkit::{NSApplication, NSApplicationPresentationOptions};
+    use cocoa::base::{id, nil};
+    use log::{error, info};
+    use objc::{msg_send, sel, sel_impl};
+
+    /// 在主线程上设置 macOS 的全屏演示模式
+    fn set_presentation_mode_on_main_thread(
+        app: id,
+        options: NSApplicationPresentationOptions,
+    ) -> i32 {
+        info!("在主线程上设置 presentation options");
+        unsafe {
+            let result: i32 = msg_send![app, setPresentationOptions: options];
+            info!("主线程 setPresentationOptions_ 返回值: {:?}", result);
+            result
+        }
+    }
+
+    /// 设置 macOS 的全屏演示模式，自动隐藏菜单栏和 Dock
+    pub fn set_presentation_mode(fullscreen: bool) {
+        unsafe {
+            let app = NSApplication::sharedApplication(nil);
+            info!("成功获取 NSApplication 共享实例");
+
+            // 激活应用程序，确保它处于活动状态
+            info!("激活应用程序");
+            app.activateIgnoringOtherApps_(cocoa::base::YES);
+
+            if fullscreen {
+                info!("正在设置全屏演示模式（隐藏 Dock 和菜单栏）");
+
+                let options = NSApplicationPresentationOptions::NSApplicationPresentationHideDock
+                    | NSApplicationPresentationOptions::NSApplicationPresentationHideMenuBar
+                    | NSApplicationPresentationOptions::NSApplicationPresentationDisableHideApplication
+                    | NSApplicationPresentationOptions::NSApplicationPresentationDisableProcessSwitching;
+
+                info!("方法1: 使用 setPresentationOptions_（在主线程上）");
+                info!("Presentation Options 值: {:?}", options);
+
+                // 在主线程上执行
+                let result = set_presentation_mode_on_main_thread(app, options);
+                info!("setPresentationOptions_ 返回值: {:?}", result);
+
+                // 检查返回值是否为错误码（负数表示错误）
+                if result < 0 {
+                    error!(
+                        "setPresentationOptions_ 返回错误码: {} (OSStatus错误)",
+                        result
+                    );
+                    error!("可能的原因：应用程序状态不正确或某些选项不被支持");
+
+                    // 方法2：尝试简化选项，只使用基本的隐藏选项
+                    info!("方法2: 尝试使用简化的 presentation options...");
+                    let simple_options =
+                        NSApplicationPresentationOptions::NSApplicationPresentationHideDock
+                            | NSApplicationPresentationOptions::NSApplicationPresentationHideMenuBar;
+                    info!("简化后的 Options 值: {:?}", simple_options);
+
+                    let simple_result = set_presentation_mode_on_main_thread(app, simple_options);
+                    info!("简化选项的返回值: {:?}", simple_result);
+
+                    if simple_result < 0 {
+                        error!("简化选项也失败，返回错误码: {}", simple_result);
+
+                        // 方法3：尝试只隐藏 Dock
+                        info!("方法3: 尝试只隐藏 Dock...");
+                        let dock_only =
+                            NSApplicationPresentationOptions::NSApplicationPresentationHideDock;
+
+                        let dock_result = set_presentation_mode_on_main_thread(app, dock_only);
+                        info!("只隐藏 Dock 的返回值: {:?}", dock_result);
+
+                        if dock_result < 0 {
+                            error!("所有方法都失败了");
+
+                            // 方法4：尝试直接设置 presentationOptions 属性（不使用方法）
+                            info!("方法4: 尝试直接设置 presentationOptions 属性...");
+                            let _: () = msg_send![app, setPresentationOptions: options];
+                            info!("已尝试直接设置 presentationOptions 属性");
+                        }
+                    }
+                }
+
+                info!("全屏演示模式设置成功");
+            } else {
+                info!("正在恢复默认演示模式");
+                let options = NSApplicationPresentationOptions::NSApplicationPresentationDefault;
+
+                info!("Presentation Options 值: {:?}", options);
+
+                let result = set_presentation_mode_on_main_thread(app, options);
+                info!("setPresentationOptions_ 返回值: {:?}", result);
+
+                // 检查返回值是否为错误码
+                if result < 0 {
+                    error!("恢复默认演示模式失败，错误码: {}", result);
+                    error!("警告: 可能无法完全恢复演示模式，但程序会继续运行");
+
+                    // 尝试直接设置属性
+                    info!("尝试直接设置 presentationOptions 属性...");
+                    let _: () = msg_send![app, setPresentationOptions: options];
+                    info!("已尝试直接设置 presentationOptions 属性");
+                }
+
+                info!("默认演示模式设置成功");
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::AtomicUsize;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_overlay_guard_restores_once() {
+        let restored = Arc::new(AtomicUsize::new(0));
+        let guard = |restored: &Arc<AtomicUsize>| {
+            let restored = restored.clone();
+            OverlayGuard::new(1, move || {
+                restored.fetch_add(1, Ordering::SeqCst);
+                Ok(())
+            })
+        };
+
+        // 丢弃时恢复，例如窗口关闭或 panic 展开
+        drop(guard(&restored));
+        assert_eq!(restored.load(Ordering::SeqCst), 1);
+
+        // 主动释放后不会在丢弃时再恢复一次
+        guard(&restored).release().unwrap();
+        assert_eq!(restored.load(Ordering::SeqCst), 2);
+
+        let failing = OverlayGuard::new(2, || Err("恢复失败".to_string()));
+        assert_eq!(failing.release(), Err("恢复失败".to_string()));
+    }
+
+    #[test]
+    fn test_fullscreen_monitors_spans_layout() {
+        // 左边竖放的副屏比主屏高，右边还有一块较低的屏幕
+        let monitors = [
+            (1080, 400, 2560, 1440),
+            (0, 0, 1080, 1920),
+            (3640, 700, 1920, 1080),
+        ];
+        assert_eq!(fullscreen_monitors(&monitors), Some([1, 1, 1, 2]));
+        assert_eq!(
+            fullscreen_monitors(&[(0, 0, 1920, 1080)]),
+            Some([0, 0, 0, 0])
+        );
+        assert_eq!(fullscreen_monitors(&[]), None);
+    }
+}
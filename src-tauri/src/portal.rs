@@ -0,0 +1,826 @@
+use crate::settings::{self, SettingsState};
+use async_io::Timer;
+use futures_lite::{future, StreamExt};
+use image::RgbaImage;
+use log::{error, info, warn};
+use std::collections::HashMap;
+use std::os::fd::OwnedFd;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, OnceLock};
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+use zbus::blocking::{Connection, Proxy};
+use zbus::zvariant::{ObjectPath, OwnedObjectPath, OwnedValue, Value};
+
+/// xdg-desktop-portal 在会话总线上的名称
+pub const PORTAL_DESTINATION: &str = "org.freedesktop.portal.Desktop";
+const PORTAL_PATH: &str = "/org/freedesktop/portal/desktop";
+const REQUEST_INTERFACE: &str = "org.freedesktop.portal.Request";
+const SESSION_INTERFACE: &str = "org.freedesktop.portal.Session";
+const SCREENSHOT_INTERFACE: &str = "org.freedesktop.portal.Screenshot";
+const SCREENCAST_INTERFACE: &str = "org.freedesktop.portal.ScreenCast";
+
+/// 等待门户响应的最长时间，包括用户在授权对话框中选择的时间
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// ScreenCast 的来源类型：显示器
+const SOURCE_TYPE_MONITOR: u32 = 1;
+/// ScreenCast 的授权保存方式：在用户撤销前一直有效
+const PERSIST_MODE_PERSISTENT: u32 = 2;
+
+/// 门户请求 Response 信号中的结果
+type Results = HashMap<String, OwnedValue>;
+
+/// 方法调用的选项参数（`a{sv}`）
+type Options<'a> = HashMap<&'static str, Value<'a>>;
+
+/// 开始一次 ScreenCast 后得到的 PipeWire 流
+pub struct ScreenCast {
+    session: OwnedObjectPath,
+    /// PipeWire 远端的文件描述符，只能访问授权的流
+    pub remote: OwnedFd,
+    pub node_id: u32,
+    /// 共享的显示器在桌面中的位置，门户不一定提供
+    pub position: Option<(i32, i32)>,
+    /// 下次跳过授权对话框使用的令牌，每次开始后都会换新
+    pub restore_token: Option<String>,
+}
+
+/// xdg-desktop-portal 客户端
+pub struct Portal {
+    conn: Connection,
+    destination: String,
+}
+
+impl Portal {
+    /// 连接会话总线上的桌面门户
+    pub fn session() -> Result<Self, String> {
+        let conn = Connection::session().map_err(|e| format!("无法连接 D-Bus 会话总线: {}", e))?;
+        Ok(Self::new(conn, PORTAL_DESTINATION))
+    }
+
+    /// 使用指定的连接和服务名称，测试时可以指向模拟的门户
+    pub fn new(conn: Connection, destination: impl Into<String>) -> Self {
+        Self {
+            conn,
+            destination: destination.into(),
+        }
+    }
+
+    /// 请求对象的路径由调用方的唯一名称和 handle_token 决定，调用前就能订阅 Response 信号
+    fn request_path(&self, token: &str) -> Result<OwnedObjectPath, String> {
+        let sender = self
+            .conn
+            .unique_name()
+            .ok_or("D-Bus 连接没有唯一名称".to_string())?;
+        let sender = sender.as_str().trim_start_matches(':').replace('.', "_");
+        OwnedObjectPath::try_from(format!("{}/request/{}/{}", PORTAL_PATH, sender, token))
+            .map_err(|e| format!("无效的请求路径: {}", e))
+    }
+
+    /// 调用返回 Request 对象的门户方法，等待 Response 信号并返回结果
+    fn request<B>(
+        &self,
+        interface: &str,
+        method: &str,
+        token: &str,
+        body: &B,
+    ) -> Result<Results, String>
+    where
+        B: serde::Serialize + zbus::zvariant::DynamicType,
+    {
+        let path = self.request_path(token)?;
+        let proxy = Proxy::new(
+            &self.conn,
+            self.destination.as_str(),
+            path.as_str(),
+            REQUEST_INTERFACE,
+        )
+        .map_err(|e| format!("创建门户请求代理失败: {}", e))?;
+        let mut responses = zbus::block_on(proxy.inner().receive_signal("Response"))
+            .map_err(|e| format!("订阅门户响应失败: {}", e))?;
+
+        self.conn
+            .call_method(
+                Some(self.destination.as_str()),
+                PORTAL_PATH,
+                Some(interface),
+                method,
+                body,
+            )
+            .map_err(|e| format!("调用 {}.{} 失败: {}", interface, method, e))?;
+
+        // 用户不理会对话框或门户卡住时不能一直等待
+        let response = zbus::block_on(future::or(async { Ok(responses.next().await) }, async {
+            Timer::after(REQUEST_TIMEOUT).await;
+            Err(())
+        }));
+        let message = match response {
+            Ok(message) => message.ok_or("门户没有返回结果".to_string())?,
+            Err(()) => {
+                // 关闭请求，门户会同时关闭还在显示的对话框
+                let _ = proxy.call_method("Close", &());
+                return Err(format!(
+                    "等待门户 {}.{} 响应超时 ({:?})",
+                    interface, method, REQUEST_TIMEOUT
+                ));
+            }
+        };
+        let (response, results): (u32, Results) = message
+            .body()
+            .deserialize()
+            .map_err(|e| format!("解析门户响应失败: {}", e))?;
+        match response {
+            0 => Ok(results),
+            1 => Err("用户取消了屏幕共享授权".to_string()),
+            code => Err(format!("门户请求 {}.{} 失败 ({})", interface, method, code)),
+        }
+    }
+
+    /// 通过 Screenshot 门户截取整个桌面，不显示交互对话框
+    pub fn screenshot(&self) -> Result<RgbaImage, String> {
+        let token = next_token();
+        let options = Options::from([
+            ("handle_token", Value::from(token.as_str())),
+            ("interactive", Value::from(false)),
+        ]);
+        let results = self.request(SCREENSHOT_INTERFACE, "Screenshot", &token, &("", options))?;
+
+        let uri = string_result(&results, "uri").ok_or("门户没有返回截图文件".to_string())?;
+        let path = url::Url::parse(uri)
+            .ok()
+            .and_then(|url| url.to_file_path().ok())
+            .ok_or_else(|| format!("无效的截图文件地址: {}", uri))?;
+        let image = image::open(&path)
+            .map_err(|e| format!("读取门户截图失败 {:?}: {}", path, e))
+            .map(|image| image.into_rgba8());
+        // 门户把截图写入图片目录，读取后删除，避免每次截图都留下文件
+        if let Err(e) = std::fs::remove_file(&path) {
+            warn!("删除门户截图文件失败 {:?}: {}", path, e);
+        }
+        let image = image?;
+        info!(
+            "门户截图完成: {:?}, {}x{}",
+            path,
+            image.width(),
+            image.height()
+        );
+        Ok(image)
+    }
+
+    /// 创建 ScreenCast 会话并开始共享一个显示器
+    ///
+    /// `restore_token` 有效时门户直接恢复之前的授权，不再弹出选择对话框。
+    pub fn start_screencast(&self, restore_token: Option<&str>) -> Result<ScreenCast, String> {
+        let token = next_token();
+        let session_token = next_token();
+        let options = Options::from([
+            ("handle_token", Value::from(token.as_str())),
+            ("session_handle_token", Value::from(session_token.as_str())),
+        ]);
+        let results = self.request(SCREENCAST_INTERFACE, "CreateSession", &token, &(options,))?;
+        let session = string_result(&results, "session_handle")
+            .and_then(|handle| OwnedObjectPath::try_from(handle).ok())
+            .ok_or("门户没有返回 ScreenCast 会话".to_string())?;
+
+        let result = self.select_and_start(&session, restore_token);
+        if result.is_err() {
+            self.close_session(&session);
+        }
+        result
+    }
+
+    fn select_and_start(
+        &self,
+        session: &OwnedObjectPath,
+        restore_token: Option<&str>,
+    ) -> Result<ScreenCast, String> {
+        let token = next_token();
+        let mut options = Options::from([
+            ("handle_token", Value::from(token.as_str())),
+            ("types", Value::from(SOURCE_TYPE_MONITOR)),
+            ("multiple", Value::from(false)),
+            ("persist_mode", Value::from(PERSIST_MODE_PERSISTENT)),
+        ]);
+        if let Some(restore_token) = restore_token {
+            options.insert("restore_token", Value::from(restore_token));
+        }
+        self.request(
+            SCREENCAST_INTERFACE,
+            "SelectSources",
+            &token,
+            &(&**session, options),
+        )?;
+
+        let token = next_token();
+        let options = Options::from([("handle_token", Value::from(token.as_str()))]);
+        let results = self.request(
+            SCREENCAST_INTERFACE,
+            "Start",
+            &token,
+            &(&**session, "", options),
+        )?;
+
+        let (node_id, properties) = results
+            .get("streams")
+            .and_then(|streams| streams.try_clone().ok())
+            .and_then(|streams| Vec::<(u32, Results)>::try_from(streams).ok())
+            .and_then(|streams| streams.into_iter().next())
+            .ok_or("门户没有返回 PipeWire 流".to_string())?;
+        let position = properties
+            .get("position")
+            .and_then(|position| position.try_clone().ok())
+            .and_then(|position| <(i32, i32)>::try_from(position).ok());
+        let restore_token = string_result(&results, "restore_token").map(str::to_string);
+
+        let reply = self
+            .conn
+            .call_method(
+                Some(self.destination.as_str()),
+                PORTAL_PATH,
+                Some(SCREENCAST_INTERFACE),
+                "OpenPipeWireRemote",
+                &(&**session, Options::new()),
+            )
+            .map_err(|e| format!("打开 PipeWire 远端失败: {}", e))?;
+        let remote: zbus::zvariant::OwnedFd = reply
+            .body()
+            .deserialize()
+            .map_err(|e| format!("解析 PipeWire 远端失败: {}", e))?;
+
+        Ok(ScreenCast {
+            session: session.clone(),
+            remote: remote.into(),
+            node_id,
+            position,
+            restore_token,
+        })
+    }
+
+    fn close_session(&self, session: &ObjectPath<'_>) {
+        if let Err(e) = self.conn.call_method(
+            Some(self.destination.as_str()),
+            session.clone(),
+            Some(SESSION_INTERFACE),
+            "Close",
+            &(),
+        ) {
+            warn!("关闭 ScreenCast 会话失败: {}", e);
+        }
+    }
+}
+
+fn next_token() -> String {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    format!(
+        "screenshot_{}_{}",
+        std::process::id(),
+        COUNTER.fetch_add(1, Ordering::Relaxed)
+    )
+}
+
+fn string_result<'a>(results: &'a Results, key: &str) -> Option<&'a str> {
+    results
+        .get(key)
+        .and_then(|value| value.downcast_ref::<&str>().ok())
+}
+
+/// 上次 ScreenCast 授权返回的恢复令牌
+static RESTORE_TOKEN: Mutex<Option<String>> = Mutex::new(None);
+
+/// 保存新恢复令牌的回调
+type PersistToken = Box<dyn Fn(&str) + Send + Sync>;
+static PERSIST_TOKEN: OnceLock<PersistToken> = OnceLock::new();
+
+/// 设置已保存的恢复令牌，以及令牌更新时保存它的方法
+pub fn configure(restore_token: Option<String>, persist: impl Fn(&str) + Send + Sync + 'static) {
+    *RESTORE_TOKEN.lock().unwrap() = restore_token;
+    let _ = PERSIST_TOKEN.set(Box::new(persist));
+}
+
+/// 使用设置中保存的恢复令牌，新的令牌写回设置
+pub fn init(app: &AppHandle) {
+    let restore_token = settings::current(app).portal_restore_token;
+    let app = app.clone();
+    configure(restore_token, move |token| {
+        let state = app.state::<SettingsState>();
+        if let Err(e) = state.update(|settings| {
+            settings.portal_restore_token = Some(token.to_string());
+        }) {
+            error!("保存屏幕共享恢复令牌失败: {}", e);
+        }
+    });
+}
+
+/// 通过 Screenshot 门户截取整个桌面
+pub fn screenshot() -> Result<RgbaImage, String> {
+    Portal::session()?.screenshot()
+}
+
+/// 从 ScreenCast 读取的一帧画面
+pub struct Frame {
+    pub image: RgbaImage,
+    /// 共享的显示器在桌面中的位置
+    pub position: Option<(i32, i32)>,
+}
+
+/// 在区域截图之间保持打开的 ScreenCast 会话，避免每次截图都重新创建会话
+struct ActiveCast {
+    portal: Portal,
+    cast: ScreenCast,
+}
+
+impl ActiveCast {
+    fn start(portal: Portal) -> Result<Self, String> {
+        let restore_token = RESTORE_TOKEN.lock().unwrap().clone();
+        let cast = portal.start_screencast(restore_token.as_deref())?;
+
+        // 恢复令牌只能使用一次，每次都保存门户返回的新令牌
+        if let Some(token) = &cast.restore_token {
+            if restore_token.as_ref() != Some(token) {
+                *RESTORE_TOKEN.lock().unwrap() = Some(token.clone());
+                if let Some(persist) = PERSIST_TOKEN.get() {
+                    persist(token);
+                }
+            }
+        }
+        info!("已开始 ScreenCast 会话");
+        Ok(Self { portal, cast })
+    }
+
+    fn read(&self) -> Result<Frame, String> {
+        // 每次读取使用新的 PipeWire 连接，会话和授权保持不变
+        let remote = self
+            .cast
+            .remote
+            .try_clone()
+            .map_err(|e| format!("复制 PipeWire 远端失败: {}", e))?;
+        let image = stream::read_frame(remote, self.cast.node_id)?;
+        Ok(Frame {
+            image,
+            position: self.cast.position,
+        })
+    }
+
+    fn close(self) {
+        self.portal.close_session(&self.cast.session);
+    }
+}
+
+static SCREENCAST: Mutex<Option<ActiveCast>> = Mutex::new(None);
+
+/// 通过 ScreenCast 门户从 PipeWire 读取一帧完整画面
+///
+/// 第一次截图时创建会话，之后复用；会话失效（例如用户撤销了共享）时重新创建一次。
+pub fn capture_frame() -> Result<Frame, String> {
+    let mut active = SCREENCAST.lock().unwrap();
+    if let Some(cast) = active.as_ref() {
+        match cast.read() {
+            Ok(frame) => return Ok(frame),
+            Err(e) => {
+                warn!("ScreenCast 会话已失效，重新创建: {}", e);
+                if let Some(cast) = active.take() {
+                    cast.close();
+                }
+            }
+        }
+    }
+
+    let cast = ActiveCast::start(Portal::session()?)?;
+    match cast.read() {
+        Ok(frame) => {
+            info!(
+                "ScreenCast 截图完成: {}x{}",
+                frame.image.width(),
+                frame.image.height()
+            );
+            *active = Some(cast);
+            Ok(frame)
+        }
+        Err(e) => {
+            error!("读取 PipeWire 画面失败: {}", e);
+            cast.close();
+            Err(e)
+        }
+    }
+}
+
+/// 将 32 位的 BGRx/RGBx 行数据转换为不透明的 RGBA，`stride` 是每行的字节数
+fn frame_to_rgba(
+    data: &[u8],
+    stride: usize,
+    width: u32,
+    height: u32,
+    bgr: bool,
+) -> Result<RgbaImage, String> {
+    let row_len = width as usize * 4;
+    if width == 0
+        || height == 0
+        || stride < row_len
+        || data.len() < stride * (height as usize - 1) + row_len
+    {
+        return Err("PipeWire 画面数据大小不正确".to_string());
+    }
+
+    let mut rgba = Vec::with_capacity(row_len * height as usize);
+    for row in data.chunks(stride).take(height as usize) {
+        for pixel in row[..row_len].chunks_exact(4) {
+            let (r, b) = if bgr {
+                (pixel[2], pixel[0])
+            } else {
+                (pixel[0], pixel[2])
+            };
+            rgba.extend_from_slice(&[r, pixel[1], b, 255]);
+        }
+    }
+    RgbaImage::from_raw(width, height, rgba).ok_or("PipeWire 画面数据大小不正确".to_string())
+}
+
+/// 从 PipeWire 流中读取单帧画面
+mod stream {
+    use super::frame_to_rgba;
+    use image::RgbaImage;
+    use pipewire as pw;
+    use pw::spa;
+    use pw::spa::param::format::{FormatProperties, MediaSubtype, MediaType};
+    use pw::spa::param::format_utils;
+    use pw::spa::param::video::{VideoFormat, VideoInfoRaw};
+    use pw::spa::param::ParamType;
+    use pw::spa::pod::Pod;
+    use pw::spa::utils::{Direction, Fraction, Rectangle, SpaTypes};
+    use pw::stream::{Stream, StreamFlags, StreamState};
+    use std::cell::RefCell;
+    use std::os::fd::OwnedFd;
+    use std::rc::Rc;
+    use std::time::Duration;
+
+    /// 等待第一帧的最长时间
+    const FRAME_TIMEOUT: Duration = Duration::from_secs(5);
+
+    type FrameResult = Rc<RefCell<Option<Result<RgbaImage, String>>>>;
+
+    pub fn read_frame(remote: OwnedFd, node_id: u32) -> Result<RgbaImage, String> {
+        pw::init();
+        let mainloop = pw::main_loop::MainLoop::new(None)
+            .map_err(|e| format!("创建 PipeWire 主循环失败: {}", e))?;
+        let context = pw::context::Context::new(&mainloop)
+            .map_err(|e| format!("创建 PipeWire 上下文失败: {}", e))?;
+        let core = context
+            .connect_fd(remote, None)
+            .map_err(|e| format!("连接 PipeWire 失败: {}", e))?;
+        let stream = Stream::new(
+            &core,
+            "screenshot",
+            pw::properties::properties! {
+                *pw::keys::MEDIA_TYPE => "Video",
+                *pw::keys::MEDIA_CATEGORY => "Capture",
+                *pw::keys::MEDIA_ROLE => "Screen",
+            },
+        )
+        .map_err(|e| format!("创建 PipeWire 流失败: {}", e))?;
+
+        let frame: FrameResult = Rc::new(RefCell::new(None));
+        let _listener = stream
+            .add_local_listener_with_user_data(VideoInfoRaw::default())
+            .state_changed({
+                let frame = frame.clone();
+                let mainloop = mainloop.clone();
+                move |_, _, _, state| {
+                    if let StreamState::Error(message) = state {
+                        frame
+                            .borrow_mut()
+                            .get_or_insert(Err(format!("PipeWire 流出错: {}", message)));
+                        mainloop.quit();
+                    }
+                }
+            })
+            .param_changed(|_, format, id, param| {
+                let Some(param) = param else {
+                    return;
+                };
+                if id != ParamType::Format.as_raw() {
+                    return;
+                }
+                let Ok((media_type, media_subtype)) = format_utils::parse_format(param) else {
+                    return;
+                };
+                if media_type != MediaType::Video || media_subtype != MediaSubtype::Raw {
+                    return;
+                }
+                if let Err(e) = format.parse(param) {
+                    log::warn!("解析 PipeWire 视频格式失败: {}", e);
+                }
+            })
+            .process({
+                let frame = frame.clone();
+                let mainloop = mainloop.clone();
+                move |stream, format| {
+                    if frame.borrow().is_some() {
+                        return;
+                    }
+                    let Some(mut buffer) = stream.dequeue_buffer() else {
+                        return;
+                    };
+                    let Some(data) = buffer.datas_mut().first_mut() else {
+                        return;
+                    };
+                    let offset = data.chunk().offset() as usize;
+                    let stride = data.chunk().stride().max(0) as usize;
+                    let size = format.size();
+                    let bgr = format.format() == VideoFormat::BGRx
+                        || format.format() == VideoFormat::BGRA;
+                    if let Some(bytes) = data.data().and_then(|bytes| bytes.get(offset..)) {
+                        *frame.borrow_mut() =
+                            Some(frame_to_rgba(bytes, stride, size.width, size.height, bgr));
+                        mainloop.quit();
+                    }
+                }
+            })
+            .register()
+            .map_err(|e| format!("注册 PipeWire 回调失败: {}", e))?;
+
+        // 只接受每像素 4 字节的格式，转换时不需要处理其他布局
+        let format = spa::pod::object!(
+            SpaTypes::ObjectParamFormat,
+            ParamType::EnumFormat,
+            spa::pod::property!(FormatProperties::MediaType, Id, MediaType::Video),
+            spa::pod::property!(FormatProperties::MediaSubtype, Id, MediaSubtype::Raw),
+            spa::pod::property!(
+                FormatProperties::VideoFormat,
+                Choice,
+                Enum,
+                Id,
+                VideoFormat::BGRx,
+                VideoFormat::BGRx,
+                VideoFormat::RGBx,
+                VideoFormat::BGRA,
+                VideoFormat::RGBA
+            ),
+            spa::pod::property!(
+                FormatProperties::VideoSize,
+                Choice,
+                Range,
+                Rectangle,
+                Rectangle {
+                    width: 1920,
+                    height: 1080
+                },
+                Rectangle {
+                    width: 1,
+                    height: 1
+                },
+                Rectangle {
+                    width: 16384,
+                    height: 16384
+                }
+            ),
+            spa::pod::property!(
+                FormatProperties::VideoFramerate,
+                Choice,
+                Range,
+                Fraction,
+                Fraction { num: 30, denom: 1 },
+                Fraction { num: 0, denom: 1 },
+                Fraction {
+                    num: 1000,
+                    denom: 1
+                }
+            ),
+        );
+        let values: Vec<u8> = spa::pod::serialize::PodSerializer::serialize(
+            std::io::Cursor::new(Vec::new()),
+            &spa::pod::Value::Object(format),
+        )
+        .map_err(|e| format!("构造 PipeWire 格式参数失败: {:?}", e))?
+        .0
+        .into_inner();
+        let mut params =
+            [Pod::from_bytes(&values).ok_or("构造 PipeWire 格式参数失败".to_string())?];
+
+        stream
+            .connect(
+                Direction::Input,
+                Some(node_id),
+                StreamFlags::AUTOCONNECT | StreamFlags::MAP_BUFFERS,
+                &mut params,
+            )
+            .map_err(|e| format!("连接 PipeWire 流失败: {}", e))?;
+
+        let timer = mainloop.loop_().add_timer({
+            let mainloop = mainloop.clone();
+            move |_| mainloop.quit()
+        });
+        timer
+            .update_timer(Some(FRAME_TIMEOUT), None)
+            .into_result()
+            .map_err(|e| format!("设置 PipeWire 超时失败: {}", e))?;
+
+        mainloop.run();
+        let _ = stream.disconnect();
+        let result = frame.borrow_mut().take();
+        result.unwrap_or_else(|| Err("等待 PipeWire 画面超时".to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_frame_to_rgba_skips_row_padding() {
+        // 2x2 的 BGRx 画面，每行末尾有 4 字节填充
+        let data = [
+            1, 2, 3, 0, 4, 5, 6, 0, 9, 9, 9, 9, //
+            7, 8, 9, 0, 10, 11, 12, 0,
+        ];
+        let image = frame_to_rgba(&data, 12, 2, 2, true).unwrap();
+        assert_eq!(
+            image.into_raw(),
+            vec![3, 2, 1, 255, 6, 5, 4, 255, 9, 8, 7, 255, 12, 11, 10, 255]
+        );
+        assert!(frame_to_rgba(&data, 4, 2, 2, true).is_err());
+    }
+
+    /// 按门户的规则计算请求路径，并立即发出带有结果的 Response 信号
+    async fn respond(
+        header: &zbus::message::Header<'_>,
+        conn: &zbus::Connection,
+        options: &Results,
+        results: HashMap<&str, Value<'_>>,
+    ) -> zbus::fdo::Result<OwnedObjectPath> {
+        let token = string_result(options, "handle_token").unwrap_or_default();
+        let sender = header
+            .sender()
+            .map(|sender| sender.as_str().trim_start_matches(':').replace('.', "_"))
+            .unwrap_or_default();
+        let path =
+            OwnedObjectPath::try_from(format!("{}/request/{}/{}", PORTAL_PATH, sender, token))
+                .map_err(|e| zbus::fdo::Error::InvalidArgs(e.to_string()))?;
+        conn.emit_signal(
+            None::<&str>,
+            path.as_str(),
+            REQUEST_INTERFACE,
+            "Response",
+            &(0u32, results),
+        )
+        .await?;
+        Ok(path)
+    }
+
+    /// 模拟 Screenshot 门户：立即返回预先准备好的截图文件
+    struct MockScreenshot {
+        uri: String,
+    }
+
+    #[zbus::interface(name = "org.freedesktop.portal.Screenshot")]
+    impl MockScreenshot {
+        async fn screenshot(
+            &self,
+            #[zbus(header)] header: zbus::message::Header<'_>,
+            #[zbus(connection)] conn: &zbus::Connection,
+            _parent_window: &str,
+            options: Results,
+        ) -> zbus::fdo::Result<OwnedObjectPath> {
+            let results = HashMap::from([("uri", Value::from(self.uri.as_str()))]);
+            respond(&header, conn, &options, results).await
+        }
+    }
+
+    /// 模拟 ScreenCast 门户，记录 SelectSources 收到的恢复令牌和保存方式
+    struct MockScreenCast {
+        selected: Arc<Mutex<Option<(Option<String>, u32)>>>,
+    }
+
+    #[zbus::interface(name = "org.freedesktop.portal.ScreenCast")]
+    impl MockScreenCast {
+        async fn create_session(
+            &self,
+            #[zbus(header)] header: zbus::message::Header<'_>,
+            #[zbus(connection)] conn: &zbus::Connection,
+            options: Results,
+        ) -> zbus::fdo::Result<OwnedObjectPath> {
+            let session = format!("{}/session/mock/1", PORTAL_PATH);
+            let results = HashMap::from([("session_handle", Value::from(session.as_str()))]);
+            respond(&header, conn, &options, results).await
+        }
+
+        async fn select_sources(
+            &self,
+            #[zbus(header)] header: zbus::message::Header<'_>,
+            #[zbus(connection)] conn: &zbus::Connection,
+            _session: OwnedObjectPath,
+            options: Results,
+        ) -> zbus::fdo::Result<OwnedObjectPath> {
+            let persist_mode = options
+                .get("persist_mode")
+                .and_then(|mode| mode.downcast_ref::<u32>().ok())
+                .unwrap_or_default();
+            let restore_token = string_result(&options, "restore_token").map(str::to_string);
+            *self.selected.lock().unwrap() = Some((restore_token, persist_mode));
+            respond(&header, conn, &options, HashMap::new()).await
+        }
+
+        async fn start(
+            &self,
+            #[zbus(header)] header: zbus::message::Header<'_>,
+            #[zbus(connection)] conn: &zbus::Connection,
+            _session: OwnedObjectPath,
+            _parent_window: &str,
+            options: Results,
+        ) -> zbus::fdo::Result<OwnedObjectPath> {
+            let stream = HashMap::from([("position", Value::from((1920i32, 0i32)))]);
+            let results = HashMap::from([
+                ("streams", Value::from(vec![(42u32, stream)])),
+                ("restore_token", Value::from("new-token")),
+            ]);
+            respond(&header, conn, &options, results).await
+        }
+
+        async fn open_pipe_wire_remote(
+            &self,
+            _session: OwnedObjectPath,
+            _options: Results,
+        ) -> zbus::fdo::Result<zbus::zvariant::OwnedFd> {
+            let file = std::fs::File::open("/dev/null")
+                .map_err(|e| zbus::fdo::Error::Failed(e.to_string()))?;
+            Ok(OwnedFd::from(file).into())
+        }
+    }
+
+    /// 在会话总线上以唯一的名称提供模拟门户，返回服务端连接和名称
+    fn serve_mock(
+        name: &str,
+        interface: impl zbus::object_server::Interface,
+    ) -> zbus::blocking::Connection {
+        zbus::blocking::connection::Builder::session()
+            .unwrap()
+            .name(name)
+            .unwrap()
+            .serve_at(PORTAL_PATH, interface)
+            .unwrap()
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    #[ignore = "需要 D-Bus 会话总线：dbus-run-session -- cargo test portal -- --ignored"]
+    fn test_screenshot_with_mock_portal() {
+        let client = Connection::session().unwrap();
+        let dir =
+            std::env::temp_dir().join(format!("screenshot-portal-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("portal.png");
+        RgbaImage::from_pixel(4, 2, image::Rgba([1, 2, 3, 255]))
+            .save(&path)
+            .unwrap();
+        let uri = url::Url::from_file_path(&path).unwrap().to_string();
+
+        let name = format!(
+            "org.freedesktop.portal.MockScreenshot{}",
+            std::process::id()
+        );
+        let _server = serve_mock(&name, MockScreenshot { uri });
+
+        let image = Portal::new(client, name).screenshot().unwrap();
+        assert_eq!(image.dimensions(), (4, 2));
+        assert_eq!(image.get_pixel(3, 1).0, [1, 2, 3, 255]);
+        // 读取后删除门户写入的文件
+        assert!(!path.exists());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    #[ignore = "需要 D-Bus 会话总线：dbus-run-session -- cargo test portal -- --ignored"]
+    fn test_screencast_with_mock_portal_persists_restore_token() {
+        let client = Connection::session().unwrap();
+        let name = format!(
+            "org.freedesktop.portal.MockScreenCast{}",
+            std::process::id()
+        );
+        let selected = Arc::new(Mutex::new(None));
+        let _server = serve_mock(
+            &name,
+            MockScreenCast {
+                selected: selected.clone(),
+            },
+        );
+
+        let persisted = Arc::new(Mutex::new(Vec::new()));
+        let sink = persisted.clone();
+        configure(Some("old-token".to_string()), move |token| {
+            sink.lock().unwrap().push(token.to_string())
+        });
+
+        let cast = ActiveCast::start(Portal::new(client, name)).unwrap();
+        assert_eq!(
+            *selected.lock().unwrap(),
+            Some((Some("old-token".to_string()), PERSIST_MODE_PERSISTENT))
+        );
+        assert_eq!(cast.cast.node_id, 42);
+        assert_eq!(cast.cast.position, Some((1920, 0)));
+        assert_eq!(*persisted.lock().unwrap(), vec!["new-token"]);
+        assert_eq!(RESTORE_TOKEN.lock().unwrap().as_deref(), Some("new-token"));
+        cast.close();
+    }
+}
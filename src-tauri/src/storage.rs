@@ -0,0 +1,261 @@
+use crate::filename;
+use image::ImageFormat;
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::io::{self, Cursor, Write};
+use std::path::{Component, Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// 允许保存的图像格式
+const SUPPORTED_FORMATS: [ImageFormat; 3] =
+    [ImageFormat::Png, ImageFormat::Jpeg, ImageFormat::WebP];
+
+/// 根据文件内容识别图像格式，并确认图像头部可以正常解析
+///
+/// 只信任内容本身，不信任调用方提供的扩展名。
+pub fn sniff_image_format(data: &[u8]) -> Result<ImageFormat, String> {
+    let format = image::guess_format(data).map_err(|_| "文件内容不是可识别的图像".to_string())?;
+    if !SUPPORTED_FORMATS.contains(&format) {
+        return Err(format!("不支持的图像格式: {:?}", format));
+    }
+
+    image::ImageReader::with_format(Cursor::new(data), format)
+        .into_dimensions()
+        .map_err(|e| format!("图像数据已损坏: {}", e))?;
+    Ok(format)
+}
+
+/// 计算内容的 SHA-256，用作历史记录和缩略图缓存的内容标识
+pub fn content_hash(data: &[u8]) -> String {
+    Sha256::digest(data)
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+/// 返回图像格式对应的首选扩展名
+pub fn extension_for(format: ImageFormat) -> &'static str {
+    format.extensions_str().first().copied().unwrap_or("img")
+}
+
+/// 保证文件名的扩展名与实际图像格式一致，不一致时追加正确的扩展名
+fn normalize_extension(name: &str, format: ImageFormat) -> String {
+    let matches = Path::new(name)
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .and_then(ImageFormat::from_extension)
+        .is_some_and(|existing| existing == format);
+    if matches {
+        name.to_string()
+    } else {
+        format!("{}.{}", name, extension_for(format))
+    }
+}
+
+fn canonicalize_dir(dir: &Path) -> io::Result<PathBuf> {
+    fs::create_dir_all(dir)?;
+    dir.canonicalize()
+}
+
+/// 解析前端传入的文件名，返回规范化后的目标目录和文件名
+///
+/// - 相对路径只能包含普通路径段，最终位于 `default_dir` 之内
+/// - 绝对路径必须位于 `allowed_dirs` 中的某个目录之内
+/// - 目录会通过 canonicalize 解析符号链接，防止通过链接跳出允许的目录
+pub fn resolve_target(
+    requested: &str,
+    default_dir: &Path,
+    allowed_dirs: &[PathBuf],
+    format: ImageFormat,
+) -> Result<(PathBuf, String), String> {
+    let requested_path = Path::new(requested);
+
+    let name = requested_path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .map(filename::sanitize)
+        .filter(|name| !name.is_empty() && !name.starts_with('.'))
+        .ok_or_else(|| format!("无效的文件名: {}", requested))?;
+    let name = normalize_extension(&name, format);
+
+    let parent = requested_path.parent().unwrap_or(Path::new(""));
+
+    let (target_dir, roots) = if requested_path.is_absolute() {
+        let roots = std::iter::once(default_dir)
+            .chain(allowed_dirs.iter().map(PathBuf::as_path))
+            .filter_map(|dir| canonicalize_dir(dir).ok())
+            .collect::<Vec<_>>();
+        (parent.to_path_buf(), roots)
+    } else {
+        if parent
+            .components()
+            .any(|component| !matches!(component, Component::Normal(_)))
+        {
+            return Err(format!("文件名不能包含 '..' 或根目录: {}", requested));
+        }
+        let root = canonicalize_dir(default_dir).map_err(|e| format!("无法访问截图目录: {}", e))?;
+        (root.join(parent), vec![root])
+    };
+
+    if target_dir
+        .components()
+        .any(|component| matches!(component, Component::ParentDir))
+    {
+        return Err(format!("文件名不能包含 '..': {}", requested));
+    }
+
+    // 创建目录前先检查最深的已存在祖先目录，防止经由符号链接在允许范围之外创建目录
+    let ancestor_allowed = target_dir
+        .ancestors()
+        .find_map(|ancestor| ancestor.canonicalize().ok())
+        .is_some_and(|ancestor| roots.iter().any(|root| ancestor.starts_with(root)));
+    if !ancestor_allowed {
+        return Err(format!("不允许保存到该目录: {:?}", target_dir));
+    }
+
+    let target_dir = canonicalize_dir(&target_dir).map_err(|e| format!("无法创建目录: {}", e))?;
+    if !roots.iter().any(|root| target_dir.starts_with(root)) {
+        return Err(format!("不允许保存到该目录: {:?}", target_dir));
+    }
+
+    Ok((target_dir, name))
+}
+
+/// 原子写入文件：先写入同目录下的临时文件并同步到磁盘，再重命名为目标文件
+///
+/// 不覆盖时先用 `filename::create_unique` 占位，保证不会与已有文件或并发保存冲突。
+/// 返回的路径基于解析了符号链接的目录，与截图目录和历史记录中的路径写法一致。
+pub fn write_atomic(dir: &Path, name: &str, data: &[u8], overwrite: bool) -> io::Result<PathBuf> {
+    let dir = &dir.canonicalize()?;
+    let target = if overwrite {
+        dir.join(name)
+    } else {
+        filename::create_unique(dir, name, false)?.0
+    };
+
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.subsec_nanos())
+        .unwrap_or_default();
+    let tmp_path = dir.join(format!(".{}.{}.{}.tmp", name, std::process::id(), nanos));
+
+    let result = (|| {
+        let mut file = fs::File::create(&tmp_path)?;
+        file.write_all(data)?;
+        file.sync_all()?;
+        fs::rename(&tmp_path, &target)
+    })();
+
+    if let Err(e) = result {
+        let _ = fs::remove_file(&tmp_path);
+        if !overwrite {
+            let _ = fs::remove_file(&target);
+        }
+        return Err(e);
+    }
+    Ok(target)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn png_bytes() -> Vec<u8> {
+        let mut buffer = Vec::new();
+        image::RgbaImage::new(2, 2)
+            .write_to(&mut Cursor::new(&mut buffer), ImageFormat::Png)
+            .unwrap();
+        buffer
+    }
+
+    fn test_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "screenshot-storage-test-{}-{}",
+            name,
+            std::process::id()
+        ));
+        fs::create_dir_all(&dir).unwrap();
+        dir.canonicalize().unwrap()
+    }
+
+    #[test]
+    fn test_sniff_image_format() {
+        assert_eq!(sniff_image_format(&png_bytes()).unwrap(), ImageFormat::Png);
+        assert!(sniff_image_format(b"#!/bin/sh\nrm -rf ~\n").is_err());
+        // 只有文件头而没有有效内容的数据也应被拒绝
+        assert!(sniff_image_format(&png_bytes()[..12]).is_err());
+    }
+
+    #[test]
+    fn test_resolve_target_rejects_traversal() {
+        let dir = test_dir("traversal");
+        let format = ImageFormat::Png;
+
+        assert!(resolve_target("../../.bashrc", &dir, &[], format).is_err());
+        assert!(resolve_target("/etc/passwd", &dir, &[], format).is_err());
+        assert!(resolve_target("..", &dir, &[], format).is_err());
+
+        let (target_dir, name) = resolve_target("sub/shot.png", &dir, &[], format).unwrap();
+        assert_eq!(target_dir, dir.join("sub"));
+        assert_eq!(name, "shot.png");
+
+        // 扩展名与内容不符时追加正确的扩展名
+        let (_, name) = resolve_target("shot.sh", &dir, &[], format).unwrap();
+        assert_eq!(name, "shot.sh.png");
+
+        let absolute = dir.join("abs.png");
+        let (target_dir, _) =
+            resolve_target(absolute.to_str().unwrap(), &dir, &[], format).unwrap();
+        assert_eq!(target_dir, dir);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_resolve_target_rejects_symlink_escape() {
+        let dir = test_dir("symlink");
+        let outside = test_dir("symlink-outside");
+        std::os::unix::fs::symlink(&outside, dir.join("link")).unwrap();
+
+        assert!(resolve_target("link/shot.png", &dir, &[], ImageFormat::Png).is_err());
+
+        fs::remove_dir_all(&dir).unwrap();
+        fs::remove_dir_all(&outside).unwrap();
+    }
+
+    #[test]
+    fn test_write_atomic() {
+        let dir = test_dir("atomic");
+
+        let first = write_atomic(&dir, "shot.png", b"first", false).unwrap();
+        let second = write_atomic(&dir, "shot.png", b"second", false).unwrap();
+        assert_ne!(first, second);
+        assert_eq!(fs::read(&first).unwrap(), b"first");
+
+        let replaced = write_atomic(&dir, "shot.png", b"third", true).unwrap();
+        assert_eq!(replaced, first);
+        assert_eq!(fs::read(&first).unwrap(), b"third");
+
+        // 返回的路径与目录的写法无关
+        fs::create_dir_all(dir.join("sub")).unwrap();
+        let relative = write_atomic(&dir.join("sub/.."), "up.png", b"up", false).unwrap();
+        assert_eq!(relative, dir.join("up.png"));
+
+        // 不应残留临时文件
+        let leftovers = fs::read_dir(&dir)
+            .unwrap()
+            .filter(|entry| {
+                entry
+                    .as_ref()
+                    .unwrap()
+                    .file_name()
+                    .to_string_lossy()
+                    .ends_with(".tmp")
+            })
+            .count();
+        assert_eq!(leftovers, 0);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,259 @@
+use crate::history::HistoryState;
+use crate::screenshot::SavedScreenshot;
+use crate::settings;
+use crate::storage;
+use image::imageops::{self, FilterType};
+use image::ImageFormat;
+use log::{error, info, warn};
+use std::fs;
+use std::io::{self, Cursor};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use tauri::http::{header, Request, Response, StatusCode};
+use tauri::{AppHandle, Manager, UriSchemeResponder};
+
+/// 缩略图自定义协议名称
+///
+/// 前端通过 `thumb://localhost/<内容哈希>`（Windows 上为 `http://thumb.localhost/<内容哈希>`）
+/// 加载缩略图，哈希即历史记录中的 `hash` 字段。
+pub const THUMBNAIL_PROTOCOL: &str = "thumb";
+
+/// 缩略图最长边（像素）
+const THUMBNAIL_MAX_EDGE: u32 = 256;
+
+/// 获取缩略图缓存目录
+fn get_cache_dir() -> Option<PathBuf> {
+    dirs::cache_dir().map(|dir| dir.join("screenshot-app").join("thumbnails"))
+}
+
+/// 内容哈希必须是 64 位十六进制字符串，防止通过请求路径访问缓存目录之外的文件
+fn is_valid_hash(hash: &str) -> bool {
+    hash.len() == 64 && hash.bytes().all(|b| b.is_ascii_hexdigit())
+}
+
+fn thumbnail_path(cache_dir: &Path, hash: &str) -> PathBuf {
+    cache_dir.join(format!("{}.webp", hash))
+}
+
+/// 按比例缩放到最长边不超过 `max_edge`，不放大小图
+fn fit_within(width: u32, height: u32, max_edge: u32) -> (u32, u32) {
+    let longest = width.max(height);
+    if longest <= max_edge {
+        return (width.max(1), height.max(1));
+    }
+    let scale = max_edge as f64 / longest as f64;
+    (
+        ((width as f64 * scale).round() as u32).max(1),
+        ((height as f64 * scale).round() as u32).max(1),
+    )
+}
+
+/// 生成缩略图并写入缓存，已缓存时直接返回缓存路径
+pub fn generate(cache_dir: &Path, hash: &str, data: &[u8]) -> Result<PathBuf, String> {
+    let path = thumbnail_path(cache_dir, hash);
+    if path.is_file() {
+        return Ok(path);
+    }
+
+    let image = image::load_from_memory(data).map_err(|e| format!("解码截图失败: {}", e))?;
+    let (width, height) = fit_within(image.width(), image.height(), THUMBNAIL_MAX_EDGE);
+    let thumbnail = imageops::resize(&image.to_rgba8(), width, height, FilterType::Lanczos3);
+
+    let mut buffer = Vec::new();
+    thumbnail
+        .write_to(&mut Cursor::new(&mut buffer), ImageFormat::WebP)
+        .map_err(|e| format!("缩略图编码失败: {}", e))?;
+
+    fs::create_dir_all(cache_dir).map_err(|e| format!("无法创建缩略图目录: {}", e))?;
+    let name = format!("{}.webp", hash);
+    storage::write_atomic(cache_dir, &name, &buffer, true)
+        .map_err(|e| format!("保存缩略图失败: {}", e))
+}
+
+/// 按最近访问时间淘汰缩略图，直到缓存总大小不超过 `budget` 字节，返回删除的文件数
+///
+/// 访问时间以文件修改时间记录，每次通过协议读取缩略图时会刷新。
+pub fn evict(cache_dir: &Path, budget: u64) -> io::Result<usize> {
+    let mut entries = Vec::new();
+    let mut total = 0;
+    for entry in fs::read_dir(cache_dir)?.flatten() {
+        let Ok(metadata) = entry.metadata() else {
+            continue;
+        };
+        if !metadata.is_file() {
+            continue;
+        }
+        total += metadata.len();
+        let modified = metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+        entries.push((modified, metadata.len(), entry.path()));
+    }
+    if total <= budget {
+        return Ok(0);
+    }
+
+    entries.sort_by_key(|(modified, _, _)| *modified);
+    let mut removed = 0;
+    for (_, size, path) in entries {
+        if total <= budget {
+            break;
+        }
+        if fs::remove_file(&path).is_ok() {
+            total -= size;
+            removed += 1;
+        }
+    }
+    Ok(removed)
+}
+
+/// 刷新缩略图的访问时间
+fn touch(path: &Path) {
+    if let Err(e) = fs::File::options()
+        .write(true)
+        .open(path)
+        .and_then(|file| file.set_modified(SystemTime::now()))
+    {
+        warn!("更新缩略图访问时间失败: {:?}, {}", path, e);
+    }
+}
+
+fn cache_budget(app: &AppHandle) -> u64 {
+    settings::current(app).thumbnail_cache_mb * 1024 * 1024
+}
+
+/// 为截图文件生成缩略图并按缓存预算淘汰旧缩略图
+fn generate_for_file(app: &AppHandle, cache_dir: &Path, path: &Path) -> Result<PathBuf, String> {
+    let data = fs::read(path).map_err(|e| format!("读取截图文件失败: {:?}, {}", path, e))?;
+    let hash = storage::content_hash(&data);
+    let thumbnail = generate(cache_dir, &hash, &data)?;
+
+    match evict(cache_dir, cache_budget(app)) {
+        Ok(0) => {}
+        Ok(removed) => info!("缩略图缓存超出预算，已淘汰 {} 个文件", removed),
+        Err(e) => warn!("淘汰缩略图缓存失败: {}", e),
+    }
+    Ok(thumbnail)
+}
+
+/// 获取截图文件的缩略图，缓存中已有时直接返回
+pub fn thumbnail_for_file(app: &AppHandle, path: &Path) -> Result<PathBuf, String> {
+    let cache_dir = get_cache_dir().ok_or("无法获取缓存目录".to_string())?;
+    generate_for_file(app, &cache_dir, path)
+}
+
+/// 在 rayon 线程池中为刚保存的截图生成缩略图，由 `screenshot::announce_saved` 调用
+pub fn generate_saved(app: &AppHandle, saved: &SavedScreenshot) {
+    let Some(cache_dir) = get_cache_dir() else {
+        warn!("无法获取缓存目录，缩略图不可用");
+        return;
+    };
+    let app = app.clone();
+    let path = saved.path.clone();
+    rayon::spawn(move || {
+        if let Err(e) = generate_for_file(&app, &cache_dir, Path::new(&path)) {
+            error!("生成缩略图失败: {}", e);
+        }
+    });
+}
+
+fn respond_status(responder: UriSchemeResponder, status: StatusCode) {
+    responder.respond(Response::builder().status(status).body(Vec::new()).unwrap());
+}
+
+/// 处理 `thumb://` 协议请求，缓存中没有时从历史记录找到原图即时生成
+pub fn handle_request(app: &AppHandle, request: Request<Vec<u8>>, responder: UriSchemeResponder) {
+    let hash = request
+        .uri()
+        .path()
+        .trim_start_matches('/')
+        .trim_end_matches(".webp")
+        .to_ascii_lowercase();
+    if !is_valid_hash(&hash) {
+        respond_status(responder, StatusCode::BAD_REQUEST);
+        return;
+    }
+    let Some(cache_dir) = get_cache_dir() else {
+        respond_status(responder, StatusCode::INTERNAL_SERVER_ERROR);
+        return;
+    };
+
+    let app = app.clone();
+    rayon::spawn(move || {
+        let mut path = thumbnail_path(&cache_dir, &hash);
+        if !path.is_file() {
+            let source = app
+                .try_state::<HistoryState>()
+                .and_then(|history| history.path_for_hash(&hash).ok().flatten());
+            let Some(source) = source else {
+                respond_status(responder, StatusCode::NOT_FOUND);
+                return;
+            };
+            match generate_for_file(&app, &cache_dir, Path::new(&source)) {
+                Ok(generated) => path = generated,
+                Err(e) => {
+                    error!("生成缩略图失败: {}", e);
+                    respond_status(responder, StatusCode::NOT_FOUND);
+                    return;
+                }
+            }
+            // 原图在记录之后被修改过，生成的缩略图与请求的哈希不对应
+            if path != thumbnail_path(&cache_dir, &hash) {
+                respond_status(responder, StatusCode::NOT_FOUND);
+                return;
+            }
+        }
+
+        match fs::read(&path) {
+            Ok(data) => {
+                touch(&path);
+                responder.respond(
+                    Response::builder()
+                        .header(header::CONTENT_TYPE, "image/webp")
+                        .header(header::CACHE_CONTROL, "max-age=31536000, immutable")
+                        .body(data)
+                        .unwrap(),
+                );
+            }
+            Err(e) => {
+                error!("读取缩略图失败: {:?}, {}", path, e);
+                respond_status(responder, StatusCode::NOT_FOUND);
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fit_within() {
+        assert_eq!(fit_within(1920, 1080, 256), (256, 144));
+        assert_eq!(fit_within(1080, 1920, 256), (144, 256));
+        assert_eq!(fit_within(100, 50, 256), (100, 50));
+        assert_eq!(fit_within(10000, 1, 256), (256, 1));
+    }
+
+    #[test]
+    fn test_generate_and_evict() {
+        let dir =
+            std::env::temp_dir().join(format!("screenshot-thumbnail-test-{}", std::process::id()));
+
+        let mut png = Vec::new();
+        image::RgbaImage::new(640, 480)
+            .write_to(&mut Cursor::new(&mut png), ImageFormat::Png)
+            .unwrap();
+        let hash = storage::content_hash(&png);
+
+        let path = generate(&dir, &hash, &png).unwrap();
+        assert_eq!(path, thumbnail_path(&dir, &hash));
+        let thumbnail = image::open(&path).unwrap();
+        assert_eq!((thumbnail.width(), thumbnail.height()), (256, 192));
+
+        assert!(!is_valid_hash("../../etc/passwd"));
+        assert_eq!(evict(&dir, u64::MAX).unwrap(), 0);
+        assert_eq!(evict(&dir, 0).unwrap(), 1);
+        assert!(!path.exists());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,216 @@
+use crate::actions::CaptureMode;
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::command;
+
+/// 内存中保留的最近截图耗时记录数
+const MAX_SAMPLES: usize = 1000;
+
+/// 最近的截图耗时记录，最新的在末尾
+static SAMPLES: Mutex<VecDeque<CaptureTimings>> = Mutex::new(VecDeque::new());
+
+/// 一次截图各阶段的耗时（毫秒），随截图结果一起返回
+///
+/// 编码阶段包括 RGBA 转换和 Base64，它们在编码流水线中同时进行，无法单独计时。
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CaptureTimings {
+    pub mode: CaptureMode,
+    /// 枚举显示器或窗口
+    pub enumerate_ms: f64,
+    pub capture_ms: f64,
+    pub encode_ms: f64,
+    pub total_ms: f64,
+    #[serde(skip)]
+    start: Option<Instant>,
+}
+
+fn millis(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+impl CaptureTimings {
+    /// 开始计时
+    pub fn start(mode: CaptureMode) -> Self {
+        Self {
+            mode,
+            enumerate_ms: 0.0,
+            capture_ms: 0.0,
+            encode_ms: 0.0,
+            total_ms: 0.0,
+            start: Some(Instant::now()),
+        }
+    }
+
+    pub fn set_enumerate(&mut self, elapsed: Duration) {
+        self.enumerate_ms = millis(elapsed);
+    }
+
+    pub fn set_capture(&mut self, elapsed: Duration) {
+        self.capture_ms = millis(elapsed);
+    }
+
+    pub fn set_encode(&mut self, elapsed: Duration) {
+        self.encode_ms = millis(elapsed);
+    }
+
+    /// 结束计时并加入统计，返回最终的耗时记录
+    pub fn finish(mut self) -> Self {
+        if let Some(start) = self.start.take() {
+            self.total_ms = millis(start.elapsed());
+        }
+        record(self.clone());
+        self
+    }
+
+    fn stage(&self, stage: Stage) -> f64 {
+        match stage {
+            Stage::Enumerate => self.enumerate_ms,
+            Stage::Capture => self.capture_ms,
+            Stage::Encode => self.encode_ms,
+            Stage::Total => self.total_ms,
+        }
+    }
+}
+
+fn record(timings: CaptureTimings) {
+    let mut samples = SAMPLES.lock().unwrap();
+    if samples.len() == MAX_SAMPLES {
+        samples.pop_front();
+    }
+    samples.push_back(timings);
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Stage {
+    Enumerate,
+    Capture,
+    Encode,
+    Total,
+}
+
+impl Stage {
+    const ALL: [Stage; 4] = [
+        Stage::Enumerate,
+        Stage::Capture,
+        Stage::Encode,
+        Stage::Total,
+    ];
+}
+
+/// 单个阶段的耗时分布（毫秒）
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct StageStats {
+    pub stage: Stage,
+    pub p50_ms: f64,
+    pub p95_ms: f64,
+    pub max_ms: f64,
+}
+
+/// 某种截图方式的耗时统计
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ModeStats {
+    pub mode: CaptureMode,
+    pub count: usize,
+    pub stages: Vec<StageStats>,
+}
+
+/// `get_capture_stats` 的返回值
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CaptureStats {
+    pub count: usize,
+    pub modes: Vec<ModeStats>,
+    /// 最近一次截图的耗时
+    pub last: Option<CaptureTimings>,
+}
+
+/// 最近邻秩法计算百分位数，`sorted` 需要已按升序排列
+fn percentile(sorted: &[f64], percent: f64) -> f64 {
+    if sorted.is_empty() {
+        return 0.0;
+    }
+    let rank = (percent / 100.0 * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+fn summarize(samples: &VecDeque<CaptureTimings>) -> CaptureStats {
+    let modes = [
+        CaptureMode::FullScreen,
+        CaptureMode::Region,
+        CaptureMode::ActiveWindow,
+    ]
+    .into_iter()
+    .filter_map(|mode| {
+        let samples: Vec<&CaptureTimings> = samples
+            .iter()
+            .filter(|timings| timings.mode == mode)
+            .collect();
+        if samples.is_empty() {
+            return None;
+        }
+        let stages = Stage::ALL
+            .into_iter()
+            .map(|stage| {
+                let mut values: Vec<f64> = samples.iter().map(|t| t.stage(stage)).collect();
+                values.sort_by(f64::total_cmp);
+                StageStats {
+                    stage,
+                    p50_ms: percentile(&values, 50.0),
+                    p95_ms: percentile(&values, 95.0),
+                    max_ms: values.last().copied().unwrap_or_default(),
+                }
+            })
+            .collect();
+        Some(ModeStats {
+            mode,
+            count: samples.len(),
+            stages,
+        })
+    })
+    .collect();
+
+    CaptureStats {
+        count: samples.len(),
+        modes,
+        last: samples.back().cloned(),
+    }
+}
+
+/// 获取本次运行中最近截图的耗时统计（按截图方式分别计算 p50/p95）
+#[command]
+pub fn get_capture_stats() -> CaptureStats {
+    summarize(&SAMPLES.lock().unwrap())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_percentile_and_summarize() {
+        let sorted: Vec<f64> = (1..=20).map(f64::from).collect();
+        assert_eq!(percentile(&sorted, 50.0), 10.0);
+        assert_eq!(percentile(&sorted, 95.0), 19.0);
+        assert_eq!(percentile(&[], 95.0), 0.0);
+
+        let samples: VecDeque<CaptureTimings> = (1..=20)
+            .map(|i| CaptureTimings {
+                capture_ms: f64::from(i),
+                total_ms: f64::from(i) * 2.0,
+                ..CaptureTimings::start(CaptureMode::Region)
+            })
+            .collect();
+        let stats = summarize(&samples);
+        assert_eq!(stats.count, 20);
+        assert_eq!(stats.modes.len(), 1);
+        let capture = &stats.modes[0].stages[1];
+        assert_eq!(capture.stage, Stage::Capture);
+        assert_eq!(
+            (capture.p50_ms, capture.p95_ms, capture.max_ms),
+            (10.0, 19.0, 20.0)
+        );
+        assert_eq!(stats.last.unwrap().total_ms, 40.0);
+    }
+}
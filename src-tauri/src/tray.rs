@@ -0,0 +1,279 @@
+use crate::history::HistoryState;
+use crate::hotkeys::{self, HotkeyAction};
+use crate::screenshot::{self, SavedScreenshot};
+use crate::settings;
+use log::{error, info, warn};
+use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::menu::{Menu, MenuEvent, MenuItem, PredefinedMenuItem, Submenu};
+use tauri::tray::TrayIconBuilder;
+use tauri::{AppHandle, Manager, Wry};
+use tauri_plugin_opener::OpenerExt;
+
+const TRAY_ID: &str = "main";
+
+/// 托盘菜单中保留的最近截图数量
+const RECENT_CAPTURES_LIMIT: usize = 10;
+
+/// 托盘菜单提供的延时截图选项（秒）
+const DELAY_PRESETS: [u64; 3] = [3, 5, 10];
+
+/// 最近截图菜单中的一项，`id` 在程序运行期间不变，菜单项通过它找到对应的文件
+#[derive(Clone)]
+struct RecentCapture {
+    id: u64,
+    path: PathBuf,
+}
+
+/// 最近保存的截图，最新的在最前面
+#[derive(Default)]
+struct RecentCaptures {
+    captures: Mutex<VecDeque<RecentCapture>>,
+    next_id: AtomicU64,
+}
+
+/// 创建托盘图标，并在每次保存截图后刷新“最近截图”子菜单
+pub fn init(app: &AppHandle) -> tauri::Result<()> {
+    app.manage(RecentCaptures::default());
+
+    let menu = build_menu(app, &[])?;
+    let mut builder = TrayIconBuilder::with_id(TRAY_ID)
+        .menu(&menu)
+        .tooltip("screenshot")
+        .on_menu_event(handle_menu_event);
+    if let Some(icon) = app.default_window_icon() {
+        builder = builder.icon(icon.clone());
+    }
+    builder.build(app)?;
+
+    info!("托盘图标创建完成");
+    Ok(())
+}
+
+/// 把刚保存的截图加入最近截图菜单，由 `screenshot::announce_saved` 调用
+pub fn record_capture(app: &AppHandle, saved: &SavedScreenshot) {
+    let Some(recent) = app.try_state::<RecentCaptures>() else {
+        return;
+    };
+    let path = PathBuf::from(&saved.path);
+    {
+        let id = recent.next_id.fetch_add(1, Ordering::Relaxed);
+        let mut captures = recent.captures.lock().unwrap();
+        captures.retain(|existing| existing.path != path);
+        captures.push_front(RecentCapture { id, path });
+        captures.truncate(RECENT_CAPTURES_LIMIT);
+    }
+    refresh_menu(app);
+}
+
+/// 根据最近截图列表重建托盘菜单
+fn refresh_menu(app: &AppHandle) {
+    let recent: Vec<RecentCapture> = app
+        .state::<RecentCaptures>()
+        .captures
+        .lock()
+        .unwrap()
+        .iter()
+        .cloned()
+        .collect();
+
+    let Some(tray) = app.tray_by_id(TRAY_ID) else {
+        warn!("未找到托盘图标，无法刷新菜单");
+        return;
+    };
+    match build_menu(app, &recent) {
+        Ok(menu) => {
+            if let Err(e) = tray.set_menu(Some(menu)) {
+                error!("设置托盘菜单失败: {}", e);
+            }
+        }
+        Err(e) => error!("创建托盘菜单失败: {}", e),
+    }
+}
+
+fn build_menu(app: &AppHandle, recent: &[RecentCapture]) -> tauri::Result<Menu<Wry>> {
+    let full_screen = MenuItem::with_id(app, "full_screen", "全屏截图", true, None::<&str>)?;
+    let region = MenuItem::with_id(app, "region", "区域截图", true, None::<&str>)?;
+    let active_window =
+        MenuItem::with_id(app, "active_window", "活动窗口截图", true, None::<&str>)?;
+    let repeat_last_region = MenuItem::with_id(
+        app,
+        "repeat_last_region",
+        "重复上次区域截图",
+        true,
+        None::<&str>,
+    )?;
+
+    let delay = Submenu::new(app, "延时全屏截图", true)?;
+    for seconds in DELAY_PRESETS {
+        delay.append(&MenuItem::with_id(
+            app,
+            format!("delay:{}", seconds),
+            format!("{} 秒后", seconds),
+            true,
+            None::<&str>,
+        )?)?;
+    }
+
+    let recent_menu = Submenu::new(app, "最近截图", !recent.is_empty())?;
+    for RecentCapture { id, path } in recent {
+        let name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_else(|| path.to_string_lossy().to_string());
+        recent_menu.append(&Submenu::with_items(
+            app,
+            name,
+            true,
+            &[
+                &MenuItem::with_id(
+                    app,
+                    format!("recent:copy:{}", id),
+                    "再次复制",
+                    true,
+                    None::<&str>,
+                )?,
+                &MenuItem::with_id(
+                    app,
+                    format!("recent:reveal:{}", id),
+                    "在文件夹中显示",
+                    true,
+                    None::<&str>,
+                )?,
+                &MenuItem::with_id(
+                    app,
+                    format!("recent:delete:{}", id),
+                    "删除",
+                    true,
+                    None::<&str>,
+                )?,
+            ],
+        )?)?;
+    }
+
+    let open_folder = MenuItem::with_id(app, "open_folder", "打开截图文件夹", true, None::<&str>)?;
+    let quit = MenuItem::with_id(app, "quit", "退出", true, None::<&str>)?;
+
+    Menu::with_items(
+        app,
+        &[
+            &full_screen,
+            &region,
+            &active_window,
+            &repeat_last_region,
+            &delay,
+            &PredefinedMenuItem::separator(app)?,
+            &open_folder,
+            &recent_menu,
+            &PredefinedMenuItem::separator(app)?,
+            &quit,
+        ],
+    )
+}
+
+fn handle_menu_event(app: &AppHandle, event: MenuEvent) {
+    let id = event.id().as_ref();
+    info!("托盘菜单点击: {}", id);
+
+    match id {
+        "full_screen" => hotkeys::trigger(app, HotkeyAction::FullScreen),
+        "region" => hotkeys::trigger(app, HotkeyAction::Region),
+        "active_window" => hotkeys::trigger(app, HotkeyAction::ActiveWindow),
+        "repeat_last_region" => hotkeys::trigger(app, HotkeyAction::RepeatLastRegion),
+        "open_folder" => open_screenshots_folder(app),
+        "quit" => app.exit(0),
+        _ => {
+            if let Some(seconds) = id.strip_prefix("delay:") {
+                if let Ok(seconds) = seconds.parse::<u64>() {
+                    let app = app.clone();
+                    std::thread::spawn(move || {
+                        info!("{} 秒后开始全屏截图", seconds);
+                        std::thread::sleep(Duration::from_secs(seconds));
+                        hotkeys::trigger(&app, HotkeyAction::FullScreen);
+                    });
+                }
+            } else if let Some(rest) = id.strip_prefix("recent:") {
+                handle_recent_event(app, rest);
+            } else {
+                warn!("未知的托盘菜单项: {}", id);
+            }
+        }
+    }
+}
+
+/// 处理“最近截图”子菜单，`rest` 的格式为 `<操作>:<编号>`
+///
+/// 菜单可能在点击前已经刷新，按编号而不是位置查找，找不到时不做任何操作。
+fn handle_recent_event(app: &AppHandle, rest: &str) {
+    let Some((operation, id)) = rest.split_once(':') else {
+        return;
+    };
+    let Ok(id) = id.parse::<u64>() else {
+        return;
+    };
+    let Some(path) = app
+        .state::<RecentCaptures>()
+        .captures
+        .lock()
+        .unwrap()
+        .iter()
+        .find(|capture| capture.id == id)
+        .map(|capture| capture.path.clone())
+    else {
+        warn!("最近截图已不在列表中: {}", id);
+        return;
+    };
+
+    match operation {
+        "copy" => {
+            if let Err(e) = screenshot::copy_image_file_to_clipboard(&path) {
+                error!("再次复制截图失败: {}", e);
+            }
+        }
+        "reveal" => {
+            if let Err(e) = app.opener().reveal_item_in_dir(&path) {
+                error!("在文件夹中显示截图失败: {}", e);
+            }
+        }
+        "delete" => delete_screenshot(app, &path),
+        _ => warn!("未知的最近截图操作: {}", operation),
+    }
+}
+
+/// 删除截图文件，同时移除历史记录和最近截图菜单中的条目（通知中的“删除”也使用此入口）
+///
+/// 只删除截图目录和允许的保存目录中的普通文件，其他情况只清理记录。
+pub fn delete_screenshot(app: &AppHandle, path: &Path) {
+    if let Err(e) = screenshot::remove_saved_file(&settings::current(app), path) {
+        warn!("未删除截图 {:?}: {}", path, e);
+    }
+    if let Some(history) = app.try_state::<HistoryState>() {
+        if let Err(e) = history.delete_by_path(&path.to_string_lossy()) {
+            error!("删除截图历史失败: {}", e);
+        }
+    }
+    if let Some(recent) = app.try_state::<RecentCaptures>() {
+        recent
+            .captures
+            .lock()
+            .unwrap()
+            .retain(|existing| existing.path != path);
+        refresh_menu(app);
+    }
+}
+
+fn open_screenshots_folder(app: &AppHandle) {
+    let dir = match screenshot::get_screenshots_dir(&settings::current(app)) {
+        Ok(dir) => dir,
+        Err(e) => {
+            error!("获取截图目录失败: {}", e);
+            return;
+        }
+    };
+    if let Err(e) = app.opener().open_path(dir.to_string_lossy(), None::<&str>) {
+        error!("打开截图文件夹失败: {}", e);
+    }
+}
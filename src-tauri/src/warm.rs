@@ -0,0 +1,275 @@
+use crate::crop;
+use crate::monitors::{self, MonitorInfo};
+use image::RgbaImage;
+use log::{error, info, warn};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+/// 预热模式下保留的空闲帧缓冲数量，优先保留最大的缓冲
+const MAX_SPARE_BUFFERS: usize = 2;
+
+/// 读取屏幕像素的后端
+pub trait Grabber: Send {
+    /// 读取虚拟屏幕坐标下的矩形，以 RGBA 写入 `out`（长度为 width * height * 4）
+    fn grab(
+        &mut self,
+        x: i32,
+        y: i32,
+        width: u32,
+        height: u32,
+        out: &mut [u8],
+    ) -> Result<(), String>;
+}
+
+impl<G: Grabber + ?Sized> Grabber for Box<G> {
+    fn grab(
+        &mut self,
+        x: i32,
+        y: i32,
+        width: u32,
+        height: u32,
+        out: &mut [u8],
+    ) -> Result<(), String> {
+        (**self).grab(x, y, width, height, out)
+    }
+}
+
+/// 保持后端连接打开并复用帧缓冲的截图器
+pub struct WarmCapture<G> {
+    grabber: G,
+    spare: Vec<Vec<u8>>,
+}
+
+impl<G: Grabber> WarmCapture<G> {
+    pub fn new(grabber: G) -> Self {
+        Self {
+            grabber,
+            spare: Vec::new(),
+        }
+    }
+
+    /// 截取虚拟屏幕坐标下的矩形
+    pub fn capture(
+        &mut self,
+        x: i32,
+        y: i32,
+        width: u32,
+        height: u32,
+    ) -> Result<RgbaImage, String> {
+        let len = width as usize * height as usize * 4;
+        // 优先使用容量足够的空闲缓冲，避免重新分配和缺页
+        let mut buffer = match self.spare.iter().position(|spare| spare.capacity() >= len) {
+            Some(index) => self.spare.swap_remove(index),
+            None => Vec::with_capacity(len),
+        };
+        buffer.resize(len, 0);
+
+        if let Err(e) = self.grabber.grab(x, y, width, height, &mut buffer) {
+            self.spare.push(buffer);
+            return Err(e);
+        }
+        RgbaImage::from_raw(width, height, buffer).ok_or("帧缓冲大小不正确".to_string())
+    }
+
+    /// 归还不再使用的截图，缓冲留给之后的截图
+    pub fn recycle(&mut self, image: RgbaImage) {
+        self.spare.push(image.into_raw());
+        self.spare
+            .sort_by_key(|buffer| std::cmp::Reverse(buffer.capacity()));
+        self.spare.truncate(MAX_SPARE_BUFFERS);
+    }
+}
+
+/// 测试和基准测试使用的假后端，从内存中的画面读取像素
+#[derive(Clone)]
+pub struct FakeGrabber {
+    width: u32,
+    height: u32,
+    screen: Arc<Vec<u8>>,
+}
+
+impl FakeGrabber {
+    /// 生成一张渐变画面作为屏幕内容
+    pub fn new(width: u32, height: u32) -> Self {
+        let screen = (0..height)
+            .flat_map(|y| (0..width).flat_map(move |x| [x as u8, y as u8, (x ^ y) as u8, 255]))
+            .collect();
+        Self {
+            width,
+            height,
+            screen: Arc::new(screen),
+        }
+    }
+}
+
+impl Grabber for FakeGrabber {
+    fn grab(
+        &mut self,
+        x: i32,
+        y: i32,
+        width: u32,
+        height: u32,
+        out: &mut [u8],
+    ) -> Result<(), String> {
+        let (x, y) = match (u32::try_from(x), u32::try_from(y)) {
+            (Ok(x), Ok(y)) => (x, y),
+            _ => return Err("区域超出屏幕范围".to_string()),
+        };
+        crop::check_bounds(self.width, self.height, x, y, width, height)?;
+
+        let stride = self.width as usize * 4;
+        let row_len = width as usize * 4;
+        for (row, out_row) in out.chunks_exact_mut(row_len).enumerate() {
+            let start = (y as usize + row) * stride + x as usize * 4;
+            out_row.copy_from_slice(&self.screen[start..start + row_len]);
+        }
+        Ok(())
+    }
+}
+
+/// 打开当前平台支持预热的截图后端
+#[cfg(all(unix, not(target_os = "macos")))]
+pub fn connect_native() -> Result<Box<dyn Grabber>, String> {
+    crate::capture::x11::WarmConnection::open().map(|conn| Box::new(conn) as Box<dyn Grabber>)
+}
+
+/// 打开当前平台支持预热的截图后端
+#[cfg(not(all(unix, not(target_os = "macos"))))]
+pub fn connect_native() -> Result<Box<dyn Grabber>, String> {
+    Err("当前平台不支持预热截图".to_string())
+}
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// 每次关闭预热或显示器布局变化时加一，之前开始的预热结果作废
+static GENERATION: AtomicU64 = AtomicU64::new(0);
+
+/// 预热的截图器，未开启或后端不可用时为 `None`
+static WARM: Mutex<Option<WarmCapture<Box<dyn Grabber>>>> = Mutex::new(None);
+
+/// 开启或关闭预热模式，开启时在后台建立连接并预分配帧缓冲
+pub fn configure(enabled: bool) {
+    let was_enabled = ENABLED.swap(enabled, Ordering::SeqCst);
+    if enabled && !was_enabled {
+        thread::spawn(warm_up);
+    } else if !enabled && was_enabled {
+        GENERATION.fetch_add(1, Ordering::SeqCst);
+        if WARM.lock().unwrap().take().is_some() {
+            info!("已关闭预热截图，释放连接和帧缓冲");
+        }
+    }
+}
+
+/// 显示器布局变化后丢弃连接和缓冲，重新预热
+pub fn invalidate() {
+    GENERATION.fetch_add(1, Ordering::SeqCst);
+    if WARM.lock().unwrap().take().is_some() {
+        info!("显示器布局已变化，重新预热截图后端");
+    }
+    if ENABLED.load(Ordering::SeqCst) {
+        thread::spawn(warm_up);
+    }
+}
+
+/// 在锁外建立连接并预热，完成后放入 `WARM`，期间截图照常使用普通后端
+fn warm_up() {
+    let generation = GENERATION.load(Ordering::SeqCst);
+    if !ENABLED.load(Ordering::SeqCst) || WARM.lock().unwrap().is_some() {
+        return;
+    }
+
+    let grabber = match connect_native() {
+        Ok(grabber) => grabber,
+        Err(e) => {
+            info!("预热截图不可用，使用普通截图: {}", e);
+            return;
+        }
+    };
+    let mut capture = WarmCapture::new(grabber);
+
+    // 先截取一次主显示器，连接、共享内存和帧缓冲都在第一次截图前准备好
+    match monitors::registry().primary() {
+        Ok(monitor) => match capture.capture(monitor.x, monitor.y, monitor.width, monitor.height) {
+            Ok(image) => capture.recycle(image),
+            Err(e) => warn!("预热截图失败: {}", e),
+        },
+        Err(e) => warn!("预热时获取主显示器失败: {}", e),
+    }
+
+    let mut warm = WARM.lock().unwrap();
+    // 预热期间关闭了预热或显示器布局变化，这次的结果已经过时，由之后的预热重新准备
+    if GENERATION.load(Ordering::SeqCst) != generation || !ENABLED.load(Ordering::SeqCst) {
+        return;
+    }
+    // 同时有多个预热时只保留先完成的一个
+    if warm.is_none() {
+        *warm = Some(capture);
+        info!("预热截图已就绪");
+    }
+}
+
+/// 用预热的后端截取显示器上的区域，坐标是相对于显示器左上角的物理像素
+///
+/// 预热模式未开启或后端不可用时返回 `Ok(None)`，由调用方使用普通截图。
+pub fn capture(
+    monitor: &MonitorInfo,
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
+) -> Result<Option<RgbaImage>, String> {
+    if !ENABLED.load(Ordering::SeqCst) {
+        return Ok(None);
+    }
+    crop::check_bounds(monitor.width, monitor.height, x, y, width, height)?;
+
+    let mut warm = WARM.lock().unwrap();
+    let Some(capture) = warm.as_mut() else {
+        return Ok(None);
+    };
+    match capture.capture(
+        monitor.x.saturating_add_unsigned(x),
+        monitor.y.saturating_add_unsigned(y),
+        width,
+        height,
+    ) {
+        Ok(image) => Ok(Some(image)),
+        Err(e) => {
+            // 连接可能已经断开，丢弃后重新预热，这次使用普通截图
+            error!("预热截图失败，重新建立连接: {}", e);
+            *warm = None;
+            drop(warm);
+            thread::spawn(warm_up);
+            Ok(None)
+        }
+    }
+}
+
+/// 归还不再使用的截图，预热模式下复用它的缓冲
+pub fn recycle(image: RgbaImage) {
+    if let Some(capture) = WARM.lock().unwrap().as_mut() {
+        capture.recycle(image);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_warm_capture_reuses_buffers() {
+        let mut capture = WarmCapture::new(FakeGrabber::new(64, 32));
+
+        let image = capture.capture(0, 0, 64, 32).unwrap();
+        let ptr = image.as_raw().as_ptr();
+        capture.recycle(image);
+
+        let region = capture.capture(10, 4, 8, 2).unwrap();
+        assert_eq!(region.as_raw().as_ptr(), ptr);
+        assert_eq!(region.get_pixel(0, 0).0, [10, 4, 10 ^ 4, 255]);
+        assert_eq!(region.get_pixel(7, 1).0, [17, 5, 17 ^ 5, 255]);
+
+        assert!(capture.capture(60, 0, 8, 2).is_err());
+    }
+}